@@ -0,0 +1,468 @@
+/*
+Benchmarks without a window: `cargo run --release -- bench --examples ex01,ex07 --out base.json`,
+then after a change `cargo run --release -- bench compare base.json bench-<timestamp>.json` says what got slower.
+
+Each example renders offscreen (see `headless.rs`), without vsync or the runner's 60 fps cap:
+first warm-up frames (pipelines, caches, lazily made resources), then the measured ones.
+Per measured frame:
+    - cpu: time spent in the example's `render`, i.e. encoding and submitting
+    - gpu: between timestamps written before and after the example's submissions, if the adapter has `TIMESTAMP_QUERY`
+    - frame: from the end of the previous frame to the end of this one, including waiting for the GPU
+Results (min, median, p95 and p99 in milliseconds) go to `bench-<timestamp>.json`, or `--out <path>`.
+
+Things we learned:
+    - Timestamps written by the command encoder between submissions also count the GPU idling in between,
+        e.g. while the example encodes. So gpu is an upper bound, and includes the submissions' overhead.
+    - Waiting for every frame serializes the CPU and the GPU, frames are slower than in the windowed zoo.
+        But then a frame time is one frame's work, not however many frames are queued up.
+    - Debug builds mostly measure wgpu's and our own debug code, use `--release`.
+ */
+use std::{path::Path, time::Duration, time::Instant};
+
+use serde::{Deserialize, Serialize};
+use wgpu::{
+    AdapterInfo, Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Features,
+    Maintain, MapMode, QuerySet, QuerySetDescriptor, QueryType,
+};
+
+use crate::{headless, registry::Registry, ExampleData};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub min: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+// Nearest rank, of ascending `sorted`
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100. * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl Stats {
+    // None without samples
+    pub fn new(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len();
+        Some(Self {
+            min: sorted[0],
+            median: (sorted[(n - 1) / 2] + sorted[n / 2]) / 2.,
+            p95: percentile(&sorted, 95.),
+            p99: percentile(&sorted, 99.),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExampleResult {
+    pub name: String,
+    // Why it stopped rendering, then there are no timings
+    #[serde(default)]
+    pub fault: Option<String>,
+    pub cpu_ms: Option<Stats>,
+    // None without timestamp queries
+    pub gpu_ms: Option<Stats>,
+    pub frame_ms: Option<Stats>,
+}
+
+impl ExampleResult {
+    fn metrics(&self) -> [(&'static str, Option<Stats>); 3] {
+        [
+            ("cpu", self.cpu_ms),
+            ("gpu", self.gpu_ms),
+            ("frame", self.frame_ms),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    // Comparing across adapters isn't very meaningful, but possible
+    pub adapter: String,
+    pub backend: String,
+    pub size: [u32; 2],
+    pub warmup: u32,
+    pub frames: u32,
+    pub examples: Vec<ExampleResult>,
+}
+
+impl Report {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("{path:?}: {err}"))?;
+        serde_json::from_str(&text).map_err(|err| format!("{path:?}: {err}"))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        std::fs::write(path, text + "\n").map_err(|err| format!("{path:?}: {err}"))
+    }
+}
+
+pub struct Settings {
+    pub warmup: u32,
+    pub frames: u32,
+}
+
+// Like example 09's, but a frame's work is spread over the example's submissions
+struct Timestamps {
+    query_set: QuerySet,
+    resolve_buf: Buffer,
+    read_buf: Buffer,
+}
+
+impl Timestamps {
+    fn new(e: &ExampleData) -> Option<Self> {
+        if !e.device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let resolve_buf = e.device.create_buffer(&BufferDescriptor {
+            label: "bench-query-resolve".into(),
+            size: 2 * std::mem::size_of::<u64>() as u64,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set: e.device.create_query_set(&QuerySetDescriptor {
+                label: "bench-queries".into(),
+                ty: QueryType::Timestamp,
+                count: 2,
+            }),
+            read_buf: e.device.create_buffer(&BufferDescriptor {
+                label: "bench-query-read".into(),
+                size: resolve_buf.size(),
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            resolve_buf,
+        })
+    }
+
+    // Submitted before the example's submissions
+    fn begin(&self, e: &ExampleData) {
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "bench-begin-ce".into(),
+        });
+        ce.write_timestamp(&self.query_set, 0);
+        e.queue.submit(std::iter::once(ce.finish()));
+    }
+
+    // Submitted after them
+    fn end(&self, e: &ExampleData) {
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "bench-end-ce".into(),
+        });
+        ce.write_timestamp(&self.query_set, 1);
+        ce.resolve_query_set(&self.query_set, 0..2, &self.resolve_buf, 0);
+        ce.copy_buffer_to_buffer(
+            &self.resolve_buf,
+            0,
+            &self.read_buf,
+            0,
+            self.read_buf.size(),
+        );
+        e.queue.submit(std::iter::once(ce.finish()));
+    }
+
+    // In milliseconds, once the frame is done
+    fn read(&self, e: &ExampleData) -> f64 {
+        let slice = self.read_buf.slice(..);
+        slice.map_async(MapMode::Read, |_| {});
+        e.device.poll(Maintain::Wait);
+        let ticks = {
+            let data = slice.get_mapped_range();
+            let ts: &[u64] = bytemuck::cast_slice(&data);
+            ts[1].saturating_sub(ts[0])
+        };
+        self.read_buf.unmap();
+
+        ticks as f64 * e.queue.get_timestamp_period() as f64 / 1e6
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e3
+}
+
+// Benchmark the examples at `indices` one after the other
+pub fn run(
+    e: &ExampleData,
+    adapter: &AdapterInfo,
+    examples: &mut Registry,
+    indices: &[usize],
+    settings: Settings,
+) -> Report {
+    let (_texture, view) = headless::target(e);
+    let timestamps = Timestamps::new(e);
+    if timestamps.is_none() {
+        println!("No timestamp query support, only CPU and frame timings are available");
+    }
+
+    let mut results = vec![];
+    for &index in indices {
+        println!(
+            "Benchmarking {}: {} warm-up and {} measured frames",
+            examples.name(index),
+            settings.warmup,
+            settings.frames
+        );
+        examples.set_active(&[index], e);
+
+        let (mut cpu, mut gpu, mut frame) = (vec![], vec![], vec![]);
+        let mut last = Instant::now();
+        for i in 0..settings.warmup + settings.frames {
+            if let Some(timestamps) = &timestamps {
+                timestamps.begin(e);
+            }
+            let start = Instant::now();
+            headless::render_frame(examples, index, e, &view);
+            let encoded = start.elapsed();
+            if let Some(timestamps) = &timestamps {
+                timestamps.end(e);
+            }
+            e.device.poll(Maintain::Wait);
+            let now = Instant::now();
+
+            if examples.fault(index).is_some() {
+                break;
+            }
+            if i >= settings.warmup {
+                cpu.push(ms(encoded));
+                frame.push(ms(now - last));
+                if let Some(timestamps) = &timestamps {
+                    gpu.push(timestamps.read(e));
+                }
+            }
+            last = now;
+        }
+
+        let fault = examples.fault(index).map(String::from);
+        let measured = fault.is_none();
+        results.push(ExampleResult {
+            name: examples.name(index).to_string(),
+            fault,
+            cpu_ms: Stats::new(&cpu).filter(|_| measured),
+            gpu_ms: Stats::new(&gpu).filter(|_| measured),
+            frame_ms: Stats::new(&frame).filter(|_| measured),
+        });
+    }
+    examples.set_active(&[], e);
+
+    let size = e.extent_3d();
+    Report {
+        adapter: adapter.name.clone(),
+        backend: format!("{:?}", adapter.backend),
+        size: [size.width, size.height],
+        warmup: settings.warmup,
+        frames: settings.frames,
+        examples: results,
+    }
+}
+
+// The results as a table of medians
+pub fn format_report(report: &Report) -> String {
+    let mut out = format!(
+        "{} ({}), {}x{}, median of {} frames in ms\n{:<8} {:>9} {:>9} {:>9}\n",
+        report.adapter,
+        report.backend,
+        report.size[0],
+        report.size[1],
+        report.frames,
+        "example",
+        "cpu",
+        "gpu",
+        "frame"
+    );
+    for result in &report.examples {
+        out += &format!("{:<8}", result.name);
+        match &result.fault {
+            Some(fault) => out += &format!(" faulted: {fault}"),
+            None => {
+                for (_, stats) in result.metrics() {
+                    match stats {
+                        Some(stats) => out += &format!(" {:>9.3}", stats.median),
+                        None => out += &format!(" {:>9}", "-"),
+                    }
+                }
+            }
+        }
+        out += "\n";
+    }
+    out
+}
+
+// One example's median of one metric, before and after
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub example: String,
+    pub metric: &'static str,
+    pub baseline: f64,
+    // None if the example faulted
+    pub current: Option<f64>,
+}
+
+impl Change {
+    // How much slower, in percent
+    pub fn percent(&self) -> Option<f64> {
+        self.current
+            .map(|current| (current / self.baseline - 1.) * 100.)
+    }
+
+    // Slower by more than `threshold` percent, or faulted
+    pub fn regressed(&self, threshold: f64) -> bool {
+        self.percent().is_none_or(|percent| percent > threshold)
+    }
+}
+
+// The metrics of the examples in both reports.
+// Metrics only one report has (e.g. gpu on another adapter) are left out.
+pub fn compare(baseline: &Report, current: &Report) -> Vec<Change> {
+    let mut changes = vec![];
+    for before in &baseline.examples {
+        let Some(after) = current.examples.iter().find(|r| r.name == before.name) else {
+            continue;
+        };
+        for ((metric, before_stats), (_, after_stats)) in
+            before.metrics().into_iter().zip(after.metrics())
+        {
+            let Some(before_stats) = before_stats.filter(|stats| stats.median > 0.) else {
+                continue;
+            };
+            let current = match (&after.fault, after_stats) {
+                (Some(_), _) => None,
+                (None, Some(after_stats)) => Some(after_stats.median),
+                (None, None) => continue,
+            };
+            changes.push(Change {
+                example: before.name.clone(),
+                metric,
+                baseline: before_stats.median,
+                current,
+            });
+        }
+    }
+    changes
+}
+
+pub fn format_changes(changes: &[Change], threshold: f64) -> String {
+    let mut out = format!(
+        "{:<8} {:<6} {:>9} {:>9} {:>8}\n",
+        "example", "metric", "baseline", "current", "change"
+    );
+    for change in changes {
+        let (current, percent) = match (change.current, change.percent()) {
+            (Some(current), Some(percent)) => (format!("{current:.3}"), format!("{percent:+.1}%")),
+            _ => ("faulted".to_string(), String::new()),
+        };
+        out += &format!(
+            "{:<8} {:<6} {:>9.3} {:>9} {:>8}{}\n",
+            change.example,
+            change.metric,
+            change.baseline,
+            current,
+            percent,
+            if change.regressed(threshold) {
+                "  REGRESSION"
+            } else {
+                ""
+            }
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(median: f64) -> Option<Stats> {
+        Some(Stats {
+            min: median,
+            median,
+            p95: median,
+            p99: median,
+        })
+    }
+
+    fn report(examples: Vec<ExampleResult>) -> Report {
+        Report {
+            adapter: "test".into(),
+            backend: "Vulkan".into(),
+            size: [800, 600],
+            warmup: 1,
+            frames: 2,
+            examples,
+        }
+    }
+
+    fn result(name: &str, cpu: f64, gpu: Option<f64>, fault: Option<&str>) -> ExampleResult {
+        ExampleResult {
+            name: name.into(),
+            fault: fault.map(String::from),
+            cpu_ms: stats(cpu),
+            gpu_ms: gpu.and_then(stats),
+            frame_ms: stats(cpu * 2.),
+        }
+    }
+
+    #[test]
+    fn percentiles() {
+        let samples: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        assert_eq!(
+            Stats::new(&samples),
+            Some(Stats {
+                min: 1.,
+                median: 50.5,
+                p95: 95.,
+                p99: 99.,
+            })
+        );
+        assert_eq!(Stats::new(&[3., 1., 2.]).unwrap().median, 2.);
+        assert_eq!(Stats::new(&[]), None);
+    }
+
+    #[test]
+    fn flags_regressions() {
+        let baseline = report(vec![
+            result("ex01", 1., Some(2.), None),
+            result("ex02", 1., None, None),
+            result("ex03", 1., None, None),
+        ]);
+        let current = report(vec![
+            result("ex01", 1.05, None, None),
+            result("ex02", 1.5, None, None),
+            result("ex03", 0., None, Some("panic: oops")),
+        ]);
+        let regressed: Vec<_> = compare(&baseline, &current)
+            .into_iter()
+            .map(|change| {
+                let regressed = change.regressed(10.);
+                (change.example, change.metric, regressed)
+            })
+            .collect();
+        // ex01's gpu is left out, the current report doesn't have it
+        assert_eq!(
+            regressed,
+            [
+                ("ex01".to_string(), "cpu", false),
+                ("ex01".to_string(), "frame", false),
+                ("ex02".to_string(), "cpu", true),
+                ("ex02".to_string(), "frame", true),
+                ("ex03".to_string(), "cpu", true),
+                ("ex03".to_string(), "frame", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_round_trip() {
+        let report = report(vec![result("ex01", 1., Some(0.5), None)]);
+        let text = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<Report>(&text).unwrap(), report);
+    }
+}
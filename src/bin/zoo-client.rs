@@ -0,0 +1,101 @@
+/*
+Talks to a zoo started with `--listen` (or `headless --listen`), see `src/remote.rs`.
+
+    cargo run --bin zoo-client -- /tmp/zoo.sock '{"cmd": "example", "name": "ex07"}' '{"cmd": "frame"}'
+
+Every argument after the address is a request, without any they're read from stdin, one per line.
+Responses are printed, except that a frame's PNG is saved to `frame-<n>.png` instead
+(`--out <prefix>` to name them differently), and its file name printed.
+A frame log is printed as it is, one line per group or marker.
+ */
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream},
+};
+
+use base64::Engine;
+use serde_json::Value;
+
+fn connect(address: &str) -> std::io::Result<(Box<dyn Read>, Box<dyn Write>)> {
+    if let Ok(address) = address.parse::<SocketAddr>() {
+        let stream = TcpStream::connect(address)?;
+        return Ok((Box::new(stream.try_clone()?), Box::new(stream)));
+    }
+    #[cfg(unix)]
+    {
+        let stream = std::os::unix::net::UnixStream::connect(address)?;
+        Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+    }
+    #[cfg(not(unix))]
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "no Unix sockets here, use e.g. 127.0.0.1:7878",
+    ))
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let prefix = match args.iter().position(|arg| arg == "--out") {
+        Some(i) if i + 1 < args.len() => args.drain(i..i + 2).nth(1).unwrap(),
+        _ => "frame".to_string(),
+    };
+    if args.is_empty() {
+        println!(
+            "Usage: zoo-client <socket path, or 127.0.0.1:port> [--out <prefix>] [request...]"
+        );
+        std::process::exit(2);
+    }
+    let address = args.remove(0);
+
+    let (reader, mut writer) = connect(&address).unwrap_or_else(|err| {
+        println!("Could not connect to {address:?}: {err}");
+        std::process::exit(1);
+    });
+    let mut responses = BufReader::new(reader).lines();
+
+    let requests: Box<dyn Iterator<Item = String>> = if args.is_empty() {
+        Box::new(std::io::stdin().lock().lines().map_while(Result::ok))
+    } else {
+        Box::new(args.into_iter())
+    };
+
+    let mut frames = 0;
+    let mut failed = false;
+    for request in requests.filter(|request| !request.trim().is_empty()) {
+        if writeln!(writer, "{}", request.trim()).is_err() {
+            println!("The zoo hung up");
+            std::process::exit(1);
+        }
+        let Some(Ok(line)) = responses.next() else {
+            println!("The zoo hung up");
+            std::process::exit(1);
+        };
+
+        let mut response: Value = serde_json::from_str(&line).unwrap_or(Value::Null);
+        failed |= response["ok"] != Value::Bool(true);
+
+        // Save the PNG, print where to instead of the bytes
+        if let Some(png) = response["png"].as_str() {
+            frames += 1;
+            let path = format!("{prefix}-{frames}.png");
+            let saved = base64::engine::general_purpose::STANDARD
+                .decode(png)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| std::fs::write(&path, bytes).map_err(|err| err.to_string()));
+            response["png"] = match saved {
+                Ok(()) => path.into(),
+                Err(err) => format!("could not save: {err}").into(),
+            };
+            println!("{response}");
+        } else if let Some(log) = response["log"].as_str() {
+            print!("{log}");
+        } else {
+            println!("{line}");
+        }
+    }
+
+    // Such that scripts notice
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,370 @@
+/*
+Commands typed on stdin, or read from a script file (`cargo run -- --script demo.txt`),
+such that a session can be scripted or something precise done without clicking around.
+
+One command per line, `#` starts a comment:
+
+    # Show example 2 with a big ring, let it run for a second, then save a screenshot
+    example 2
+    set radius 1.3
+    wait 60
+    screenshot ring.png
+
+Stdin is read on a background thread, the runner takes commands from here once per event loop iteration.
+Commands which need frames to happen (`wait`, `step`, `screenshot`) hold back the following ones until they have,
+so a script runs the same every time.
+ */
+use std::{
+    collections::VecDeque,
+    io::BufRead,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, TryRecvError},
+};
+
+use wgpu::PolygonMode;
+
+use crate::record;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    // By name or number
+    Example(String),
+    // A parameter of the active example, or of any example as `ex02.radius`
+    Set { name: String, value: String },
+    Params,
+    // Toggle, or on/off
+    Pause(Option<bool>),
+    // Pause and advance this many frames
+    Step(u32),
+    // Frames to wait before the next command
+    Wait(u32),
+    Screenshot(Option<PathBuf>),
+    Polygon(PolygonMode),
+    // Start recording, or stop with none
+    Record(Option<record::Settings>),
+    // Recompile all shaders
+    Reload,
+    // Write what naga makes of the example's shader, again on every reload
+    ShaderDump,
+    // The examples
+    List,
+    Help,
+    Quit,
+}
+
+pub const HELP: &str = "\
+Commands:
+  example <name or number>   Show an example, e.g. `example 5` or `example ex05`
+  set <param> <value>        Set a parameter of the example, or `set ex02.radius 1.3`
+  params                     Print the example's parameters
+  pause [on|off]             Pause/resume the examples' time (toggles without argument)
+  step [frames]              Pause, then advance this many frames (default 1)
+  wait <frames>              Wait this many frames before the next command
+  screenshot [path]          Save the next frame as a PNG
+  polygon <fill|line|point>  Set the example's polygon mode
+  record <gif|png> [n] [k]   Record n frames (0 until stopped), every kth one
+  record stop                Stop recording
+  reload                     Recompile all shaders
+  shader-dump                Write the example's shader as naga IR, SPIR-V, GLSL, HLSL, MSL to shader-dump/
+  list                       List the examples
+  help                       This
+  quit                       Quit
+";
+
+fn number(arg: Option<&str>, default: Option<u32>) -> Result<u32, String> {
+    match (arg, default) {
+        (Some(arg), _) => arg
+            .parse()
+            .map_err(|_| format!("expected a number, got \"{arg}\"")),
+        (None, Some(default)) => Ok(default),
+        (None, None) => Err("expected a number".to_string()),
+    }
+}
+
+// Nothing for blank lines and comments
+pub fn parse(line: &str) -> Result<Option<Command>, String> {
+    let line = line.split('#').next().unwrap_or_default();
+    let mut words = line.split_whitespace();
+    let Some(command) = words.next() else {
+        return Ok(None);
+    };
+    let args: Vec<&str> = words.collect();
+    let arg = args.first().copied();
+
+    let expect_args = |n: usize| {
+        if args.len() == n {
+            Ok(())
+        } else {
+            Err(format!(
+                "\"{command}\" takes {n} argument(s), got {}",
+                args.len()
+            ))
+        }
+    };
+    let at_most_one = || {
+        if args.len() <= 1 {
+            Ok(())
+        } else {
+            Err(format!("\"{command}\" takes at most one argument"))
+        }
+    };
+
+    let command = match command {
+        "example" => {
+            expect_args(1)?;
+            Command::Example(args[0].to_string())
+        }
+        "set" => {
+            expect_args(2)?;
+            Command::Set {
+                name: args[0].to_string(),
+                value: args[1].to_string(),
+            }
+        }
+        "params" => {
+            expect_args(0)?;
+            Command::Params
+        }
+        "pause" => {
+            at_most_one()?;
+            Command::Pause(match arg {
+                None => None,
+                Some("on") => Some(true),
+                Some("off") => Some(false),
+                Some(arg) => return Err(format!("expected on or off, got \"{arg}\"")),
+            })
+        }
+        "step" => {
+            at_most_one()?;
+            Command::Step(number(arg, Some(1))?)
+        }
+        "wait" => {
+            expect_args(1)?;
+            Command::Wait(number(arg, None)?)
+        }
+        "screenshot" => {
+            at_most_one()?;
+            Command::Screenshot(arg.map(PathBuf::from))
+        }
+        "polygon" => {
+            expect_args(1)?;
+            Command::Polygon(match args[0] {
+                "fill" => PolygonMode::Fill,
+                "line" => PolygonMode::Line,
+                "point" => PolygonMode::Point,
+                mode => return Err(format!("expected fill, line or point, got \"{mode}\"")),
+            })
+        }
+        "record" => {
+            let format = match arg {
+                Some("stop") => {
+                    expect_args(1)?;
+                    return Ok(Some(Command::Record(None)));
+                }
+                Some("gif") => record::Format::Gif,
+                Some("png") => record::Format::Png,
+                _ => return Err("expected gif, png or stop".to_string()),
+            };
+            if args.len() > 3 {
+                return Err(format!("\"{command}\" takes at most three arguments"));
+            }
+            let frames = number(args.get(1).copied(), Some(0))?;
+            let every = number(args.get(2).copied(), Some(1))?;
+            if every == 0 {
+                return Err("can't record every 0th frame".to_string());
+            }
+            Command::Record(Some(record::Settings {
+                format,
+                frames: (frames > 0).then_some(frames),
+                every,
+            }))
+        }
+        "reload" => {
+            expect_args(0)?;
+            Command::Reload
+        }
+        "shader-dump" => {
+            expect_args(0)?;
+            Command::ShaderDump
+        }
+        "list" => {
+            expect_args(0)?;
+            Command::List
+        }
+        "help" => Command::Help,
+        "quit" | "exit" => Command::Quit,
+        _ => return Err(format!("unknown command \"{command}\", try \"help\"")),
+    };
+    Ok(Some(command))
+}
+
+#[derive(Default)]
+pub struct Console {
+    // Lines not run yet, with where they're from, e.g. `demo.txt:3` or `stdin`
+    queue: VecDeque<(String, String)>,
+    stdin: Option<Receiver<String>>,
+    // Frames to go before the next command
+    wait: u32,
+}
+
+impl Console {
+    // Read lines from stdin on a background thread
+    pub fn listen_stdin(&mut self) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        self.stdin = Some(rx);
+    }
+
+    // Queue every line of a script file
+    pub fn load_script(&mut self, path: &Path) -> std::io::Result<()> {
+        let script = std::fs::read_to_string(path)?;
+        self.queue_script(&path.display().to_string(), &script);
+        Ok(())
+    }
+
+    fn queue_script(&mut self, name: &str, script: &str) {
+        for (line_number, line) in script.lines().enumerate() {
+            self.queue
+                .push_back((format!("{name}:{}", line_number + 1), line.to_string()));
+        }
+    }
+
+    // The next command to run now, if any, with where it's from.
+    // A line which doesn't parse comes back as an error, for the runner to report.
+    pub fn next(&mut self) -> Option<(String, Result<Command, String>)> {
+        if let Some(stdin) = self.stdin.as_ref() {
+            loop {
+                match stdin.try_recv() {
+                    Ok(line) => self.queue.push_back(("stdin".to_string(), line)),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.stdin = None;
+                        break;
+                    }
+                }
+            }
+        }
+
+        while self.wait == 0 {
+            let (origin, line) = self.queue.pop_front()?;
+            let command = match parse(&line) {
+                Ok(None) => continue,
+                Ok(Some(command)) => command,
+                Err(err) => return Some((origin, Err(err))),
+            };
+
+            match command {
+                // Only holds back what follows, nothing for the runner to do
+                Command::Wait(frames) => {
+                    self.wait = frames;
+                    continue;
+                }
+                // What follows should see the frames stepped
+                Command::Step(frames) => self.wait = frames,
+                // And the screenshot taken
+                Command::Screenshot(_) => self.wait = 1,
+                _ => {}
+            }
+            return Some((origin, Ok(command)));
+        }
+        None
+    }
+
+    // The runner rendered a frame
+    pub fn frame_rendered(&mut self) {
+        self.wait = self.wait.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse("  # just a comment"), Ok(None));
+        assert_eq!(
+            parse("example 5 # the scissor one"),
+            Ok(Some(Command::Example("5".into())))
+        );
+        assert_eq!(
+            parse("set ex02.radius 1.3"),
+            Ok(Some(Command::Set {
+                name: "ex02.radius".into(),
+                value: "1.3".into()
+            }))
+        );
+        assert_eq!(parse("step"), Ok(Some(Command::Step(1))));
+        assert_eq!(parse("pause off"), Ok(Some(Command::Pause(Some(false)))));
+        assert_eq!(
+            parse("polygon line"),
+            Ok(Some(Command::Polygon(PolygonMode::Line)))
+        );
+        assert_eq!(
+            parse("screenshot out.png"),
+            Ok(Some(Command::Screenshot(Some("out.png".into()))))
+        );
+
+        assert_eq!(
+            parse("record png 0 2"),
+            Ok(Some(Command::Record(Some(record::Settings {
+                format: record::Format::Png,
+                frames: None,
+                every: 2
+            }))))
+        );
+        assert_eq!(parse("record stop"), Ok(Some(Command::Record(None))));
+        assert_eq!(parse("shader-dump"), Ok(Some(Command::ShaderDump)));
+
+        assert!(parse("set radius").is_err());
+        assert!(parse("record mp4").is_err());
+        assert!(parse("step many").is_err());
+        assert!(parse("polygon wire").is_err());
+        assert!(parse("shader-dump ex02").is_err());
+        assert!(parse("dance").is_err());
+    }
+
+    #[test]
+    fn script_waits_for_frames() {
+        let mut console = Console::default();
+        console.queue_script(
+            "demo",
+            "example 2\nwait 2\n\nset radius 1.3\nstep 1\nlist\nbogus\n",
+        );
+
+        let (origin, command) = console.next().unwrap();
+        assert_eq!(origin, "demo:1");
+        assert_eq!(command, Ok(Command::Example("2".into())));
+        // The wait isn't handed out, it holds back what follows
+        assert_eq!(console.next(), None);
+        console.frame_rendered();
+        assert_eq!(console.next(), None);
+        console.frame_rendered();
+
+        assert_eq!(
+            console.next().map(|(_, c)| c),
+            Some(Ok(Command::Set {
+                name: "radius".into(),
+                value: "1.3".into()
+            }))
+        );
+        assert_eq!(console.next().map(|(_, c)| c), Some(Ok(Command::Step(1))));
+        assert_eq!(console.next(), None);
+        console.frame_rendered();
+        assert_eq!(console.next().map(|(_, c)| c), Some(Ok(Command::List)));
+
+        let (origin, command) = console.next().unwrap();
+        assert_eq!(origin, "demo:7");
+        assert!(command.is_err());
+        assert_eq!(console.next(), None);
+    }
+}
@@ -0,0 +1,185 @@
+/*
+Debug groups and markers, from the README TODO "Debug markers in vulkan?".
+
+`group` pushes a debug group on a command encoder, render pass or compute pass, and pops it when dropped.
+`marker` inserts a single marker.
+Tools like RenderDoc show these as a tree around the commands.
+
+Everything is also recorded into a log for the current frame, such that the frame's structure
+can be printed without a capture tool (F3, see `format_frame`).
+
+Things we learned:
+    - Groups can't span command encoders, and every example makes its own encoder (and submits it).
+        So the runner's group around an example's frame is a `scope`, which is only in the log.
+    - A group pushed on a pass must be popped on that same pass, before the pass ends.
+        Tying the pop to a guard which borrows the pass makes that hard to get wrong.
+ */
+use std::{
+    cell::RefCell,
+    ops::{Deref, DerefMut},
+};
+
+use wgpu::{CommandEncoder, ComputePass, RenderPass};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    Push(String),
+    Pop,
+    Marker(String),
+}
+
+thread_local! {
+    // Entries since `begin_frame`
+    static FRAME: RefCell<Vec<Entry>> = const { RefCell::new(vec![]) };
+}
+
+fn record(entry: Entry) {
+    FRAME.with(|frame| frame.borrow_mut().push(entry));
+}
+
+// Forget what was recorded, the runner calls this before rendering a frame
+pub fn begin_frame() {
+    FRAME.with(|frame| frame.borrow_mut().clear());
+}
+
+// What was recorded since `begin_frame`
+pub fn take_frame() -> Vec<Entry> {
+    FRAME.with(|frame| std::mem::take(&mut *frame.borrow_mut()))
+}
+
+// Whatever can have debug groups, i.e. command encoders and passes
+pub trait Debuggable {
+    fn push(&mut self, label: &str);
+    fn pop(&mut self);
+    fn marker(&mut self, label: &str);
+}
+
+impl Debuggable for CommandEncoder {
+    fn push(&mut self, label: &str) {
+        self.push_debug_group(label);
+    }
+    fn pop(&mut self) {
+        self.pop_debug_group();
+    }
+    fn marker(&mut self, label: &str) {
+        self.insert_debug_marker(label);
+    }
+}
+
+impl Debuggable for RenderPass<'_> {
+    fn push(&mut self, label: &str) {
+        self.push_debug_group(label);
+    }
+    fn pop(&mut self) {
+        self.pop_debug_group();
+    }
+    fn marker(&mut self, label: &str) {
+        self.insert_debug_marker(label);
+    }
+}
+
+impl Debuggable for ComputePass<'_> {
+    fn push(&mut self, label: &str) {
+        self.push_debug_group(label);
+    }
+    fn pop(&mut self) {
+        self.pop_debug_group();
+    }
+    fn marker(&mut self, label: &str) {
+        self.insert_debug_marker(label);
+    }
+}
+
+// A debug group, popped when dropped.
+// Derefs to what it was pushed on, such that e.g. a render pass can be begun inside it.
+pub struct Group<'a, D: Debuggable> {
+    inner: &'a mut D,
+}
+
+pub fn group<'a, D: Debuggable>(inner: &'a mut D, label: &str) -> Group<'a, D> {
+    inner.push(label);
+    record(Entry::Push(label.to_string()));
+    Group { inner }
+}
+
+impl<D: Debuggable> Deref for Group<'_, D> {
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        self.inner
+    }
+}
+
+impl<D: Debuggable> DerefMut for Group<'_, D> {
+    fn deref_mut(&mut self) -> &mut D {
+        self.inner
+    }
+}
+
+impl<D: Debuggable> Drop for Group<'_, D> {
+    fn drop(&mut self) {
+        self.inner.pop();
+        record(Entry::Pop);
+    }
+}
+
+pub fn marker(inner: &mut impl Debuggable, label: &str) {
+    inner.marker(label);
+    record(Entry::Marker(label.to_string()));
+}
+
+// A group which is only in the log, for things which span several command encoders
+pub struct Scope(());
+
+pub fn scope(label: &str) -> Scope {
+    record(Entry::Push(label.to_string()));
+    Scope(())
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        record(Entry::Pop);
+    }
+}
+
+// One line per group or marker, indented by depth
+pub fn format_frame(entries: &[Entry]) -> String {
+    let mut out = String::new();
+    let mut depth = 0;
+    for entry in entries {
+        match entry {
+            Entry::Push(label) => {
+                out += &format!("{}{label}\n", "  ".repeat(depth));
+                depth += 1;
+            }
+            Entry::Pop => depth = depth.saturating_sub(1),
+            Entry::Marker(label) => out += &format!("{}- {label}\n", "  ".repeat(depth)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_nest() {
+        begin_frame();
+        {
+            let _frame = scope("ex06");
+            for quadrant in ["top-left", "top-right"] {
+                let _quadrant = scope(quadrant);
+                record(Entry::Marker("draw".into()));
+            }
+        }
+        drop(scope("split"));
+
+        assert_eq!(
+            format_frame(&take_frame()),
+            "ex06\n  top-left\n    - draw\n  top-right\n    - draw\nsplit\n"
+        );
+        // Taken, so the next frame starts empty
+        assert!(take_frame().is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+/*
+An optional depth/stencil attachment for examples, the size of whatever they render into.
+An example which wants one keeps a `DepthStencil` and asks it for the attachment each frame:
+
+    depth_stencil_attachment: Some(self.depth_stencil.attachment(e, Some(Operations::default()), None)),
+
+It's recreated when the target's size changes (window resize, split screen cells), like the x-ray lens textures.
+Pipelines drawing in such a pass need a `depth_stencil` state with `FORMAT`, see `state`.
+
+Things we learned:
+    - The depth/stencil attachment must be the same size (and sample count) as the color attachments.
+    - `Depth24PlusStencil8` works everywhere. `Depth32FloatStencil8` needs a feature,
+        and "24Plus" means the depth part may be stored as anything at least that precise, so it can't be copied out.
+    - Depth and stencil have their own load/store ops. `None` means the pass leaves that aspect alone (read only).
+    - Every pipeline used in a pass with a depth/stencil attachment needs a `depth_stencil` state of the same format,
+        even if it doesn't test or write anything, or validation fails.
+    - The stencil reference value isn't part of the pipeline, it's set per pass via `set_stencil_reference`.
+ */
+use wgpu::{
+    CompareFunction, DepthBiasState, DepthStencilState, Operations,
+    RenderPassDepthStencilAttachment, StencilState, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+use crate::ExampleData;
+
+pub const FORMAT: TextureFormat = TextureFormat::Depth24PlusStencil8;
+
+pub struct DepthStencil {
+    label: &'static str,
+    // Created when first used
+    texture: Option<(Texture, TextureView)>,
+}
+
+impl DepthStencil {
+    pub fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            texture: None,
+        }
+    }
+
+    // Matches the target's size
+    pub fn view(&mut self, e: &ExampleData) -> &TextureView {
+        // Handle resize
+        let size = e.extent_3d();
+        if self.texture.as_ref().map(|(texture, _)| texture.size()) != Some(size) {
+            let texture = e.device.create_texture(&TextureDescriptor {
+                label: Some(self.label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            self.texture = Some((texture, view));
+        }
+        &self.texture.as_ref().unwrap().1
+    }
+
+    // `None` ops leave depth or stencil as they are
+    pub fn attachment(
+        &mut self,
+        e: &ExampleData,
+        depth_ops: Option<Operations<f32>>,
+        stencil_ops: Option<Operations<u32>>,
+    ) -> RenderPassDepthStencilAttachment<'_> {
+        RenderPassDepthStencilAttachment {
+            view: self.view(e),
+            depth_ops,
+            stencil_ops,
+        }
+    }
+}
+
+// For a pipeline drawing with the attachment: Depth tested and written as given, and the stencil state
+pub fn state(
+    depth_compare: CompareFunction,
+    depth_write_enabled: bool,
+    stencil: StencilState,
+) -> DepthStencilState {
+    DepthStencilState {
+        format: FORMAT,
+        depth_write_enabled,
+        depth_compare,
+        stencil,
+        bias: DepthBiasState::default(),
+    }
+}
@@ -24,11 +24,16 @@ struct VertexOutput {
     @location(0) ii: f32,
 };
 
+// Rotates by `a` radians clockwise
+fn rotation(a: f32) -> mat2x2<f32> {
+    return mat2x2f(cos(a), -sin(a), sin(a), cos(a));
+}
+
 @vertex
 fn vs(vertex: VertexInput) -> VertexOutput {
     var iif = f32(vertex.ii);
     var a = (2. * 3.1415) * (iif / f32(u_instances));
-    var rot = mat2x2f(cos(a), -sin(a), sin(a), cos(a));
+    var rot = rotation(a);
     var scale = mat2x2f(0.1, 0., 0., 0.1);
 
     var offset = (3.0 + sin(u_time*5. + a)) * vec2<f32>(u_radius, u_radius);
@@ -4,7 +4,7 @@ var<uniform> u_viewport: vec2<f32>;
 
 @group(0)
 @binding(1)
-var<uniform> u_quad: array<vec4<f32>, 4>;
+var<storage, read> s_vertices: array<vec2<f32>>;
 
 @group(0)
 @binding(2)
@@ -16,19 +16,28 @@ var<uniform> u_time: f32;
 
 var<push_constant> threshold: f32;
 
-struct VertexInput {
-    @location(0) position: vec2<f32>,
-}
-
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
 };
 
+// From framebuffer pixels (`@builtin(position)` in a fragment shader) to clip space
+fn framebuffer_to_clip(position: vec2<f32>, viewport: vec2<f32>) -> vec2<f32> {
+    // Normalize again to 0.0->1.0;
+    var pos = position / viewport;
+    // Now to -1.0..1.0
+    pos = (pos * 2.0) - 1.0;
+    // Framebuffer coords have flipped y compared to clip
+    pos.y *= -1.0;
+    return pos;
+}
+
 @vertex
-fn vs(vertex: VertexInput) -> VertexOutput {
+fn vs(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     var v: VertexOutput;
 
-    v.position = vec4<f32>(vertex.position.x , vertex.position.y, 0.0, 1.0);
+    // Drawn indexed, so this is the index from the index buffer
+    let position = s_vertices[vertex_index];
+    v.position = vec4<f32>(position.x , position.y, 0.0, 1.0);
 
     return v;
 }
@@ -48,23 +57,18 @@ fn fs(input: VertexOutput) -> @location(0) vec4<f32> {
     // This means that in order to relate this to the mouse uniform,
     // we have to know the size of the viewport, which we have now added as u_viewport.
 
-    // Normalize again to 0.0->1.0;
-    var pos = input.position.xy / u_viewport;
-    // Now to -1.0..1.0
-    pos = (pos * 2.0) - 1.0;
-    // Framebuffer coords have flipped y compared to clip
-    pos.y *= -1.0;
+    let pos = framebuffer_to_clip(input.position.xy, u_viewport);
 
     // We only care about the distance to the closest vertex- i.e. the one with the least distance
     var closest = 10.;
-    var vertex = u_quad[0].xy;
+    var vertex = s_vertices[0];
 
-    for (var i: i32 = 0; i < 4; i++) {
-        // How far to quad vertex
-        let l = length(-u_mouse + u_quad[i].xy);
+    for (var i: u32 = 0u; i < arrayLength(&s_vertices); i++) {
+        // How far to polygon vertex
+        let l = length(-u_mouse + s_vertices[i]);
         if (l < closest) {
             closest = l;
-            vertex = u_quad[i].xy;
+            vertex = s_vertices[i];
         }
     }
 
@@ -4,31 +4,53 @@ struct VertexInput {
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
+    // Integers can't be interpolated
+    @location(0) @interpolate(flat) primitive_id: u32,
+    @location(1) barycentric: vec2<f32>,
+    // The builtin position is in framebuffer coordinates in the fragment shader, so pass clip space separately
+    @location(2) clip: vec2<f32>,
 };
 
 struct FragOutput {
-  @location(0) fb0: vec4<f32>,
-  @location(1) fb1: vec4<f32>
+  @location(0) color: vec4<f32>,
+  @location(1) position: vec4<f32>,
+  @location(2) primitive_id: u32,
+  @location(3) barycentric: vec2<f32>,
 }
 
 @vertex
 fn vs(input: VertexInput) -> VertexOutput {
     var out: VertexOutput;
 
-    // Expect draw 3.
+    // Expect draw 9, i.e. three triangles.
     // Yes- there is a trick where this is done branchless, but it doesn't matter to this small example.
+    let corner = input.vertex_index % 3u;
+    let triangle = input.vertex_index / 3u;
 
-    if(input.vertex_index == 0u) {
+    if(corner == 0u) {
         // Note: The *0.5 will not have any effect, since the last entry of the vec4 will also then be *0.5,
         // which is automatically adjusted for.
-        out.position = vec4<f32>(-1.0, -1.0, 0.0, 1.0)*0.5; 
-    } else if (input.vertex_index == 1u) {
+        out.position = vec4<f32>(-1.0, -1.0, 0.0, 1.0)*0.5;
+        out.barycentric = vec2<f32>(1., 0.);
+    } else if (corner == 1u) {
         // This leaves the `w` part alone, which then _does_ mean this vertex will be moved by *0.9.
-        out.position = vec4<f32>(1.0*0.9, -1.0*0.9, 0.0, 1.0); 
+        out.position = vec4<f32>(1.0*0.9, -1.0*0.9, 0.0, 1.0);
+        out.barycentric = vec2<f32>(0., 1.);
     } else {
-        out.position = vec4<f32>(0.0, 1.0, 0.0, 1.0); 
+        out.position = vec4<f32>(0.0, 1.0, 0.0, 1.0);
+        out.barycentric = vec2<f32>(0., 0.);
     }
 
+    // The first triangle is the big one, the others are smaller copies in the top corners
+    if (triangle == 1u) {
+        out.position = vec4<f32>(out.position.xy / out.position.w * 0.3 + vec2<f32>(-0.7, 0.6), 0.0, 1.0);
+    } else if (triangle == 2u) {
+        out.position = vec4<f32>(out.position.xy / out.position.w * 0.3 + vec2<f32>(0.7, 0.6), 0.0, 1.0);
+    }
+
+    out.primitive_id = triangle;
+    out.clip = out.position.xy / out.position.w;
+
     return out;
 }
 
@@ -36,9 +58,88 @@ fn vs(input: VertexInput) -> VertexOutput {
 fn fs(input: VertexOutput) -> FragOutput {
     var out: FragOutput;
 
-    out.fb0 = vec4(1., 0., 0., 1.);
-    // Will turn out green anyway since we selectively enable only green & blue channels.
-    out.fb1 = vec4(1., 1., 0., 1.);
+    out.color = vec4(1., 0., 0., 1.);
+    // Negative values are fine in a float format
+    out.position = vec4(input.clip, input.position.z, 1.);
+    // Offset by one such that the cleared background (0) is distinguishable from the first triangle
+    out.primitive_id = input.primitive_id + 1u;
+    out.barycentric = input.barycentric;
 
     return out;
 }
+
+// Showing the attachments on screen
+
+@group(0)
+@binding(0)
+var t_color: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var t_position: texture_2d<f32>;
+
+@group(0)
+@binding(2)
+var t_primitive_id: texture_2d<u32>;
+
+@group(0)
+@binding(3)
+var t_barycentric: texture_2d<f32>;
+
+// Which attachment to show, 0..=3.
+// 4 shows all of them side by side.
+var<push_constant> show: u32;
+
+// Full screen triangle, draw 3
+@vertex
+fn vs_show(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * vec2<f32>(2., -2.) + vec2<f32>(-1., 1.), 0., 1.);
+}
+
+// Hash an integer to a color
+fn id_color(id: u32) -> vec3<f32> {
+    if (id == 0u) {
+        return vec3<f32>(0.);
+    }
+    var h = id * 747796405u + 2891336453u;
+    h = ((h >> ((h >> 28u) + 4u)) ^ h) * 277803737u;
+    return vec3<f32>(f32(h & 255u), f32((h >> 8u) & 255u), f32((h >> 16u) & 255u)) / 255.;
+}
+
+fn visualize(attachment: u32, pixel: vec2<i32>) -> vec4<f32> {
+    if (attachment == 0u) {
+        // Already a color
+        return textureLoad(t_color, pixel, 0);
+    } else if (attachment == 1u) {
+        // Clip space -1..1 to 0..1
+        let p = textureLoad(t_position, pixel, 0);
+        return vec4<f32>(p.xy * 0.5 + 0.5, 0., 1.);
+    } else if (attachment == 2u) {
+        // Integers can't be filtered, so load them and make up a color
+        let id = textureLoad(t_primitive_id, pixel, 0).r;
+        return vec4<f32>(id_color(id), 1.);
+    } else {
+        // Two channels, blue left empty
+        let b = textureLoad(t_barycentric, pixel, 0).rg;
+        return vec4<f32>(b, 0., 1.);
+    }
+}
+
+@fragment
+fn fs_show(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+
+    if (show < 4u) {
+        return visualize(show, pixel);
+    }
+
+    // Side by side in a 2x2 grid.
+    // Each quadrant shows the whole attachment at half size.
+    let size = vec2<i32>(textureDimensions(t_color));
+    let half = size / 2;
+    let quadrant = vec2<i32>(select(0, 1, pixel.x >= half.x), select(0, 1, pixel.y >= half.y));
+    let attachment = u32(quadrant.x + quadrant.y * 2);
+
+    return visualize(attachment, (pixel - quadrant * half) * 2);
+}
@@ -9,6 +9,8 @@ struct VertexInput {
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
+    // 0 at the center, 1 at the end of the lines
+    @location(0) t: f32,
 };
 
 struct FragOutput {
@@ -22,6 +24,7 @@ fn vs(input: VertexInput) -> VertexOutput {
     if ((input.vertex_index % 2u) == 0u) {
         // Even, place starting vertex at origin
         out.position = vec4<f32>(0.0, 0.0, 0.0, 1.0); 
+        out.t = 0.;
     } else {
         // Odd, indices 1, 3, 5, 7, .., 63.
         // Make the angle radians based on vertex index.
@@ -30,6 +33,7 @@ fn vs(input: VertexInput) -> VertexOutput {
         let t = (u_time * .2) + rads;
 
         out.position = vec4<f32>(cos(t), sin(t), 0.0, 1.0); 
+        out.t = 1.;
     }
 
     return out;
@@ -39,7 +43,9 @@ fn vs(input: VertexInput) -> VertexOutput {
 fn fs(input: VertexOutput) -> FragOutput {
     var out: FragOutput;
 
-    out.fb0 = vec4(1.);
+    // Fade out towards the end of the lines.
+    // Without blending the alpha only matters when alpha to coverage is enabled.
+    out.fb0 = vec4(1., 1., 1., 1. - input.t);
 
     return out;
 }
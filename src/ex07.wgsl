@@ -8,15 +8,33 @@ var t_read: texture_2d<f32>;
 
 @group(0)
 @binding(2)
-var t_write: texture_storage_2d<r32float, write>;
+var t_write: texture_storage_2d<rgba16float, write>;
 
 @group(0)
 @binding(3)
 var s_sampler: sampler;
 
+struct Brush {
+    color: vec4<f32>,
+    // Window space
+    mouse: vec2<f32>,
+    // Pixels
+    radius: f32,
+    // How much is painted per second at the center of the brush
+    strength: f32,
+    // How much alpha fades per second, everywhere
+    decay: f32,
+    // Seconds since the last frame
+    dt: f32,
+    // Mouse held?
+    painting: u32,
+    // Remove instead of paint
+    erase: u32,
+}
+
 @group(0)
 @binding(4)
-var<uniform> u_mouse: vec2<u32>;
+var<uniform> u_brush: Brush;
 
 struct VertexInput {
     @builtin(vertex_index) vertex_index: u32,
@@ -35,16 +53,16 @@ fn vs(input: VertexInput) -> VertexOutput {
 
     if (input.vertex_index == 0u) {
         // bottom left
-        xy = vec2<f32>(-1.0, -1.0) * size; 
+        xy = vec2<f32>(-1.0, -1.0) * size;
     } else if (input.vertex_index == 1u) {
         // bottom right
-        xy = vec2<f32>(1.0, -1.0) * size; 
+        xy = vec2<f32>(1.0, -1.0) * size;
     } else if (input.vertex_index == 2u) {
         // top left
-        xy = vec2<f32>(-1.0, 1.0) * size; 
+        xy = vec2<f32>(-1.0, 1.0) * size;
     } else {
         // top right
-        xy = vec2<f32>(1.0, 1.0) * size; 
+        xy = vec2<f32>(1.0, 1.0) * size;
     }
 
     out.position = vec4<f32>(xy, 0., 1.);
@@ -57,27 +75,32 @@ fn fs(input: VertexOutput) -> @location(0) vec4<f32> {
     let pixel_coordinates = vec2<i32>(input.position.xy);
 
     let width_height = textureDimensions(t_read);
-
     let whf = vec2<f32>(width_height);
-    let l_from_mouse = length(vec2<f32>((-vec2<i32>(u_mouse)) + pixel_coordinates) / whf);
-
-    let draw_radius = 0.05;
-    let dist01 = max(0., 1. - (l_from_mouse * (1. / draw_radius)));
 
     let posf = input.position.xy;
 
     // What's already stored
-    var value = textureSample(t_read, s_sampler, (posf / whf)).r;
+    var value = textureSample(t_read, s_sampler, (posf / whf));
 
-    // How much to add, based on distance from mouse
-    let add = dist01 / 10.;
+    // 1 at the center of the brush, 0 at the radius and beyond
+    let l_from_mouse = length(posf - u_brush.mouse);
+    let falloff = max(0., 1. - (l_from_mouse / u_brush.radius));
+
+    // How much to paint, based on distance from mouse
+    let amount = saturate(falloff * u_brush.strength * u_brush.dt * f32(u_brush.painting));
+
+    if (u_brush.erase == 1u) {
+        value.a = max(0., value.a - amount);
+    } else {
+        value = mix(value, u_brush.color, amount);
+    }
 
-    // How much to remove per frame
-    let fade = 0.001;
+    // How much to remove, per second
+    value.a = max(0., value.a - u_brush.decay * u_brush.dt);
 
-    let result = saturate(value + add - fade);
+    textureStore(t_write, pixel_coordinates, value);
 
-    let col = vec4<f32>(result, 0.1, 0.1, 1.);
-    textureStore(t_write, pixel_coordinates, col);
-    return col;
+    // The canvas on top of a dark background
+    let background = vec3<f32>(0.1, 0.1, 0.1);
+    return vec4<f32>(mix(background, value.rgb, value.a), 1.);
 }
@@ -0,0 +1,65 @@
+@group(0)
+@binding(0)
+var t_out: texture_storage_2d<rgba8unorm, write>;
+
+@group(0)
+@binding(1)
+var<uniform> u_time: f32;
+
+// Used by the blit to the swapchain, see `vs` and `fs`
+@group(0)
+@binding(2)
+var t_read: texture_2d<f32>;
+
+@group(0)
+@binding(3)
+var s_sampler: sampler;
+
+// NOTE: The example swaps out the `@workgroup_size(..)` below for each variant it benchmarks,
+// so keep it on a single line.
+@compute
+@workgroup_size(8, 8, 1)
+fn cs(@builtin(global_invocation_id) giid: vec3<u32>) {
+    let size = textureDimensions(t_out);
+
+    // Dispatches are rounded up to whole workgroups, so the edges can be outside the texture
+    if (giid.x >= size.x || giid.y >= size.y) {
+        return;
+    }
+
+    let uv = vec2<f32>(giid.xy) / vec2<f32>(size);
+
+    // Just some arithmetic such that each invocation does a little bit of work
+    var p = uv * 2. - 1.;
+    var acc = 0.;
+    for (var i: i32 = 0; i < 16; i++) {
+        p = abs(p) / dot(p, p) - vec2<f32>(0.9 + 0.05 * sin(u_time), 0.6);
+        acc += length(p);
+    }
+    acc /= 16.;
+
+    let col = vec4<f32>(uv.x, uv.y, saturate(acc * 0.5), 1.);
+    textureStore(t_out, vec2<i32>(giid.xy), col);
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Full screen triangle, draw 3
+@vertex
+fn vs(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.position = vec4<f32>(uv * vec2<f32>(2., -2.) + vec2<f32>(-1., 1.), 0., 1.);
+    out.uv = uv;
+
+    return out;
+}
+
+@fragment
+fn fs(input: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_read, s_sampler, input.uv);
+}
@@ -0,0 +1,65 @@
+struct Globals {
+    // Keeps the quads square in a viewport which isn't
+    scale: vec2<f32>,
+    // The blend mode expects color premultiplied by alpha
+    premultiply: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> u_globals: Globals;
+
+struct QuadInput {
+    @builtin(vertex_index) vi: u32,
+    @location(0) center: vec2<f32>,
+    @location(1) size: f32,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs(input: QuadInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1., -1.),
+        vec2<f32>(1., 1.),
+        vec2<f32>(-1., 1.),
+        vec2<f32>(1., 1.),
+        vec2<f32>(-1., -1.),
+        vec2<f32>(1., -1.),
+    );
+
+    var out: VertexOutput;
+    let pos = input.center + corners[input.vi] * input.size;
+    out.position = vec4<f32>(pos * u_globals.scale, 0., 1.);
+    out.color = input.color;
+    return out;
+}
+
+@fragment
+fn fs(input: VertexOutput) -> @location(0) vec4<f32> {
+    if u_globals.premultiply == 1u {
+        return vec4<f32>(input.color.rgb * input.color.a, input.color.a);
+    }
+    return input.color;
+}
+
+// One triangle covering the viewport
+@vertex
+fn vs_background(@builtin(vertex_index) vi: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vi << 1u) & 2u), f32(vi & 2u));
+    return vec4<f32>(uv * 2. - 1., 0., 1.);
+}
+
+// A checkerboard, to see what's behind the quads
+@fragment
+fn fs_background(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let cell = vec2<u32>(position.xy / 32.);
+    if (cell.x + cell.y) % 2u == 0u {
+        return vec4<f32>(0.35, 0.35, 0.35, 1.);
+    }
+    return vec4<f32>(0.65, 0.65, 0.65, 1.);
+}
@@ -0,0 +1,68 @@
+@group(0)
+@binding(0)
+var<uniform> u_time: f32;
+
+// The mask shapes, and their outlines
+@vertex
+fn vs_shape(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(position, 0., 1.);
+}
+
+@fragment
+fn fs_outline() -> @location(0) vec4<f32> {
+    return vec4<f32>(1., 1., 1., 1.);
+}
+
+struct FullScreen {
+    @builtin(position) position: vec4<f32>,
+    // Which stencil value is shown, see `fs_values`
+    @location(0) @interpolate(flat) value: u32,
+};
+
+// One triangle covering the target, per 3 vertices.
+// Vertices 3v to 3v + 2 pass value v on.
+@vertex
+fn vs_full_screen(@builtin(vertex_index) vi: u32) -> FullScreen {
+    var out: FullScreen;
+    let corner = vi % 3u;
+    let uv = vec2<f32>(f32((corner << 1u) & 2u), f32(corner & 2u));
+    out.position = vec4<f32>(uv * 2. - 1., 0., 1.);
+    out.value = vi / 3u;
+    return out;
+}
+
+// Moving warm stripes
+@fragment
+fn fs_inside(input: FullScreen) -> @location(0) vec4<f32> {
+    let stripe = fract((input.position.x + input.position.y) / 40. - u_time * 0.5);
+    if stripe < 0.5 {
+        return vec4<f32>(1., 0.45, 0.1, 1.);
+    }
+    return vec4<f32>(0.9, 0.15, 0.2, 1.);
+}
+
+// A dim checkerboard
+@fragment
+fn fs_outside(input: FullScreen) -> @location(0) vec4<f32> {
+    let cell = vec2<u32>(input.position.xy / 24.);
+    if (cell.x + cell.y) % 2u == 0u {
+        return vec4<f32>(0.1, 0.15, 0.25, 1.);
+    }
+    return vec4<f32>(0.15, 0.2, 0.35, 1.);
+}
+
+// A color per stencil value, drawn where the stencil test passes for that value
+@fragment
+fn fs_values(input: FullScreen) -> @location(0) vec4<f32> {
+    var colors = array<vec3<f32>, 8>(
+        vec3<f32>(0., 0., 0.),
+        vec3<f32>(1., 0.1, 0.1),
+        vec3<f32>(0.1, 1., 0.1),
+        vec3<f32>(0.1, 0.2, 1.),
+        vec3<f32>(1., 1., 0.1),
+        vec3<f32>(0.1, 1., 1.),
+        vec3<f32>(1., 0.1, 1.),
+        vec3<f32>(1., 1., 1.),
+    );
+    return vec4<f32>(colors[input.value % 8u], 1.);
+}
@@ -0,0 +1,115 @@
+struct Cull {
+    // Clip space
+    mouse: vec2<f32>,
+    // Keeps the spiral round in a target which isn't
+    aspect: vec2<f32>,
+    instances: u32,
+    // 0: viewport, 1: mouse region, 2: off
+    mode: u32,
+    time: f32,
+    // Radius of the mouse region
+    region: f32,
+    // How far the viewport bounds are moved in
+    inset: f32,
+    // Of an instance, from its center to a corner
+    size: f32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> u_cull: Cull;
+
+// The survivors, compacted
+struct Instance {
+    position: vec2<f32>,
+    angle: f32,
+    index: u32,
+};
+
+@group(0)
+@binding(1)
+var<storage, read_write> instances: array<Instance>;
+
+// Laid out like `draw_indirect` expects, see `wgpu::util::DrawIndirect`
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    base_vertex: u32,
+    base_instance: u32,
+};
+
+@group(0)
+@binding(2)
+var<storage, read_write> draw_args: DrawArgs;
+
+// Whether an instance at this position (spiral space) may be on screen
+fn visible(position: vec2<f32>) -> bool {
+    let clip = position * u_cull.aspect;
+    let size = u_cull.size * max(u_cull.aspect.x, u_cull.aspect.y);
+    if u_cull.mode == 0u {
+        let bound = 1. - u_cull.inset + size;
+        return all(abs(clip) <= vec2<f32>(bound, bound));
+    }
+    if u_cull.mode == 1u {
+        // Round on screen too
+        return length((clip - u_cull.mouse) / u_cull.aspect) <= u_cull.region + u_cull.size;
+    }
+    return true;
+}
+
+// Example 02's ring grown into a sunflower spiral, most of it off screen
+@compute
+@workgroup_size(64)
+fn cull(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= u_cull.instances {
+        return;
+    }
+
+    // The golden angle apart
+    let a = f32(i) * 2.39996 + u_cull.time * 0.1;
+    let radius = sqrt((f32(i) + 0.5) / f32(u_cull.instances)) * 2.5;
+    let wobble = 1. + 0.02 * sin(u_cull.time * 5. + a);
+    let position = vec2<f32>(cos(a), sin(a)) * radius * wobble;
+    if !visible(position) {
+        return;
+    }
+
+    let slot = atomicAdd(&draw_args.instance_count, 1u);
+    instances[slot] = Instance(position, a, i);
+}
+
+struct InstanceInput {
+    @builtin(vertex_index) vi: u32,
+    @location(0) position: vec2<f32>,
+    @location(1) angle: f32,
+    @location(2) index: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+@vertex
+fn vs(input: InstanceInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 3>(
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(0., 0.5),
+    );
+    let c = cos(input.angle);
+    let s = sin(input.angle);
+    let corner = mat2x2<f32>(c, s, -s, c) * corners[input.vi] * u_cull.size * 2.;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>((input.position + corner) * u_cull.aspect, 0., 1.);
+    let t = f32(input.index) / f32(u_cull.instances);
+    out.color = vec3<f32>(1., 0.4 + 0.6 * t, (sin(u_cull.time * 8. + f32(input.index)) + 1.) / 2.);
+    return out;
+}
+
+@fragment
+fn fs(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.);
+}
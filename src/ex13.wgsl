@@ -0,0 +1,48 @@
+@group(0)
+@binding(0)
+var<uniform> u_time: f32;
+
+// One per draw, at a dynamic offset
+struct Object {
+    // Clip space
+    position: vec2<f32>,
+    // From the center to an edge
+    size: f32,
+    phase: f32,
+};
+
+@group(1)
+@binding(0)
+var<uniform> u_object: Object;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+// A quad from 6 vertices, spinning at its own phase
+@vertex
+fn vs(@builtin(vertex_index) vi: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1., -1.),
+        vec2<f32>(1., -1.),
+        vec2<f32>(1., 1.),
+        vec2<f32>(-1., -1.),
+        vec2<f32>(1., 1.),
+        vec2<f32>(-1., 1.),
+    );
+    let a = u_time + u_object.phase;
+    let c = cos(a);
+    let s = sin(a);
+    let corner = mat2x2<f32>(c, s, -s, c) * corners[vi] * u_object.size * 0.7;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(u_object.position + corner, 0., 1.);
+    out.color = 0.5 + 0.5 * cos(u_object.phase + vec3<f32>(0., 2., 4.));
+    return out;
+}
+
+@fragment
+fn fs(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.);
+}
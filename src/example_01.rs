@@ -3,39 +3,61 @@ use wgpu::{
     BufferUsages, CommandEncoderDescriptor, Device, FragmentState, MultisampleState, Operations,
     PipelineLayoutDescriptor, PolygonMode, PrimitiveState, RenderPassColorAttachment,
     RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderModule, TextureFormat,
-    TextureViewDescriptor, VertexAttribute, VertexBufferLayout, VertexState,
+    TextureView, VertexAttribute, VertexBufferLayout, VertexState,
 };
 use winit::event::VirtualKeyCode;
 
-use crate::{util::ExampleCommonState, Example, ExampleData};
+use crate::{
+    debug,
+    keymap::{key, Action},
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "move-up",
+        description: "Move the triangle up",
+        keys: &[key(VirtualKeyCode::Up)],
+    },
+    Action {
+        name: "move-down",
+        description: "Move the triangle down",
+        keys: &[key(VirtualKeyCode::Down)],
+    },
+    Action {
+        name: "move-left",
+        description: "Move the triangle left",
+        keys: &[key(VirtualKeyCode::Left)],
+    },
+    Action {
+        name: "move-right",
+        description: "Move the triangle right",
+        keys: &[key(VirtualKeyCode::Right)],
+    },
+];
 
 pub struct Example01 {
     common: ExampleCommonState,
-    render_pipeline: Option<RenderPipeline>,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
     vertices: [[f32; 2]; 3],
 }
 
 impl Example for Example01 {
-    fn handle_key(&mut self, key: winit::event::VirtualKeyCode) {
-        match key {
-            VirtualKeyCode::Up => {
-                self.update_vertices(0.0, 0.1);
-            }
-            VirtualKeyCode::Down => {
-                self.update_vertices(0.0, -0.1);
-            }
-            VirtualKeyCode::Left => {
-                self.update_vertices(-0.1, 0.0);
-            }
-            VirtualKeyCode::Right => {
-                self.update_vertices(0.1, 0.0);
-            }
+    fn handle_action(&mut self, action: &str) {
+        match action {
+            "move-up" => self.update_vertices(0.0, 0.1),
+            "move-down" => self.update_vertices(0.0, -0.1),
+            "move-left" => self.update_vertices(-0.1, 0.0),
+            "move-right" => self.update_vertices(0.1, 0.0),
             _ => {}
         }
     }
 
-    fn render(&mut self, data: &ExampleData) {
-        self.do_render(data);
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
     }
 
     fn common(&mut self) -> &mut ExampleCommonState {
@@ -99,7 +121,7 @@ impl Example01 {
         let common = ExampleCommonState::new(&e.device, texture_format, shader_source, "ex01");
 
         Self {
-            render_pipeline: None,
+            render_pipeline: PerPolygonMode::default(),
             vertices: [[-0.5, 0.0], [0.0, 1.0], [0.5, 0.0]],
             common,
         }
@@ -119,15 +141,20 @@ impl Example01 {
         bytemuck::cast_slice(&self.vertices)
     }
 
-    fn do_render(&mut self, e: &ExampleData) {
-        if self.common.dirty || self.render_pipeline.is_none() {
-            self.render_pipeline = Some(render_pipeline(
+    fn do_render(&mut self, e: &ExampleData, view: &TextureView) {
+        if self.common.dirty {
+            self.common.dirty = false;
+            self.render_pipeline.clear();
+        }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = render_pipeline(
                 &e.device,
                 &self.common.shader_module,
                 self.common.texture_format,
-                self.common.polygon_mode,
-            ));
-            self.common.dirty = false;
+                mode,
+            );
+            self.render_pipeline.insert(mode, pipeline);
         }
 
         let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
@@ -140,33 +167,23 @@ impl Example01 {
             usage: BufferUsages::VERTEX,
         });
 
-        let current_texture = match e.surface.get_current_texture() {
-            Ok(t) => t,
-            Err(wgpu::SurfaceError::Outdated) => return,
-            Err(e) => panic!("{e:?}"),
-        };
-        let view = &current_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-
         {
             let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
                 label: "ex01-rp".into(),
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: Operations::default(),
                 })],
-                // todo
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline.as_ref().unwrap());
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
             rpass.set_vertex_buffer(0, b.slice(..));
+            debug::marker(&mut rpass, "ex01-triangle");
             rpass.draw(0..self.vertices.len() as u32, 0..1);
         }
 
         e.queue.submit(std::iter::once(ce.finish()));
-        current_texture.present();
     }
 }
@@ -4,49 +4,71 @@ use wgpu::{
     BindGroupLayoutEntry, BufferUsages, CommandEncoderDescriptor, Device, FragmentState,
     MultisampleState, Operations, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
     RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    ShaderModule, ShaderStages, TextureFormat, TextureViewDescriptor, VertexAttribute,
-    VertexBufferLayout, VertexState,
+    ShaderModule, ShaderStages, TextureFormat, TextureView, VertexAttribute, VertexBufferLayout,
+    VertexState,
 };
 use winit::event::VirtualKeyCode;
 
-use crate::{util::ExampleCommonState, Example, ExampleData};
+use crate::{
+    debug,
+    keymap::{key, Action},
+    params::Param,
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "shrink",
+        description: "Smaller circle of instances",
+        keys: &[key(VirtualKeyCode::A)],
+    },
+    Action {
+        name: "grow",
+        description: "Bigger circle of instances",
+        keys: &[key(VirtualKeyCode::D)],
+    },
+];
+
+// See `params.rs`
+pub const PARAMS: &[Param] = &[
+    Param::f32(
+        "radius",
+        "Radius of the circle of instances",
+        0.3,
+        0.1,
+        2.,
+        0.1,
+    ),
+    Param::u32("instances", "Number of instances", 10, 3, 100, 1),
+];
 
 pub struct Example02 {
     common: ExampleCommonState,
-    render_pipeline: Option<RenderPipeline>,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
     bgl0: BindGroupLayout,
     vertices: [[f32; 2]; 3],
-    num_instances: u32,
-    radius: f32,
 }
 
 impl Example for Example02 {
-    fn handle_key(&mut self, key: winit::event::VirtualKeyCode) {
-        // Use Up/Down to switch between polygon modes.
-        // Remove the render pipeline such that it's recreated
-        // later (needed to apply new mode).
-        match key {
-            VirtualKeyCode::A => {
-                self.radius = (self.radius - 0.1).max(0.1);
-            }
-            VirtualKeyCode::D => {
-                self.radius = (self.radius + 0.1).min(2.);
-            }
+    fn handle_action(&mut self, action: &str) {
+        match action {
+            "shrink" => self.common.params.adjust("radius", -1),
+            "grow" => self.common.params.adjust("radius", 1),
             _ => {}
         }
     }
 
-    fn render(&mut self, data: &ExampleData) {
-        self.do_render(data);
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
     }
 
     fn handle_scroll(&mut self, scroll_up: bool) {
-        if scroll_up {
-            self.num_instances = (self.num_instances + 1).min(100);
-        } else {
-            self.num_instances = self.num_instances.saturating_sub(1).max(3);
-        }
-        dbg!(&self.num_instances);
+        let params = &mut self.common.params;
+        params.adjust("instances", if scroll_up { 1 } else { -1 });
+        dbg!(params.u32("instances"));
     }
 
     fn common(&mut self) -> &mut ExampleCommonState {
@@ -65,11 +87,11 @@ fn render_pipeline(
         label: "ex02-rpd".into(),
         layout: Some(&device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: "ex02-pld".into(),
-            bind_group_layouts: &[&bgl],
+            bind_group_layouts: &[bgl],
             push_constant_ranges: &[],
         })),
         vertex: VertexState {
-            module: &shader_module,
+            module: shader_module,
             entry_point: "vs",
             // todo: query set later and swap order and see if there is a diff?
             buffers: &[
@@ -86,7 +108,7 @@ fn render_pipeline(
             ],
         },
         fragment: Some(FragmentState {
-            module: &shader_module,
+            module: shader_module,
             entry_point: "fs",
             // what if several targets? just have to match in render pass?
             targets: &[Some(texture_format.into())],
@@ -162,11 +184,9 @@ impl Example02 {
             });
 
         Self {
-            render_pipeline: None,
+            render_pipeline: PerPolygonMode::default(),
             vertices: [[-0.5, 0.0], [0.0, 1.0], [0.5, 0.0]],
             bgl0,
-            num_instances: 10,
-            radius: 0.3,
             common,
         }
     }
@@ -175,16 +195,21 @@ impl Example02 {
         bytemuck::cast_slice(&self.vertices)
     }
 
-    pub fn do_render(&mut self, e: &ExampleData) {
-        if self.common.dirty || self.render_pipeline.is_none() {
-            self.render_pipeline = Some(render_pipeline(
+    pub fn do_render(&mut self, e: &ExampleData, view: &TextureView) {
+        if self.common.dirty {
+            self.common.dirty = false;
+            self.render_pipeline.clear();
+        }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = render_pipeline(
                 &e.device,
                 &self.common.shader_module,
                 &self.bgl0,
                 self.common.texture_format,
-                self.common.polygon_mode,
-            ));
-            self.common.dirty = false;
+                mode,
+            );
+            self.render_pipeline.insert(mode, pipeline);
         }
 
         let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
@@ -197,14 +222,17 @@ impl Example02 {
             usage: BufferUsages::VERTEX,
         });
 
+        let num_instances = self.common.params.u32("instances");
+        let radius = self.common.params.f32("radius");
+
         let num_instances_buf = e.device.create_buffer_init(&BufferInitDescriptor {
             label: "ex02-uni-ninst".into(),
-            contents: self.num_instances.to_le_bytes().as_ref(),
+            contents: num_instances.to_le_bytes().as_ref(),
             usage: BufferUsages::UNIFORM,
         });
         let radius_buf = e.device.create_buffer_init(&BufferInitDescriptor {
             label: "ex02-uni-radius".into(),
-            contents: self.radius.to_le_bytes().as_ref(),
+            contents: radius.to_le_bytes().as_ref(),
             usage: BufferUsages::UNIFORM,
         });
         let mouse_buf = e.device.create_buffer_init(&BufferInitDescriptor {
@@ -240,30 +268,25 @@ impl Example02 {
                 },
             ],
         });
-        let current_texture = e.surface.get_current_texture().unwrap();
-        let view = &current_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
 
         {
             let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
                 label: "ex02-rp".into(),
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: Operations::default(),
                 })],
-                // todo
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline.as_ref().unwrap());
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
             rpass.set_vertex_buffer(0, index_buf.slice(..));
             rpass.set_bind_group(0, &bg0, &[]);
-            rpass.draw(0..self.vertices.len() as u32, 0..self.num_instances);
+            debug::marker(&mut rpass, &format!("ex02-ring x{num_instances}"));
+            rpass.draw(0..self.vertices.len() as u32, 0..num_instances);
         }
 
         e.queue.submit(std::iter::once(ce.finish()));
-        current_texture.present();
     }
 }
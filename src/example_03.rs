@@ -12,92 +12,321 @@ Goals:
     - When click + near, should attach to vertex somehow to be able to swap
         - Done
 
+    POLYGON EDITOR
+    - Any number of vertices instead of a hard-coded quad
+        - Click on an edge to insert a vertex there, delete the hovered vertex with a key
+    - Triangulate via ear clipping into an index buffer
+    - Save/load the polygon, undo/redo edits
+
 Things we learned:
     - The builtin position is transformed into another coord space when moving from vertex to frag shaders.
     If we want to relate things like mouse (clip space), we then have to do some math
     - An array cannot have stride length 8 (e.g. array<vec2<f32>> will fail), it must have alignment 16
+        - That's for uniforms. In a storage buffer `array<vec2<f32>>` is fine.
     - An array should be array<vec4<f32>, 4> to have 4 elements, then it gets the SIZED flag
+        - A storage buffer can instead end in a runtime sized array, and `arrayLength` tells how many elements it got.
+        So the vertex count doesn't have to be baked into the WGSL.
     - It's hard to think in terms of single fragments vs. the whole frag shader
+    - When drawing indexed, `vertex_index` is the value from the index buffer.
+        So no vertex buffer is needed, the vertex shader can look the position up in the storage buffer.
+    - A triangle strip can't describe an arbitrary polygon, but a triangle list and indices from ear clipping can.
+        Polygon mode line shows the triangulation.
+    - Ear clipping assumes a simple polygon (no self intersections). If no ear is found we just fan what's left.
+    - Clicks used to arrive in window pixels while the vertices are in clip space, so nothing was ever close enough.
+        Input events now carry every coordinate space, see `input.rs`.
  */
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
     BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BufferUsages, CommandEncoderDescriptor, FragmentState, MultisampleState,
-    Operations, PipelineLayoutDescriptor, PrimitiveState, PushConstantRange,
+    BindGroupLayoutEntry, BufferUsages, CommandEncoderDescriptor, FragmentState, IndexFormat,
+    MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState, PushConstantRange,
     RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    ShaderStages, TextureViewDescriptor, VertexAttribute, VertexBufferLayout, VertexState,
+    ShaderStages, TextureView, VertexState,
+};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    input::{InputEvent, MouseButton},
+    keymap::{ctrl, key, Action},
+    params::Param,
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
 };
 
-use crate::{util::ExampleCommonState, Example, ExampleData};
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "delete",
+        description: "Remove the hovered vertex",
+        keys: &[key(VirtualKeyCode::Delete), key(VirtualKeyCode::Back)],
+    },
+    Action {
+        name: "undo",
+        description: "Undo",
+        keys: &[key(VirtualKeyCode::Z), ctrl(VirtualKeyCode::Z)],
+    },
+    Action {
+        name: "redo",
+        description: "Redo",
+        keys: &[key(VirtualKeyCode::Y), ctrl(VirtualKeyCode::Y)],
+    },
+    Action {
+        name: "save",
+        description: "Save the polygon",
+        keys: &[key(VirtualKeyCode::F5)],
+    },
+    Action {
+        name: "load",
+        description: "Load the polygon",
+        keys: &[key(VirtualKeyCode::F9)],
+    },
+];
+
+// See `params.rs`
+pub const PARAMS: &[Param] = &[Param::f32(
+    "threshold",
+    "How close (clip space) the mouse must be to select a vertex or an edge",
+    0.2,
+    0.1,
+    0.3,
+    0.01,
+)];
+
+// Where the polygon is saved to and loaded from.
+// Text, one vertex per line: "x y" in clip space.
+const POLYGON_PATH: &str = "ex03-polygon.txt";
 
 pub struct Example03 {
     common: ExampleCommonState,
-    render_pipeline: Option<RenderPipeline>,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
     bgl0: BindGroupLayout,
-    vertices: [[f32; 2]; 4],
+
+    // The polygon outline in clip space, in order
+    vertices: Vec<[f32; 2]>,
+
+    // Undo/redo of `vertices`
+    history: History,
 
     // Index of selected vertex if any
     selected_vertex: Option<u32>,
 
-    mouse_close_threshold: f32,
+    // Clip space
+    mouse: [f32; 2],
 }
 
 impl Example for Example03 {
-    fn handle_key(&mut self, _key: winit::event::VirtualKeyCode) {}
+    fn handle_action(&mut self, action: &str) {
+        match action {
+            "delete" => self.delete_hovered(),
+            "undo" => self.undo(),
+            "redo" => self.redo(),
+            "save" => self.save(),
+            "load" => self.load(),
+            _ => {}
+        }
+    }
+
+    // A drag in progress would otherwise continue when we're back, without the button held
+    fn on_exit(&mut self) {
+        self.grab(false);
+    }
 
     fn handle_scroll(&mut self, scroll_up: bool) {
-        if scroll_up {
-            self.mouse_close_threshold = (self.mouse_close_threshold + 0.01).min(0.3);
-        } else {
-            self.mouse_close_threshold = (self.mouse_close_threshold - 0.01).max(0.1);
-        }
+        let steps = if scroll_up { 1 } else { -1 };
+        self.common.params.adjust("threshold", steps);
     }
 
-    fn render(&mut self, data: &ExampleData) {
-        self.do_render(data);
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
     }
 
     fn common(&mut self) -> &mut ExampleCommonState {
         &mut self.common
     }
 
-    fn handle_click(&mut self, position: [f32; 2], pressed: bool) {
-        dbg!(pressed, position);
+    fn handle_input(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::CursorMoved { position } => {
+                self.mouse = position.clip;
 
-        if pressed {
+                // If something is selected, move that vertex to where the mouse is
+                if let Some(vi) = self.selected_vertex {
+                    self.vertices[vi as usize] = self.mouse;
+                }
+            }
+            InputEvent::Button {
+                button: MouseButton::Left,
+                pressed,
+                position,
+                ..
+            } => {
+                self.mouse = position.clip;
+                self.grab(pressed);
+            }
+            InputEvent::Button {
+                button: MouseButton::Right,
+                pressed: true,
+                position,
+                ..
+            } => {
+                self.mouse = position.clip;
+                self.delete_hovered();
+            }
+            InputEvent::Scroll { .. } => {
+                if let Some(scroll_up) = event.scroll_up() {
+                    self.handle_scroll(scroll_up);
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
-        for (i, v) in self.vertices.iter().enumerate() {
-            let len = 
-                // Length of vertex index i to mouse
-                ((position[0] - v[0]).powf(2.0) + (position[1] - v[1]).powf(2.0)).sqrt();
-            let close_enough = len < self.mouse_close_threshold;
-            dbg!(
-                i,
-                len,
-                close_enough
-            );
+// Previous and undone polygons.
+// A drag is one edit, made when the mouse is released. Other edits, undo and redo drop a drag in progress,
+// such that the release doesn't record the polygon from before them.
+#[derive(Default)]
+struct History {
+    undo: Vec<Vec<[f32; 2]>>,
+    redo: Vec<Vec<[f32; 2]>>,
+    // The polygon when the mouse was pressed, undoable once the release finds it changed
+    grabbed: Option<Vec<[f32; 2]>>,
+}
 
-            if close_enough {
-                self.selected_vertex = Some(i as u32);
+impl History {
+    fn grab(&mut self, vertices: &[[f32; 2]]) {
+        self.grabbed = Some(vertices.to_vec());
+    }
+
+    // A click without a drag shouldn't be something to undo, nor lose the redos
+    fn release(&mut self, vertices: &[[f32; 2]]) {
+        if let Some(grabbed) = self.grabbed.take() {
+            if grabbed != vertices {
+                self.undo.push(grabbed);
+                self.redo.clear();
             }
         }
-        } else {
-            self.selected_vertex = None;
-        }
+    }
+
+    // Call with the polygon before changing it
+    fn edit(&mut self, vertices: &[[f32; 2]]) {
+        self.grabbed = None;
+        self.undo.push(vertices.to_vec());
+        self.redo.clear();
+    }
 
+    // Whether there was something to undo
+    fn undo(&mut self, vertices: &mut Vec<[f32; 2]>) -> bool {
+        let Some(previous) = self.undo.pop() else {
+            return false;
+        };
+        self.grabbed = None;
+        self.redo.push(std::mem::replace(vertices, previous));
+        true
+    }
+
+    // Whether there was something to redo
+    fn redo(&mut self, vertices: &mut Vec<[f32; 2]>) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.grabbed = None;
+        self.undo.push(std::mem::replace(vertices, next));
+        true
     }
 }
 
-impl Example03 {
-    fn vertices_align16(&self) -> [[f32; 4]; 4] {
-        [
-            [self.vertices[0][0], self.vertices[0][1], 0.0, 0.0],
-            [self.vertices[1][0], self.vertices[1][1], 0.0, 0.0],
-            [self.vertices[2][0], self.vertices[2][1], 0.0, 0.0],
-            [self.vertices[3][0], self.vertices[3][1], 0.0, 0.0],
-        ]
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]).powf(2.0) + (a[1] - b[1]).powf(2.0)).sqrt()
+}
+
+// Z of the cross product of (b - a) and (c - a).
+// Positive if a, b, c turn counter clockwise.
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+// The point on the segment a-b closest to p
+fn closest_on_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let len2 = ab[0] * ab[0] + ab[1] * ab[1];
+    if len2 == 0.0 {
+        return a;
+    }
+    let t = (((p[0] - a[0]) * ab[0] + (p[1] - a[1]) * ab[1]) / len2).clamp(0.0, 1.0);
+    [a[0] + t * ab[0], a[1] + t * ab[1]]
+}
+
+// Ear clipping: Repeatedly cut off a convex corner which has no other vertex inside it.
+// Gives 3 * (n - 2) indices for n vertices.
+fn triangulate(vertices: &[[f32; 2]]) -> Vec<u32> {
+    let n = vertices.len();
+    let signed_area: f32 = (0..n)
+        .map(|i| {
+            let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum();
+
+    // Walk counter clockwise, so convex corners are the ones turning left
+    let mut remaining: Vec<usize> = (0..n).collect();
+    if signed_area < 0.0 {
+        remaining.reverse();
     }
 
+    let mut indices = Vec::with_capacity(3 * n.saturating_sub(2));
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let corner = |i: usize| {
+            (
+                remaining[(i + len - 1) % len],
+                remaining[i],
+                remaining[(i + 1) % len],
+            )
+        };
+
+        let ear = (0..len).find(|&i| {
+            let (a, b, c) = corner(i);
+            let (pa, pb, pc) = (vertices[a], vertices[b], vertices[c]);
+
+            if cross(pa, pb, pc) <= 0.0 {
+                return false;
+            }
+
+            !remaining
+                .iter()
+                .filter(|&&p| p != a && p != b && p != c)
+                .any(|&p| {
+                    let p = vertices[p];
+                    cross(pa, pb, p) >= 0.0 && cross(pb, pc, p) >= 0.0 && cross(pc, pa, p) >= 0.0
+                })
+        });
+
+        let Some(ear) = ear else {
+            // Self intersecting, fan the rest below
+            break;
+        };
+
+        let (a, b, c) = corner(ear);
+        indices.extend([a as u32, b as u32, c as u32]);
+        remaining.remove(ear);
+    }
+
+    for i in 1..remaining.len().saturating_sub(1) {
+        indices.extend([
+            remaining[0] as u32,
+            remaining[i] as u32,
+            remaining[i + 1] as u32,
+        ]);
+    }
+
+    indices
+}
+
+impl Example03 {
     pub fn new(e: &ExampleData) -> Self {
         let shader_source = "ex03.wgsl";
         let texture_format = e.swapchain_format;
@@ -119,12 +348,12 @@ impl Example03 {
                         },
                         count: None,
                     },
-                    // Quad
+                    // Polygon vertices
                     BindGroupLayoutEntry {
                         binding: 1,
-                        visibility: ShaderStages::FRAGMENT,
+                        visibility: ShaderStages::VERTEX_FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
                             has_dynamic_offset: false,
                             min_binding_size: None,
                         },
@@ -155,30 +384,140 @@ impl Example03 {
                 ],
             });
 
-        // Triangle strip: https://docs.rs/wgpu/latest/wgpu/enum.PrimitiveTopology.html#variant.TriangleStrip
-        // Says vertices 0 1 2 3 will lead to two triangles:
-        //
-        //  - 0 1 2
-        //  - 2 1 3
-        //
-        // So then we make a quad by assigning vertex indices as such:
-        //  0 = top left
-        //  1 = bottom left
-        //  2 = top right
-        //  3 = bottom right
-        let vertices = [[-0.5, 0.5], [-0.5, -0.5], [0.5, 0.5], [0.5, -0.5]];
-
+        // Start out with the quad from before, but as an outline (counter clockwise)
+        // instead of in triangle strip order
+        let vertices = vec![[-0.5, 0.5], [-0.5, -0.5], [0.5, -0.5], [0.5, 0.5]];
 
         Self {
-            render_pipeline: None,
+            render_pipeline: PerPolygonMode::default(),
             bgl0,
             vertices,
+            history: History::default(),
             common,
-            mouse_close_threshold: 0.2,
             selected_vertex: None,
+            mouse: [0., 0.],
+        }
+    }
+
+    // Pressed: Select a vertex, or insert one on an edge and select that
+    fn grab(&mut self, pressed: bool) {
+        if !pressed {
+            self.selected_vertex = None;
+
+            self.history.release(&self.vertices);
+            return;
+        }
+
+        if let Some(vi) = self.hovered_vertex(self.mouse) {
+            self.history.grab(&self.vertices);
+            self.selected_vertex = Some(vi as u32);
+        } else if let Some((edge, point)) = self.hovered_edge(self.mouse) {
+            self.history.grab(&self.vertices);
+            self.vertices.insert(edge + 1, point);
+            self.selected_vertex = Some(edge as u32 + 1);
+        }
+    }
+
+    // Need at least a triangle
+    fn delete_hovered(&mut self) {
+        if self.vertices.len() <= 3 {
+            return;
+        }
+        if let Some(vi) = self.hovered_vertex(self.mouse) {
+            self.edit();
+            self.vertices.remove(vi);
+        }
+    }
+
+    // Call before changing the polygon, drops a drag in progress
+    fn edit(&mut self) {
+        self.history.edit(&self.vertices);
+        self.selected_vertex = None;
+    }
+
+    fn undo(&mut self) {
+        if self.history.undo(&mut self.vertices) {
+            self.selected_vertex = None;
         }
     }
 
+    fn redo(&mut self) {
+        if self.history.redo(&mut self.vertices) {
+            self.selected_vertex = None;
+        }
+    }
+
+    fn save(&self) {
+        let text: String = self
+            .vertices
+            .iter()
+            .map(|[x, y]| format!("{x} {y}\n"))
+            .collect();
+
+        match std::fs::write(POLYGON_PATH, text) {
+            Ok(()) => println!("Saved {} vertices to {POLYGON_PATH}", self.vertices.len()),
+            Err(err) => println!("Could not save polygon to {POLYGON_PATH}: {err}"),
+        }
+    }
+
+    fn load(&mut self) {
+        let text = match std::fs::read_to_string(POLYGON_PATH) {
+            Ok(text) => text,
+            Err(err) => {
+                println!("Could not load polygon from {POLYGON_PATH}: {err}");
+                return;
+            }
+        };
+
+        let vertices: Option<Vec<[f32; 2]>> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut xy = line.split_whitespace().map(|v| v.parse::<f32>().ok());
+                match (xy.next(), xy.next(), xy.next()) {
+                    (Some(Some(x)), Some(Some(y)), None) => Some([x, y]),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        match vertices {
+            Some(vertices) if vertices.len() >= 3 => {
+                println!("Loaded {} vertices from {POLYGON_PATH}", vertices.len());
+                self.edit();
+                self.vertices = vertices;
+            }
+            _ => println!("{POLYGON_PATH} should have at least three lines of \"x y\""),
+        }
+    }
+
+    // The vertex closest to the mouse, if close enough
+    fn hovered_vertex(&self, mouse: [f32; 2]) -> Option<usize> {
+        let threshold = self.common.params.f32("threshold");
+        self.vertices
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (i, distance(mouse, v)))
+            .filter(|&(_, d)| d < threshold)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    // The edge closest to the mouse if close enough, as the index of its first vertex,
+    // and the point on it closest to the mouse
+    fn hovered_edge(&self, mouse: [f32; 2]) -> Option<(usize, [f32; 2])> {
+        let threshold = self.common.params.f32("threshold");
+        let n = self.vertices.len();
+        (0..n)
+            .map(|i| {
+                let p = closest_on_segment(mouse, self.vertices[i], self.vertices[(i + 1) % n]);
+                (i, p, distance(mouse, p))
+            })
+            .filter(|&(_, _, d)| d < threshold)
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+            .map(|(i, p, _)| (i, p))
+    }
+
     fn make_render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
         e.device.create_render_pipeline(&RenderPipelineDescriptor {
             label: "ex03-rpassd".into(),
@@ -193,18 +532,8 @@ impl Example03 {
             vertex: VertexState {
                 module: &self.common.shader_module,
                 entry_point: "vs",
-                buffers: &[
-                    // The triangle vertices
-                    VertexBufferLayout {
-                        array_stride: wgpu::VertexFormat::Float32x2.size(),
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &[VertexAttribute {
-                            format: wgpu::VertexFormat::Float32x2,
-                            offset: 0,
-                            shader_location: 0,
-                        }],
-                    },
-                ],
+                // Vertices are pulled from the storage buffer
+                buffers: &[],
             },
             fragment: Some(FragmentState {
                 module: &self.common.shader_module,
@@ -213,7 +542,7 @@ impl Example03 {
                 targets: &[Some(e.swapchain_format.into())],
             }),
             primitive: PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                topology: wgpu::PrimitiveTopology::TriangleList,
                 polygon_mode: self.common.polygon_mode,
                 ..Default::default()
             },
@@ -224,30 +553,37 @@ impl Example03 {
         })
     }
 
-    pub fn do_render(&mut self, e: &ExampleData) {
-        if self.common.dirty || self.render_pipeline.is_none() {
-            self.render_pipeline = Some(self.make_render_pipeline(&e));
+    pub fn do_render(&mut self, e: &ExampleData, view: &TextureView) {
+        if self.common.dirty {
             self.common.dirty = false;
+            self.render_pipeline.clear();
         }
-        
-        // If something is selected, move that vertex to where the mouse is
-        if let Some(vi) = self.selected_vertex {
-            self.vertices[vi as usize] = e.mouse_clip_space();
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = self.make_render_pipeline(e);
+            self.render_pipeline.insert(mode, pipeline);
         }
 
+        let indices = triangulate(&self.vertices);
+
         let viewport_buf = e.device.create_buffer_init(&BufferInitDescriptor {
             label: "ex03-uni-viewport".into(),
             contents: bytemuck::cast_slice(e.viewport.as_slice()),
             usage: BufferUsages::UNIFORM,
         });
-        let quad_buf = e.device.create_buffer_init(&BufferInitDescriptor {
-            label: "ex03-uni-quad".into(),
-            contents: bytemuck::cast_slice(self.vertices_align16().as_slice()),
-            usage: BufferUsages::UNIFORM,
+        let vertices_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex03-sto-vertices".into(),
+            contents: bytemuck::cast_slice(self.vertices.as_slice()),
+            usage: BufferUsages::STORAGE,
+        });
+        let index_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex03-indices".into(),
+            contents: bytemuck::cast_slice(indices.as_slice()),
+            usage: BufferUsages::INDEX,
         });
         let mouse_buf = e.device.create_buffer_init(&BufferInitDescriptor {
             label: "ex03-uni-mouse".into(),
-            contents: bytemuck::cast_slice(e.mouse_clip_space().as_slice()),
+            contents: bytemuck::cast_slice(self.mouse.as_slice()),
             usage: BufferUsages::UNIFORM,
         });
         let time_buf = e.device.create_buffer_init(&BufferInitDescriptor {
@@ -261,18 +597,6 @@ impl Example03 {
             label: "ex03-ce".into(),
         });
 
-        let quad = e.device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("ex03-quad"),
-            contents: bytemuck::cast_slice(self.vertices.as_slice()),
-            usage: BufferUsages::VERTEX,
-        });
-
-        // Render pass resources
-        let current_texture = e.surface.get_current_texture().unwrap();
-        let view = &current_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-
         let bg0 = e.device.create_bind_group(&BindGroupDescriptor {
             label: "ex03-bg-0".into(),
             layout: &self.bgl0,
@@ -283,7 +607,7 @@ impl Example03 {
                 },
                 BindGroupEntry {
                     binding: 1,
-                    resource: quad_buf.as_entire_binding(),
+                    resource: vertices_buf.as_entire_binding(),
                 },
                 BindGroupEntry {
                     binding: 2,
@@ -301,27 +625,143 @@ impl Example03 {
             let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
                 label: "ex03-rp".into(),
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: Operations::default(),
                 })],
-                // todo
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline.as_ref().unwrap());
-            rpass.set_vertex_buffer(0, quad.slice(..));
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
+            rpass.set_index_buffer(index_buf.slice(..), IndexFormat::Uint32);
             rpass.set_bind_group(0, &bg0, &[]);
 
             rpass.set_push_constants(
                 ShaderStages::FRAGMENT,
                 0,
-                self.mouse_close_threshold.to_le_bytes().as_ref(),
+                self.common.params.f32("threshold").to_le_bytes().as_ref(),
+            );
+            debug::marker(
+                &mut rpass,
+                &format!("ex03-polygon {} triangles", indices.len() / 3),
             );
-            rpass.draw(0..4, 0..1);
+            rpass.draw_indexed(0..indices.len() as u32, 0, 0..1);
         }
 
         e.queue.submit(std::iter::once(ce.finish()));
-        current_texture.present();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Twice the area of each triangle, which all have to turn the same way
+    fn doubled_areas(vertices: &[[f32; 2]], indices: &[u32]) -> Vec<f32> {
+        indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| vertices[t[i] as usize]);
+                cross(a, b, c)
+            })
+            .collect()
+    }
+
+    fn assert_covers(vertices: &[[f32; 2]], doubled_area: f32) {
+        let indices = triangulate(vertices);
+        assert_eq!(indices.len(), 3 * (vertices.len() - 2));
+        let areas = doubled_areas(vertices, &indices);
+        assert!(
+            areas.iter().all(|&a| a >= 0.) || areas.iter().all(|&a| a <= 0.),
+            "{areas:?}"
+        );
+        let total: f32 = areas.iter().map(|a| a.abs()).sum();
+        assert!(
+            (total - doubled_area).abs() < 1e-5,
+            "{total} vs {doubled_area}"
+        );
+    }
+
+    #[test]
+    fn convex() {
+        let square = [[-0.5, 0.5], [-0.5, -0.5], [0.5, -0.5], [0.5, 0.5]];
+        assert_covers(&square, 2.);
+        // Clockwise too
+        let mut clockwise = square;
+        clockwise.reverse();
+        assert_covers(&clockwise, 2.);
+    }
+
+    #[test]
+    fn concave() {
+        // An L, the corner at (0, 0) points inwards
+        let l = [
+            [0., 0.],
+            [0., 1.],
+            [-1., 1.],
+            [-1., -1.],
+            [1., -1.],
+            [1., 0.],
+        ];
+        assert_covers(&l, 6.);
+        // Counter clockwise, none flipped or degenerate
+        let indices = triangulate(&l);
+        assert!(doubled_areas(&l, &indices).iter().all(|&a| a > 0.));
+    }
+
+    #[test]
+    fn collinear() {
+        // A vertex in the middle of the bottom edge, and one in the middle of the right edge
+        let square = [
+            [-1., -1.],
+            [0., -1.],
+            [1., -1.],
+            [1., 0.],
+            [1., 1.],
+            [-1., 1.],
+        ];
+        assert_covers(&square, 8.);
+    }
+
+    #[test]
+    fn undo_during_drag() {
+        let mut history = History::default();
+        let mut vertices = vec![[0., 0.], [1., 0.], [0., 1.]];
+        history.edit(&vertices);
+        vertices.push([1., 1.]);
+
+        // Undo while dragging the new vertex, then release
+        history.grab(&vertices);
+        vertices[3] = [2., 2.];
+        assert!(history.undo(&mut vertices));
+        history.release(&vertices);
+        assert_eq!(vertices.len(), 3);
+        assert_eq!(history.undo.len(), 0);
+
+        // What was undone (as dragged so far) is still there to redo
+        assert!(history.redo(&mut vertices));
+        assert_eq!(vertices, [[0., 0.], [1., 0.], [0., 1.], [2., 2.]]);
+    }
+
+    #[test]
+    fn edit_during_drag() {
+        let mut history = History::default();
+        let mut vertices = vec![[0., 0.], [1., 0.], [0., 1.], [1., 1.]];
+
+        // Delete a vertex while dragging another, then release: One edit
+        history.grab(&vertices);
+        vertices[0] = [-1., -1.];
+        history.edit(&vertices);
+        vertices.remove(3);
+        history.release(&vertices);
+        assert_eq!(
+            history.undo,
+            [vec![[-1., -1.], [1., 0.], [0., 1.], [1., 1.]]]
+        );
+
+        // A click without a drag isn't one
+        history.grab(&vertices);
+        history.release(&vertices);
+        assert_eq!(history.undo.len(), 1);
     }
 }
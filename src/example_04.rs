@@ -17,26 +17,112 @@ Things we learned:
         If we use two color targets and both use the winit window texture as view, we don't get any complaints.
         It seems that then the second location overwrites the first.
 
-    - We need to handle resize if we render offline (TODO).
+    - We need to handle resize if we render offline.
+        Done by recreating the attachments when the size changes.
+
+    - Formats can differ between attachments. WebGPU limits the total bytes per sample across them all to 32,
+        we use 4 + 8 + 4 + 4.
+
+    - Integer formats (R32Uint) can't be filtered or blended.
+        They are bound as `texture_2d<u32>` and read via `textureLoad`, and the vertex output needs `@interpolate(flat)`.
+
+    - To see the attachments without RenderDoc we need a second pass which reads them as textures and draws to the screen.
  */
 use wgpu::{
-    ColorWrites, CommandEncoderDescriptor, Extent3d, FragmentState, MultisampleState, Operations,
-    PipelineLayoutDescriptor, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
-    RenderPipeline, RenderPipelineDescriptor, Texture, TextureDescriptor, TextureUsages,
-    TextureViewDescriptor, VertexState,
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, ColorTargetState, ColorWrites, CommandEncoderDescriptor, FragmentState,
+    MultisampleState, Operations, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
+    PushConstantRange, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, ShaderStages, Texture, TextureDescriptor, TextureFormat,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, VertexState,
+};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    keymap::{key, Action},
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
 };
 
-use crate::{util::ExampleCommonState, Example, ExampleData};
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[Action {
+    name: "next-attachment",
+    description: "Cycle which attachment is shown, or all of them",
+    keys: &[key(VirtualKeyCode::Space)],
+}];
+
+// The attachments written by the first pass, in `@location` order.
+// The first one uses the swapchain format.
+const ATTACHMENTS: [(&str, &str); 4] = [
+    ("color", "swapchain format, shown as is"),
+    (
+        "position",
+        "Rgba16Float clip space position, xy mapped from -1..1 to 0..1",
+    ),
+    (
+        "primitive id",
+        "R32Uint triangle index + 1 (0 is background), hashed to a color",
+    ),
+    (
+        "barycentric",
+        "Rg16Float first two barycentric coordinates, as red and green",
+    ),
+];
+
+// Which attachment(s) to show on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Show {
+    // All of them side by side
+    All,
+    Single(usize),
+}
+
+impl Show {
+    fn next(self) -> Self {
+        match self {
+            Show::All => Show::Single(0),
+            Show::Single(i) if i + 1 < ATTACHMENTS.len() => Show::Single(i + 1),
+            Show::Single(_) => Show::All,
+        }
+    }
+
+    // See `show` in ex04.wgsl
+    fn push_constant(self) -> u32 {
+        match self {
+            Show::All => ATTACHMENTS.len() as u32,
+            Show::Single(i) => i as u32,
+        }
+    }
+}
 
 pub struct Example04 {
     common: ExampleCommonState,
-    render_pipeline: Option<RenderPipeline>,
-    offscreen: Texture,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
+    show_pipeline: Option<RenderPipeline>,
+    bgl0: BindGroupLayout,
+    // One per entry in `ATTACHMENTS`
+    attachments: Vec<Texture>,
+    show: Show,
 }
 
 impl Example for Example04 {
-    fn render(&mut self, data: &ExampleData) {
-        self.do_render(data);
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
+    }
+
+    fn handle_action(&mut self, action: &str) {
+        if action == "next-attachment" {
+            self.show = self.show.next();
+            match self.show {
+                Show::All => println!("Showing all attachments"),
+                Show::Single(i) => {
+                    let (name, description) = ATTACHMENTS[i];
+                    println!("Showing {name}: {description}");
+                }
+            }
+        }
     }
 
     fn common(&mut self) -> &mut ExampleCommonState {
@@ -44,48 +130,82 @@ impl Example for Example04 {
     }
 }
 
+fn attachment_formats(swapchain_format: TextureFormat) -> [TextureFormat; 4] {
+    [
+        swapchain_format,
+        TextureFormat::Rgba16Float,
+        TextureFormat::R32Uint,
+        TextureFormat::Rg16Float,
+    ]
+}
+
+fn make_attachments(e: &ExampleData) -> Vec<Texture> {
+    attachment_formats(e.swapchain_format)
+        .iter()
+        .zip(ATTACHMENTS)
+        .map(|(&format, (name, _))| {
+            e.device.create_texture(&TextureDescriptor {
+                label: Some(&format!("ex04-{name}")),
+                size: e.extent_3d(),
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        })
+        .collect()
+}
+
 impl Example04 {
     pub fn new(e: &ExampleData) -> Self {
         let shader_source = "ex04.wgsl";
         let texture_format = e.swapchain_format;
         let common = ExampleCommonState::new(&e.device, texture_format, shader_source, "ex04");
 
-        let offscreen = e.device.create_texture(&TextureDescriptor {
-            label: "offscreen".into(),
-            size: Extent3d {
-                width: e.viewport[0] as u32,
-                height: e.viewport[1] as u32,
-                depth_or_array_layers: 1,
+        let texture_entry = |binding, sample_type| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
             },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: texture_format,
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
-        });
+            count: None,
+        };
+        // Only loaded, not sampled, so nothing needs to be filterable
+        let float = TextureSampleType::Float { filterable: false };
+        let bgl0 = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex04-bgl0".into(),
+                entries: &[
+                    texture_entry(0, float),
+                    texture_entry(1, float),
+                    texture_entry(2, TextureSampleType::Uint),
+                    texture_entry(3, float),
+                ],
+            });
 
         Self {
-            render_pipeline: None,
+            render_pipeline: PerPolygonMode::default(),
+            show_pipeline: None,
             common,
-            offscreen,
+            bgl0,
+            attachments: make_attachments(e),
+            show: Show::All,
         }
     }
 
     fn make_render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
-        let texture_format = e.swapchain_format;
-
-        let cts1 = wgpu::ColorTargetState {
-            format: texture_format,
-            blend: None,
-            write_mask: ColorWrites::all(),
-        };
-        let cts2 = wgpu::ColorTargetState {
-            format: texture_format,
-            blend: None,
-            // See ex04.wgsl
-            write_mask: ColorWrites::GREEN | ColorWrites::BLUE,
-        };
+        let targets = attachment_formats(e.swapchain_format).map(|format| {
+            Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::all(),
+            })
+        });
 
         e.device.create_render_pipeline(&RenderPipelineDescriptor {
             label: "ex04-rpassd".into(),
@@ -103,7 +223,7 @@ impl Example04 {
                 module: &self.common.shader_module,
                 entry_point: "fs",
                 // This is what we want to poke at: We now have more than one of these
-                targets: &[Some(cts1), Some(cts2)],
+                targets: &targets,
             }),
             primitive: PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
@@ -116,10 +236,54 @@ impl Example04 {
         })
     }
 
-    pub fn do_render(&mut self, e: &ExampleData) {
-        if self.common.dirty || self.render_pipeline.is_none() {
-            self.render_pipeline = Some(self.make_render_pipeline(&e));
+    fn make_show_pipeline(&self, e: &ExampleData) -> RenderPipeline {
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: "ex04-show-rpassd".into(),
+            layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: "ex04-show-rpass-pld".into(),
+                bind_group_layouts: &[&self.bgl0],
+                push_constant_ranges: &[PushConstantRange {
+                    stages: ShaderStages::FRAGMENT,
+                    range: 0..4,
+                }],
+            })),
+            vertex: VertexState {
+                module: &self.common.shader_module,
+                entry_point: "vs_show",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &self.common.shader_module,
+                entry_point: "fs_show",
+                targets: &[Some(e.swapchain_format.into())],
+            }),
+            primitive: PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // Full screen, the polygon mode only applies to the first pass
+                polygon_mode: PolygonMode::Fill,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        if self.common.dirty || self.show_pipeline.is_none() {
             self.common.dirty = false;
+            self.render_pipeline.clear();
+            self.show_pipeline = Some(self.make_show_pipeline(e));
+        }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = self.make_render_pipeline(e);
+            self.render_pipeline.insert(mode, pipeline);
+        }
+
+        // Handle resize
+        if self.attachments[0].size() != e.extent_3d() {
+            self.attachments = make_attachments(e);
         }
 
         // Command encoder begin
@@ -128,47 +292,77 @@ impl Example04 {
         });
 
         // Render pass resources
-        let current_texture = e.surface.get_current_texture().unwrap();
-        let screen_view = &current_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-
-        // To actually see the output of this use renderdoc.
-        // (Since we don't save it to disk)
-        let offscreen_view = self
-            .offscreen
-            .create_view(&TextureViewDescriptor::default());
-
-        // Render pass
+        let views = self
+            .attachments
+            .iter()
+            .map(|t| t.create_view(&TextureViewDescriptor::default()))
+            .collect::<Vec<_>>();
+
+        // Render pass 1: All attachments at once
         {
-            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
-                label: "ex04-rp".into(),
-                // The color attachments must match the render pipeline's fragment state targets.
-                // Since that has `Some(_), Some(_)`, we crash if we have e.g. `Some(_), None` here.
-                color_attachments: &[
+            let color_attachments = views
+                .iter()
+                .map(|view| {
                     Some(RenderPassColorAttachment {
-                        view: &screen_view,
+                        view,
                         resolve_target: None,
                         // Default: Clear on load, and then store
                         ops: Operations::default(),
-                    }),
-                    Some(RenderPassColorAttachment {
-                        view: &offscreen_view,
-                        resolve_target: None,
-                        ops: Operations::default(),
-                    }),
-                ],
-                // todo
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut ce = debug::group(&mut ce, "ex04-attachments");
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "ex04-rp".into(),
+                // The color attachments must match the render pipeline's fragment state targets.
+                // Since that has `Some(_), Some(_), ..`, we crash if we have e.g. `Some(_), None, ..` here.
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
+            // No vertex buffer, so we'll use the trick where we calc triangles from the indices within
+            // the 0..9 range instead
+            rpass.draw(0..9, 0..1);
+        }
+
+        let bg0 = e.device.create_bind_group(&BindGroupDescriptor {
+            label: "ex04-bg0".into(),
+            layout: &self.bgl0,
+            entries: &views
+                .iter()
+                .enumerate()
+                .map(|(binding, view)| BindGroupEntry {
+                    binding: binding as u32,
+                    resource: wgpu::BindingResource::TextureView(view),
+                })
+                .collect::<Vec<_>>(),
+        });
+
+        // Render pass 2: Show the selected attachment(s)
+        {
+            let mut ce = debug::group(&mut ce, &format!("ex04-show-{:?}", self.show));
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "ex04-show-rp".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: screen_view,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                })],
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline.as_ref().unwrap());
-            // No vertex buffer, so we'll use the trick where we calc a triangle from the indices within
-            // the 0..3 range instead
+            rpass.set_pipeline(self.show_pipeline.as_ref().unwrap());
+            rpass.set_bind_group(0, &bg0, &[]);
+            rpass.set_push_constants(
+                ShaderStages::FRAGMENT,
+                0,
+                self.show.push_constant().to_le_bytes().as_ref(),
+            );
             rpass.draw(0..3, 0..1);
         }
 
         e.queue.submit(std::iter::once(ce.finish()));
-        current_texture.present();
     }
 }
@@ -26,6 +26,16 @@ Things we learned:
     - There is no problem in drawing only the left hand side using scissor rect in one render pass, then drawing the right hand side using a different pipeline
         but to the same texture (the swapchain) in another pass before presenting.
         This allows us to show MSAA on one side and non-MSAA on the other side.
+
+    - Which sample counts are available depends on the format (and adapter), see `TextureFormatFeatureFlags::MULTISAMPLE_X*`.
+        The MSAA texture and the pipeline must agree on the count, so both are recreated when it changes.
+
+    - Alpha to coverage turns the fragment's alpha into a coverage mask, so it does nothing without MSAA.
+        wgpu refuses it for a sample count of 1.
+        It also works without blending, since it's coverage that changes, not the color.
+
+    - The sample mask is ANDed with the coverage, so e.g. 0b0101 only ever writes to samples 0 and 2.
+        With only one sample enabled the MSAA side looks just as jaggy as the plain side.
  */
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
@@ -33,23 +43,105 @@ use wgpu::{
     BindGroupLayoutEntry, BufferUsages, CommandEncoderDescriptor, FragmentState, MultisampleState,
     Operations, PipelineLayoutDescriptor, PrimitiveState, RenderPassColorAttachment,
     RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderStages, Texture,
-    TextureDescriptor, TextureUsages, TextureViewDescriptor, VertexState,
+    TextureDescriptor, TextureUsages, TextureView, TextureViewDescriptor, VertexState,
 };
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    keymap::{key, Action},
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "next-sample-count",
+        description: "Cycle the MSAA sample count",
+        keys: &[key(VirtualKeyCode::Space)],
+    },
+    Action {
+        name: "alpha-to-coverage",
+        description: "Toggle alpha to coverage",
+        keys: &[key(VirtualKeyCode::A)],
+    },
+    Action {
+        name: "next-sample-mask",
+        description: "Cycle the sample mask",
+        keys: &[key(VirtualKeyCode::M)],
+    },
+    Action {
+        name: "divider-left",
+        description: "Move the divider left",
+        keys: &[key(VirtualKeyCode::Left)],
+    },
+    Action {
+        name: "divider-right",
+        description: "Move the divider right",
+        keys: &[key(VirtualKeyCode::Right)],
+    },
+];
 
-use crate::{util::ExampleCommonState, Example, ExampleData};
+// The sample masks we cycle through
+const SAMPLE_MASKS: [u64; 3] = [!0, 0x5555_5555_5555_5555, 0b1];
 
 pub struct Example05 {
     common: ExampleCommonState,
-    render_pipeline_msaa: Option<RenderPipeline>,
-    render_pipeline: Option<RenderPipeline>,
-    msaa_texture: Texture,
-    sample_count: u32,
+    // Per polygon mode
+    render_pipeline_msaa: PerPolygonMode<RenderPipeline>,
+    render_pipeline: PerPolygonMode<RenderPipeline>,
+    // Recreated on resize and when the sample count changes.
+    // Not used when the sample count is 1.
+    msaa_texture: Option<Texture>,
+    // Index into `ExampleData::sample_counts`
+    sample_count_index: usize,
+    alpha_to_coverage: bool,
+    // Index into `SAMPLE_MASKS`
+    sample_mask_index: usize,
+    // Where the split between MSAA (left) and no MSAA (right) is, 0..1 of the width
+    divider: f32,
+    // Divider follows the mouse while held
+    dragging: bool,
     bgl0: BindGroupLayout,
 }
 
 impl Example for Example05 {
-    fn render(&mut self, data: &ExampleData) {
-        self.do_render(data);
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
+    }
+
+    fn handle_action(&mut self, action: &str) {
+        match action {
+            // Next sample count, wraps around.
+            // The counts are only known with the `ExampleData` at hand, see `sample_count`.
+            "next-sample-count" => self.sample_count_index += 1,
+            "alpha-to-coverage" => self.alpha_to_coverage = !self.alpha_to_coverage,
+            "next-sample-mask" => {
+                self.sample_mask_index = (self.sample_mask_index + 1) % SAMPLE_MASKS.len()
+            }
+
+            // Moving the divider doesn't need new pipelines
+            "divider-left" => {
+                self.divider = (self.divider - 0.05).max(0.);
+                return;
+            }
+            "divider-right" => {
+                self.divider = (self.divider + 0.05).min(1.);
+                return;
+            }
+            _ => return,
+        }
+        self.common.dirty = true;
+    }
+
+    fn handle_click(&mut self, _position: [f32; 2], pressed: bool) {
+        self.dragging = pressed;
+    }
+
+    // The release might happen in another example
+    fn on_exit(&mut self) {
+        self.dragging = false;
     }
 
     fn common(&mut self) -> &mut ExampleCommonState {
@@ -62,18 +154,6 @@ impl Example05 {
         let shader_source = "ex05.wgsl";
         let texture_format = e.swapchain_format;
         let common = ExampleCommonState::new(&e.device, texture_format, shader_source, "ex05");
-        let sample_count = e.max_sample_count;
-        let msaa_texture = e.device.create_texture(&TextureDescriptor {
-            label: "MSAA".into(),
-            size: e.extent_3d(),
-            mip_level_count: 1,
-            sample_count,
-            dimension: wgpu::TextureDimension::D2,
-            format: texture_format,
-            // Is this ok?
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
-        });
         let bgl0 = e
             .device
             .create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -91,17 +171,41 @@ impl Example05 {
             });
 
         Self {
-            render_pipeline_msaa: None,
-            render_pipeline: None,
+            render_pipeline_msaa: PerPolygonMode::default(),
+            render_pipeline: PerPolygonMode::default(),
             common,
-            msaa_texture,
-            sample_count,
+            msaa_texture: None,
+            // Start at the highest
+            sample_count_index: e.sample_counts.len() - 1,
+            alpha_to_coverage: false,
+            sample_mask_index: 0,
+            divider: 0.5,
+            dragging: false,
             bgl0,
         }
     }
 
+    fn sample_count(&self, e: &ExampleData) -> u32 {
+        e.sample_counts[self.sample_count_index % e.sample_counts.len()]
+    }
+
+    fn make_msaa_texture(&self, e: &ExampleData) -> Texture {
+        e.device.create_texture(&TextureDescriptor {
+            label: "MSAA".into(),
+            size: e.extent_3d(),
+            mip_level_count: 1,
+            sample_count: self.sample_count(e),
+            dimension: wgpu::TextureDimension::D2,
+            format: e.swapchain_format,
+            // Is this ok?
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+    }
+
     fn make_render_pipeline(&self, e: &ExampleData, multisample: bool) -> RenderPipeline {
         let texture_format = e.swapchain_format;
+        let count = if multisample { self.sample_count(e) } else { 1 };
 
         e.device.create_render_pipeline(&RenderPipelineDescriptor {
             label: "ex05-rpassd".into(),
@@ -128,18 +232,57 @@ impl Example05 {
             depth_stencil: None,
             // Here we go
             multisample: MultisampleState {
-                count: if multisample { self.sample_count } else { 1 },
-                ..Default::default()
+                count,
+                mask: if multisample {
+                    SAMPLE_MASKS[self.sample_mask_index]
+                } else {
+                    !0
+                },
+                // Not allowed without MSAA
+                alpha_to_coverage_enabled: multisample && count > 1 && self.alpha_to_coverage,
             },
             multiview: None,
         })
     }
 
-    pub fn do_render(&mut self, e: &ExampleData) {
-        if self.common.dirty || self.render_pipeline.is_none() {
+    fn print_state(&self, e: &ExampleData) {
+        println!(
+            "Left side: {}x MSAA (of {:?}), alpha to coverage: {}, sample mask: {:#b}",
+            self.sample_count(e),
+            e.sample_counts,
+            self.alpha_to_coverage && self.sample_count(e) > 1,
+            SAMPLE_MASKS[self.sample_mask_index] & ((1 << self.sample_count(e)) - 1),
+        );
+    }
+
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        if self.common.dirty {
             self.common.dirty = false;
-            self.render_pipeline_msaa = Some(self.make_render_pipeline(&e, true));
-            self.render_pipeline = Some(self.make_render_pipeline(&e, false));
+            self.render_pipeline_msaa.clear();
+            self.render_pipeline.clear();
+            self.print_state(e);
+        }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let msaa = self.make_render_pipeline(e, true);
+            self.render_pipeline_msaa.insert(mode, msaa);
+            let pipeline = self.make_render_pipeline(e, false);
+            self.render_pipeline.insert(mode, pipeline);
+        }
+
+        let sample_count = self.sample_count(e);
+
+        // (Re)create on resize or sample count change
+        let stale = match &self.msaa_texture {
+            Some(t) => t.size() != e.extent_3d() || t.sample_count() != sample_count,
+            None => true,
+        };
+        if stale && sample_count > 1 {
+            self.msaa_texture = Some(self.make_msaa_texture(e));
+        }
+
+        if self.dragging {
+            self.divider = (e.mouse[0] / e.viewport[0]).clamp(0., 1.);
         }
 
         // Command encoder begin
@@ -148,13 +291,11 @@ impl Example05 {
         });
 
         // Render pass resources
-        let current_texture = e.surface.get_current_texture().unwrap();
-        let screen_view = &current_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
         let msaa_view = self
             .msaa_texture
-            .create_view(&TextureViewDescriptor::default());
+            .as_ref()
+            .filter(|_| sample_count > 1)
+            .map(|t| t.create_view(&TextureViewDescriptor::default()));
 
         let time_buf = e.device.create_buffer_init(&BufferInitDescriptor {
             label: "ex05-uni-time".into(),
@@ -172,52 +313,68 @@ impl Example05 {
 
         let extent3d = e.extent_3d();
         let (width, height) = (extent3d.width, extent3d.height);
+        let split = ((width as f32 * self.divider) as u32).min(width);
 
         // Render pass 1: MSAA left side
         {
+            let mut ce = debug::group(&mut ce, &format!("ex05-msaa-x{sample_count}"));
             let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
                 label: "ex05-rp-msaa".into(),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &msaa_view,
-                    resolve_target: Some(screen_view),
-                    // Default: Clear on load, and then store
-                    ops: Operations::default(),
+                color_attachments: &[Some(match &msaa_view {
+                    Some(msaa_view) => RenderPassColorAttachment {
+                        view: msaa_view,
+                        resolve_target: Some(screen_view),
+                        // Default: Clear on load, and then store
+                        ops: Operations::default(),
+                    },
+                    // Sample count 1, nothing to resolve
+                    None => RenderPassColorAttachment {
+                        view: screen_view,
+                        resolve_target: None,
+                        ops: Operations::default(),
+                    },
                 })],
-                // todo
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline_msaa.as_ref().unwrap());
-            rpass.set_bind_group(0, &bg0, &[]);
-            // Draw left half
-            rpass.set_scissor_rect(0, 0, width / 2, height);
-            rpass.draw(0..64, 0..1);
+            // Draw left side
+            if split > 0 {
+                rpass.set_pipeline(
+                    self.render_pipeline_msaa
+                        .get(self.common.polygon_mode)
+                        .unwrap(),
+                );
+                rpass.set_bind_group(0, &bg0, &[]);
+                rpass.set_scissor_rect(0, 0, split, height);
+                rpass.draw(0..64, 0..1);
+            }
         }
 
         // Render pass 2: Non-MSAA right side
         {
+            let mut ce = debug::group(&mut ce, "ex05-plain");
             let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
                 label: "ex05-rp".into(),
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &screen_view,
+                    view: screen_view,
                     resolve_target: None,
                     ops: Operations {
                         load: wgpu::LoadOp::Load,
                         store: true,
                     },
                 })],
-                // todo
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline.as_ref().unwrap());
-            rpass.set_bind_group(0, &bg0, &[]);
-            // Draw right half
-            rpass.set_scissor_rect(width / 2, 0, width / 2, height);
-            rpass.draw(0..64, 0..1);
+            // Draw right side
+            if split < width {
+                rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
+                rpass.set_bind_group(0, &bg0, &[]);
+                rpass.set_scissor_rect(split, 0, width - split, height);
+                rpass.draw(0..64, 0..1);
+            }
         }
 
         e.queue.submit(std::iter::once(ce.finish()));
-        current_texture.present();
     }
 }
@@ -14,21 +14,26 @@ use wgpu::{
     BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BufferUsages, CommandEncoderDescriptor, FragmentState, MultisampleState,
     Operations, PipelineLayoutDescriptor, PrimitiveState, RenderPassColorAttachment,
-    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderStages,
-    TextureViewDescriptor, VertexState,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderStages, TextureView,
+    VertexState,
 };
 
-use crate::{util::ExampleCommonState, Example, ExampleData};
+use crate::{
+    debug,
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
 
 pub struct Example06 {
     common: ExampleCommonState,
-    render_pipeline: Option<RenderPipeline>,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
     bgl0: BindGroupLayout,
 }
 
 impl Example for Example06 {
-    fn render(&mut self, data: &ExampleData) {
-        self.do_render(data);
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
     }
 
     fn common(&mut self) -> &mut ExampleCommonState {
@@ -59,7 +64,7 @@ impl Example06 {
             });
 
         Self {
-            render_pipeline: None,
+            render_pipeline: PerPolygonMode::default(),
             common,
             bgl0,
         }
@@ -96,10 +101,15 @@ impl Example06 {
         })
     }
 
-    pub fn do_render(&mut self, e: &ExampleData) {
-        if self.common.dirty || self.render_pipeline.is_none() {
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        if self.common.dirty {
             self.common.dirty = false;
-            self.render_pipeline = Some(self.make_render_pipeline(&e));
+            self.render_pipeline.clear();
+        }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = self.make_render_pipeline(e);
+            self.render_pipeline.insert(mode, pipeline);
         }
 
         // Command encoder begin
@@ -107,12 +117,6 @@ impl Example06 {
             label: "ex06-ce".into(),
         });
 
-        // Render pass resources
-        let current_texture = e.surface.get_current_texture().unwrap();
-        let screen_view = &current_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-
         let time_buf = e.device.create_buffer_init(&BufferInitDescriptor {
             label: "ex06-uni-time".into(),
             contents: self.common.time.as_secs_f32().to_le_bytes().as_ref(),
@@ -135,21 +139,18 @@ impl Example06 {
         let (w, h) = (width as f32, height as f32);
 
         let quadrants = [
-            // Top left quadrant
-            [0., 0., mx, my],
-            // Top right quadrant
-            [mx, 0., w - mx, my],
-            // Bottom left quadrant
-            [0., my, mx, h - my],
-            // Bottom right quadrant
-            [mx, my, w - mx, h - my],
+            ("top-left", [0., 0., mx, my]),
+            ("top-right", [mx, 0., w - mx, my]),
+            ("bottom-left", [0., my, mx, h - my]),
+            ("bottom-right", [mx, my, w - mx, h - my]),
         ];
 
-        for (idx, [x, y, w, h]) in quadrants.into_iter().enumerate() {
+        for (idx, (name, [x, y, w, h])) in quadrants.into_iter().enumerate() {
+            let mut ce = debug::group(&mut ce, &format!("ex06-quadrant-{name}"));
             let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
                 label: "ex06-rp".into(),
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &screen_view,
+                    view: screen_view,
                     resolve_target: None,
                     // Default: Clear on load, and then store
                     ops: if idx == 0 {
@@ -162,17 +163,15 @@ impl Example06 {
                         }
                     },
                 })],
-                // todo
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline.as_ref().unwrap());
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
             rpass.set_viewport(x, y, w, h, 0., 1.);
             rpass.set_bind_group(0, &bg0, &[]);
             rpass.draw(0..64, 0..1);
         }
 
         e.queue.submit(std::iter::once(ce.finish()));
-        current_texture.present();
     }
 }
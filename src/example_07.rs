@@ -17,45 +17,264 @@ Learned:
         More flickering on the outer edges because those fragments have advanced the most.
     - Issues around texture format incompatibility (like copy texture to texture requiring same format (except srgb-ness?)) can be avoided
         by simply _not_ doing a copy but using a sampler to read from one, then using the sampled value to store into the other.
+    - Rgba16Float works as a storage texture, so the canvas can have colors (and alpha for erasing/fading) instead of just R32Float.
+    - Decay per frame depends on the frame rate, so pass the delta time and decay per second instead.
+    - Reading back a texture needs the rows padded to 256 bytes (`COPY_BYTES_PER_ROW_ALIGNMENT`),
+        but writing to a texture via the queue doesn't.
+    - On resize we can keep the canvas by copying the overlapping region from the old textures into the new ones.
+        Texture to texture copies are fine here since the format is the same.
  */
+use std::{path::Path, time::Duration};
+
+use bytemuck::{Pod, Zeroable};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
     BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BufferUsages, CommandEncoderDescriptor, FragmentState,
-    ImageSubresourceRange, MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState,
-    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    Sampler, SamplerDescriptor, ShaderStages, Texture, TextureDescriptor, TextureDimension,
-    TextureUsages, TextureViewDescriptor, VertexState,
+    BindGroupLayoutEntry, Buffer, BufferUsages, CommandEncoderDescriptor, Extent3d, FragmentState,
+    ImageDataLayout, ImageSubresourceRange, MultisampleState, Operations, PipelineLayoutDescriptor,
+    PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerDescriptor, ShaderStages, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+    VertexState,
+};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    keymap::{key, Action},
+    params::{Param, Value},
+    util::{read_texture, ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
 };
 
-use crate::{util::ExampleCommonState, Example, ExampleData};
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "clear",
+        description: "Clear the canvas",
+        keys: &[key(VirtualKeyCode::Space)],
+    },
+    Action {
+        name: "eraser",
+        description: "Toggle the eraser",
+        keys: &[key(VirtualKeyCode::E)],
+    },
+    Action {
+        name: "weaker",
+        description: "Less brush strength",
+        keys: &[key(VirtualKeyCode::LBracket)],
+    },
+    Action {
+        name: "stronger",
+        description: "More brush strength",
+        keys: &[key(VirtualKeyCode::RBracket)],
+    },
+    Action {
+        name: "less-decay",
+        description: "Paint fades slower",
+        keys: &[key(VirtualKeyCode::Comma)],
+    },
+    Action {
+        name: "more-decay",
+        description: "Paint fades faster",
+        keys: &[key(VirtualKeyCode::Period)],
+    },
+    Action {
+        name: "save",
+        description: "Save the canvas",
+        keys: &[key(VirtualKeyCode::F5)],
+    },
+    Action {
+        name: "load",
+        description: "Load the canvas",
+        keys: &[key(VirtualKeyCode::F9)],
+    },
+    Action {
+        name: "color-1",
+        description: "Paint with palette color 1",
+        keys: &[key(VirtualKeyCode::Key1)],
+    },
+    Action {
+        name: "color-2",
+        description: "Paint with palette color 2",
+        keys: &[key(VirtualKeyCode::Key2)],
+    },
+    Action {
+        name: "color-3",
+        description: "Paint with palette color 3",
+        keys: &[key(VirtualKeyCode::Key3)],
+    },
+    Action {
+        name: "color-4",
+        description: "Paint with palette color 4",
+        keys: &[key(VirtualKeyCode::Key4)],
+    },
+    Action {
+        name: "color-5",
+        description: "Paint with palette color 5",
+        keys: &[key(VirtualKeyCode::Key5)],
+    },
+    Action {
+        name: "color-6",
+        description: "Paint with palette color 6",
+        keys: &[key(VirtualKeyCode::Key6)],
+    },
+    Action {
+        name: "color-7",
+        description: "Paint with palette color 7",
+        keys: &[key(VirtualKeyCode::Key7)],
+    },
+    Action {
+        name: "color-8",
+        description: "Paint with palette color 8",
+        keys: &[key(VirtualKeyCode::Key8)],
+    },
+];
+
+// UPDATE: Can't use the swapchain format as a storage texture anyway, see `new`
+const STORAGE_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+// Where the canvas is saved to and loaded from.
+// Raw: a small header (see `save_canvas`) followed by the Rgba16Float pixels.
+const CANVAS_PATH: &str = "ex07-canvas.rgba16f";
+const CANVAS_MAGIC: &[u8; 4] = b"ex07";
+
+// Colors on keys 1 to 8
+const PALETTE: [[f32; 4]; 8] = [
+    [1., 0.1, 0.1, 1.],
+    [0.1, 1., 0.1, 1.],
+    [0.1, 0.2, 1., 1.],
+    [1., 1., 0.1, 1.],
+    [0.1, 1., 1., 1.],
+    [1., 0.1, 1., 1.],
+    [1., 1., 1., 1.],
+    [1., 0.5, 0., 1.],
+];
+const PALETTE_NAMES: &[&str] = &[
+    "red", "green", "blue", "yellow", "cyan", "magenta", "white", "orange",
+];
+
+// The brush, see `params.rs`
+pub const PARAMS: &[Param] = &[
+    Param::f32("radius", "Brush radius in pixels", 50., 2., 500., 5.),
+    Param::f32("strength", "Paint added per second", 5., 0.5, 50., 0.5),
+    Param::f32("decay", "Alpha lost per second", 0.06, 0., 1., 0.02),
+    Param::choice("color", "Palette color", PALETTE_NAMES, 0),
+    Param::bool("eraser", "Erase instead of paint", false),
+];
+
+// See `Brush` in ex07.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct BrushUniform {
+    color: [f32; 4],
+    mouse: [f32; 2],
+    radius: f32,
+    strength: f32,
+    decay: f32,
+    dt: f32,
+    painting: u32,
+    erase: u32,
+}
 
 pub struct Example07 {
     common: ExampleCommonState,
-    render_pipeline: Option<RenderPipeline>,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
     bgl0: BindGroupLayout,
     sampler: Sampler,
     textures: [Texture; 2],
+    brush_buf: Buffer,
+
+    // The rest of the brush is in `PARAMS`
+    painting: bool,
+
+    // To know the delta time
+    last_time: Duration,
+
+    // Done when rendering, since that's when we have the device
+    save: bool,
+    load: bool,
 }
 
 impl Example for Example07 {
-    fn render(&mut self, data: &ExampleData) {
-        self.do_render(data);
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
     }
 
     fn common(&mut self) -> &mut ExampleCommonState {
         &mut self.common
     }
 
-    fn handle_key(&mut self, key: winit::event::VirtualKeyCode) {
-        match key {
+    fn handle_action(&mut self, action: &str) {
+        match action {
             // Recreates pipeline and clears textures.
-            winit::event::VirtualKeyCode::Space => self.common.dirty = true,
-            _ => {}
+            "clear" => self.common.dirty = true,
+
+            "eraser" => {
+                let params = &mut self.common.params;
+                params.adjust("eraser", 1);
+                println!("Eraser: {}", params.bool("eraser"));
+            }
+            "weaker" => self.scale_param("strength", 1. / 1.5),
+            "stronger" => self.scale_param("strength", 1.5),
+            "less-decay" => self.common.params.adjust("decay", -1),
+            "more-decay" => self.common.params.adjust("decay", 1),
+
+            "save" => self.save = true,
+            "load" => self.load = true,
+
+            action => {
+                // "color-1" to "color-8"
+                let Some(color) = action
+                    .strip_prefix("color-")
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|n| (1..=PALETTE.len()).contains(n))
+                else {
+                    return;
+                };
+                let params = &mut self.common.params;
+                params.set("color", Value::Enum(color - 1));
+                params.set("eraser", Value::Bool(false));
+            }
         }
+        self.print_brush();
+    }
+
+    fn handle_scroll(&mut self, scroll_up: bool) {
+        self.scale_param("radius", if scroll_up { 1.2 } else { 1. / 1.2 });
+        self.print_brush();
+    }
+
+    fn handle_click(&mut self, _position: [f32; 2], pressed: bool) {
+        self.painting = pressed;
+    }
+
+    // The release might happen in another example
+    fn on_exit(&mut self) {
+        self.painting = false;
     }
 }
 
+fn make_textures(e: &ExampleData, size: Extent3d) -> [Texture; 2] {
+    ["ex07-texture", "ex07-texture2"].map(|label| {
+        e.device.create_texture(&TextureDescriptor {
+            label: label.into(),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: STORAGE_TEXTURE_FORMAT,
+            usage: TextureUsages::STORAGE_BINDING
+                | TextureUsages::TEXTURE_BINDING
+                // For resizing and save/load
+                | TextureUsages::COPY_SRC
+                | TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    })
+}
+
 impl Example07 {
     pub fn new(e: &ExampleData) -> Self {
         let shader_source = "ex07.wgsl";
@@ -69,8 +288,6 @@ impl Example07 {
         //
         // let storage_texture_format = texture_format.remove_srgb_suffix();
 
-        let storage_texture_format = wgpu::TextureFormat::R32Float;
-
         let bgl0 = e
             .device
             .create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -101,7 +318,7 @@ impl Example07 {
                         visibility: ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::StorageTexture {
                             access: wgpu::StorageTextureAccess::WriteOnly,
-                            format: storage_texture_format,
+                            format: STORAGE_TEXTURE_FORMAT,
                             view_dimension: wgpu::TextureViewDimension::D2,
                         },
                         count: None,
@@ -125,40 +342,51 @@ impl Example07 {
                 ],
             });
 
-        let textures = [
-            e.device.create_texture(&TextureDescriptor {
-                label: "ex07-texture".into(),
-                size: e.extent_3d(),
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: storage_texture_format,
-                usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            }),
-            e.device.create_texture(&TextureDescriptor {
-                label: "ex07-texture2".into(),
-                size: e.extent_3d(),
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: storage_texture_format,
-                usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            }),
-        ];
+        let textures = make_textures(e, e.extent_3d());
 
         let sampler = e.device.create_sampler(&SamplerDescriptor::default());
 
+        let brush_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex07-uni-brush".into(),
+            contents: bytemuck::bytes_of(&BrushUniform::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
         Self {
-            render_pipeline: None,
+            render_pipeline: PerPolygonMode::default(),
             common,
             bgl0,
             textures,
             sampler,
+            brush_buf,
+            painting: false,
+            last_time: Duration::ZERO,
+            save: false,
+            load: false,
         }
     }
 
+    // Keys and scroll change these by a factor, the generic controls by their step
+    fn scale_param(&mut self, name: &str, factor: f32) {
+        let params = &mut self.common.params;
+        params.set(name, Value::F32(params.f32(name) * factor));
+    }
+
+    fn print_brush(&self) {
+        let params = &self.common.params;
+        println!(
+            "Brush: {}, radius: {:.0}px, strength: {:.1}/s, decay: {:.2}/s",
+            if params.bool("eraser") {
+                "eraser"
+            } else {
+                PALETTE_NAMES[params.choice("color")]
+            },
+            params.f32("radius"),
+            params.f32("strength"),
+            params.f32("decay")
+        );
+    }
+
     fn make_render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
         let texture_format = e.swapchain_format;
 
@@ -190,7 +418,92 @@ impl Example07 {
         })
     }
 
-    pub fn do_render(&mut self, e: &ExampleData) {
+    // New textures for the new size, keeping what overlaps from the old ones
+    fn resize(&mut self, e: &ExampleData) {
+        let textures = make_textures(e, e.extent_3d());
+
+        let (old, new) = (self.textures[0].size(), e.extent_3d());
+        let overlap = Extent3d {
+            width: old.width.min(new.width),
+            height: old.height.min(new.height),
+            depth_or_array_layers: 1,
+        };
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "ex07-resize-ce".into(),
+        });
+        for (from, to) in self.textures.iter().zip(textures.iter()) {
+            ce.copy_texture_to_texture(from.as_image_copy(), to.as_image_copy(), overlap);
+        }
+        e.queue.submit(std::iter::once(ce.finish()));
+
+        self.textures = textures;
+    }
+
+    // Header: magic, width (u32 le), height (u32 le).
+    // Then rows of Rgba16Float pixels, no padding.
+    fn save_canvas(&self, e: &ExampleData, texture: &Texture) {
+        let size = texture.size();
+        let mut bytes = CANVAS_MAGIC.to_vec();
+        bytes.extend_from_slice(&size.width.to_le_bytes());
+        bytes.extend_from_slice(&size.height.to_le_bytes());
+        bytes.extend(read_texture(&e.device, &e.queue, texture));
+
+        match std::fs::write(CANVAS_PATH, bytes) {
+            Ok(()) => println!(
+                "Saved {}x{} canvas to {CANVAS_PATH}",
+                size.width, size.height
+            ),
+            Err(err) => println!("Could not save canvas to {CANVAS_PATH}: {err}"),
+        }
+    }
+
+    // Loads into both textures.
+    // If the saved canvas has another size than the window, only what overlaps is loaded.
+    fn load_canvas(&self, e: &ExampleData) {
+        let bytes = match std::fs::read(Path::new(CANVAS_PATH)) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                println!("Could not load canvas from {CANVAS_PATH}: {err}");
+                return;
+            }
+        };
+
+        let (width, height, pixels) = match parse_canvas(&bytes) {
+            Ok(canvas) => canvas,
+            Err(err) => {
+                println!("{CANVAS_PATH} {err}");
+                return;
+            }
+        };
+        let bytes_per_pixel = STORAGE_TEXTURE_FORMAT.block_size(None).unwrap();
+
+        let size = self.textures[0].size();
+        let overlap = Extent3d {
+            width: width.min(size.width),
+            height: height.min(size.height),
+            depth_or_array_layers: 1,
+        };
+        for texture in &self.textures {
+            e.queue.write_texture(
+                texture.as_image_copy(),
+                pixels,
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * bytes_per_pixel),
+                    rows_per_image: None,
+                },
+                overlap,
+            );
+        }
+        println!("Loaded {width}x{height} canvas from {CANVAS_PATH}");
+    }
+
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        if self.textures[0].size() != e.extent_3d() {
+            self.resize(e);
+        }
+
         // Command encoder begin
         let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
             label: "ex07-ce".into(),
@@ -199,10 +512,11 @@ impl Example07 {
         let texture_sampled = self.common.frame() as usize % 2;
         let texture_storage = (self.common.frame() as usize + 1) % 2;
 
-        if self.common.dirty || self.render_pipeline.is_none() {
+        if self.common.dirty {
             self.common.dirty = false;
-            self.render_pipeline = Some(self.make_render_pipeline(&e));
+            self.render_pipeline.clear();
 
+            debug::marker(&mut ce, "ex07-clear-canvas");
             ce.clear_texture(
                 &self.textures[texture_storage],
                 &ImageSubresourceRange::default(),
@@ -213,23 +527,45 @@ impl Example07 {
                 &ImageSubresourceRange::default(),
             );
         }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = self.make_render_pipeline(e);
+            self.render_pipeline.insert(mode, pipeline);
+        }
 
-        // Render pass resources
-        let current_texture = e.surface.get_current_texture().unwrap();
-        let screen_view = &current_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
+        // The texture we're about to sample is what the previous frame painted
+        if self.save {
+            self.save = false;
+            self.save_canvas(e, &self.textures[texture_sampled]);
+        }
+        if self.load {
+            self.load = false;
+            self.load_canvas(e);
+        }
+
+        let dt = (self.common.time - self.last_time).as_secs_f32();
+        self.last_time = self.common.time;
 
         let time_buf = e.device.create_buffer_init(&BufferInitDescriptor {
             label: "ex07-uni-time".into(),
             contents: self.common.time.as_secs_f32().to_le_bytes().as_ref(),
             usage: BufferUsages::UNIFORM,
         });
-        let mouse_buf = e.device.create_buffer_init(&BufferInitDescriptor {
-            label: "ex07-uni-mouse".into(),
-            contents: bytemuck::cast_slice(e.mouse_window_space().as_slice()),
-            usage: BufferUsages::UNIFORM,
-        });
+        let params = &self.common.params;
+        e.queue.write_buffer(
+            &self.brush_buf,
+            0,
+            bytemuck::bytes_of(&BrushUniform {
+                color: PALETTE[params.choice("color")],
+                mouse: e.mouse,
+                radius: params.f32("radius"),
+                strength: params.f32("strength"),
+                decay: params.f32("decay"),
+                dt,
+                painting: self.painting as u32,
+                erase: params.bool("eraser") as u32,
+            }),
+        );
 
         let bg0: wgpu::BindGroup = e.device.create_bind_group(&BindGroupDescriptor {
             label: "ex07-bg0".into(),
@@ -258,13 +594,15 @@ impl Example07 {
                 },
                 BindGroupEntry {
                     binding: 4,
-                    resource: mouse_buf.as_entire_binding(),
+                    resource: self.brush_buf.as_entire_binding(),
                 },
             ],
             layout: &self.bgl0,
         });
 
+        // Paints into the storage texture and shows the canvas at once
         {
+            let mut ce = debug::group(&mut ce, "ex07-paint");
             let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
                 label: "ex07-rp".into(),
                 color_attachments: &[Some(RenderPassColorAttachment {
@@ -272,16 +610,70 @@ impl Example07 {
                     resolve_target: None,
                     ops: Operations::default(),
                 })],
-                // todo
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline.as_ref().unwrap());
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
             rpass.set_bind_group(0, &bg0, &[]);
             rpass.draw(0..4, 0..1);
         }
 
         e.queue.submit(std::iter::once(ce.finish()));
-        current_texture.present();
+    }
+}
+
+// The size and pixels of a saved canvas (see `save_canvas`).
+// The size comes from the file, so it's checked against the pixels without overflowing.
+fn parse_canvas(bytes: &[u8]) -> Result<(u32, u32, &[u8]), String> {
+    let header = CANVAS_MAGIC.len() + 8;
+    let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+    if bytes.len() < header || &bytes[..CANVAS_MAGIC.len()] != CANVAS_MAGIC {
+        return Err("is not a saved canvas".to_string());
+    }
+    let (width, height) = (u32_at(4), u32_at(8));
+
+    let bytes_per_pixel = STORAGE_TEXTURE_FORMAT.block_size(None).unwrap();
+    let expected = width
+        .checked_mul(bytes_per_pixel)
+        .and_then(|row| row.checked_mul(height));
+    if expected.map(|len| len as usize) != Some(bytes.len() - header) {
+        return Err(format!("has the wrong size for {width}x{height}"));
+    }
+    Ok((width, height, &bytes[header..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canvas(width: u32, height: u32, pixels: usize) -> Vec<u8> {
+        let mut bytes = CANVAS_MAGIC.to_vec();
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        bytes.extend(vec![7; pixels]);
+        bytes
+    }
+
+    #[test]
+    fn parses_saved_canvas() {
+        let bytes = canvas(3, 2, 3 * 2 * 8);
+        let (width, height, pixels) = parse_canvas(&bytes).unwrap();
+        assert_eq!((width, height, pixels.len()), (3, 2, 48));
+    }
+
+    #[test]
+    fn rejects_bad_canvas() {
+        assert!(parse_canvas(b"ex07").is_err());
+        assert!(parse_canvas(&canvas(3, 2, 47)).is_err());
+        let mut bytes = canvas(3, 2, 48);
+        bytes[0] = b'x';
+        assert!(parse_canvas(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_size() {
+        // Both wrap to 0 bytes in u32 math (8 bytes per pixel), which would match the empty pixels
+        assert!(parse_canvas(&canvas(0x4000_0000, 1, 0)).is_err());
+        assert!(parse_canvas(&canvas(0x1_0000, 0x4000, 0)).is_err());
     }
 }
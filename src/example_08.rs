@@ -105,14 +105,30 @@ use wgpu::{
     FragmentState, MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState,
     RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
     Sampler, SamplerDescriptor, ShaderStages, Texture, TextureDescriptor, TextureDimension,
-    TextureFormat, TextureUsages, TextureViewDescriptor, VertexBufferLayout, VertexState,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor, VertexBufferLayout,
+    VertexState,
 };
 
-use crate::{util::ExampleCommonState, Example, ExampleData};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    keymap::{key, Action},
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[Action {
+    name: "clear",
+    description: "Recreate the pipeline and clear the textures",
+    keys: &[key(VirtualKeyCode::Space)],
+}];
 
 pub struct Example08 {
     common: ExampleCommonState,
-    render_pipeline: Option<RenderPipeline>,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
     bgl0: BindGroupLayout,
     sampler: Sampler,
     textures: [Texture; 256],
@@ -120,19 +136,18 @@ pub struct Example08 {
 }
 
 impl Example for Example08 {
-    fn render(&mut self, data: &ExampleData) {
-        self.do_render(data);
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
     }
 
     fn common(&mut self) -> &mut ExampleCommonState {
         &mut self.common
     }
 
-    fn handle_key(&mut self, key: winit::event::VirtualKeyCode) {
-        match key {
-            // Recreates pipeline and clears textures.
-            winit::event::VirtualKeyCode::Space => self.common.dirty = true,
-            _ => {}
+    fn handle_action(&mut self, action: &str) {
+        // Recreates pipeline and clears textures.
+        if action == "clear" {
+            self.common.dirty = true;
         }
     }
 }
@@ -145,9 +160,8 @@ impl Example08 {
 
         println!("Creating textures with format {texture_format:?}");
         let textures: [Texture; 256] = (0..16)
-            .into_iter()
             .flat_map(|col| {
-                (0..16).into_iter().map(move |row| {
+                (0..16).map(move |row| {
                     e.device.create_texture_with_data(
                         &e.queue,
                         &TextureDescriptor {
@@ -240,7 +254,7 @@ impl Example08 {
         });
 
         Self {
-            render_pipeline: None,
+            render_pipeline: PerPolygonMode::default(),
             common,
             bgl0,
             textures,
@@ -289,22 +303,21 @@ impl Example08 {
         })
     }
 
-    pub fn do_render(&mut self, e: &ExampleData) {
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
         // Command encoder begin
         let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
             label: "ex08-ce".into(),
         });
 
-        if self.common.dirty || self.render_pipeline.is_none() {
+        if self.common.dirty {
             self.common.dirty = false;
-            self.render_pipeline = Some(self.render_pipeline(&e));
+            self.render_pipeline.clear();
+        }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = self.render_pipeline(e);
+            self.render_pipeline.insert(mode, pipeline);
         }
-
-        // Render pass resources
-        let current_texture = e.surface.get_current_texture().unwrap();
-        let screen_view = &current_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
 
         #[repr(C)]
         #[derive(Clone, Copy, Pod, Zeroable)]
@@ -329,9 +342,8 @@ impl Example08 {
         }
 
         let affine_mats = (0..16)
-            .into_iter()
             .flat_map(|col| {
-                (0..16).into_iter().map(move |row| {
+                (0..16).map(move |row| {
                     let tx: f32 = -1. + 2. * (col as f32 * 1. / 16.) + (1. / 16.);
                     let ty: f32 = -1. + 2. * (row as f32 * 1. / 16.) + (1. / 16.);
 
@@ -406,17 +418,16 @@ impl Example08 {
                         store: true,
                     },
                 })],
-                // todo
                 depth_stencil_attachment: None,
             });
 
-            rpass.set_pipeline(&self.render_pipeline.as_ref().unwrap());
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
             rpass.set_bind_group(0, &bg0, &[]);
             rpass.set_vertex_buffer(0, self.quad.slice(..));
+            debug::marker(&mut rpass, "ex08-quads x256");
             rpass.draw(0..6, 0..256);
         }
 
         e.queue.submit(std::iter::once(ce.finish()));
-        current_texture.present();
     }
 }
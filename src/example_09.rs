@@ -0,0 +1,565 @@
+/*
+Goals:
+    - Full screen compute pass writing to a storage texture, which is then sampled onto the swapchain
+    - Profile "right" and "wrong" workgroup sizes for the same full screen pass.
+        See: https://developer.nvidia.com/blog/advanced-api-performance-shaders/
+        > A good starting point is to target a thread group size corresponding to between two or eight warps.
+        > For instance, thread group size 8x8x1 or 16x16x1 for full-screen passes.
+    - Use timestamp queries if available, else fall back to timing on the CPU by waiting for the queue
+
+Things we learned:
+    - `@workgroup_size` has to be known when the shader module is created, so each variant is a separate module
+        (and pipeline). We just do a text replace on the wgsl source.
+    - The number of workgroups to dispatch is then the texture size divided by the workgroup size, rounded up.
+        The invocations outside the texture have to bail early.
+    - Timestamps written via the command encoder (i.e. outside passes) only need `Features::TIMESTAMP_QUERY`.
+        Writing them inside passes needs `TIMESTAMP_QUERY_INSIDE_PASSES` too.
+    - Timestamps are in "ticks", multiply by `Queue::get_timestamp_period` to get nanoseconds.
+ */
+use std::time::{Duration, Instant};
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Extent3d, Features,
+    FragmentState, Maintain, MapMode, MultisampleState, Operations, PipelineLayoutDescriptor,
+    PrimitiveState, QuerySet, QuerySetDescriptor, QueryType, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderStages, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor, VertexState,
+};
+
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    keymap::{key, Action},
+    util::{read_shader, ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[Action {
+    name: "benchmark",
+    description: "Benchmark every workgroup size and print the results",
+    keys: &[key(VirtualKeyCode::B)],
+}];
+
+// The variants we try, (x, y).
+// The z size is always 1.
+pub const WORKGROUP_SIZES: [[u32; 2]; 6] = [[1, 1], [8, 8], [16, 16], [32, 1], [64, 4], [256, 1]];
+
+// Dispatches per variant before measuring
+const WARMUP_DISPATCHES: u32 = 10;
+// Dispatches per variant which are measured
+const MEASURED_DISPATCHES: u32 = 100;
+
+const STORAGE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+pub struct Example09 {
+    common: ExampleCommonState,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
+    // One per entry in `WORKGROUP_SIZES`
+    compute_pipelines: Vec<ComputePipeline>,
+    // Compute: Storage texture, time.
+    // Same group as `bgl_blit`, but the bindings differ.
+    bgl0: BindGroupLayout,
+    // Blit: Sampled texture, sampler
+    bgl_blit: BindGroupLayout,
+    sampler: Sampler,
+    texture: Texture,
+    time_buf: Buffer,
+    bg0: BindGroup,
+    bg_blit: BindGroup,
+
+    // Which variant is used to draw to the screen
+    variant: usize,
+    run_benchmark: bool,
+}
+
+// Results of benchmarking a single workgroup size
+pub struct WorkgroupTiming {
+    pub workgroup_size: [u32; 2],
+    pub workgroups: [u32; 2],
+    // Average per dispatch, measured by waiting for the queue to finish
+    pub cpu: Duration,
+    // Average per dispatch, measured via timestamp queries (if supported)
+    pub gpu: Option<Duration>,
+}
+
+// Only with `Features::TIMESTAMP_QUERY`
+struct Queries {
+    query_set: QuerySet,
+    resolve_buf: Buffer,
+    read_buf: Buffer,
+}
+
+impl Example for Example09 {
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
+    }
+
+    fn handle_action(&mut self, action: &str) {
+        // Run the benchmark and print the results next frame
+        if action == "benchmark" {
+            self.run_benchmark = true;
+        }
+    }
+
+    fn handle_scroll(&mut self, scroll_up: bool) {
+        self.variant = if scroll_up {
+            (self.variant + 1).min(WORKGROUP_SIZES.len() - 1)
+        } else {
+            self.variant.saturating_sub(1)
+        };
+        let [x, y] = WORKGROUP_SIZES[self.variant];
+        println!("Drawing with workgroup size {x}x{y}");
+    }
+
+    fn common(&mut self) -> &mut ExampleCommonState {
+        &mut self.common
+    }
+}
+
+// Replace the `@workgroup_size(..)` attribute of the given wgsl source.
+fn with_workgroup_size(source: &str, [x, y]: [u32; 2]) -> String {
+    let attribute = "@workgroup_size(";
+    let start = source
+        .find(attribute)
+        .expect("shader should have a workgroup size");
+    let end = start
+        + source[start..]
+            .find(')')
+            .expect("attribute should be closed")
+        + 1;
+
+    format!(
+        "{}@workgroup_size({x}, {y}, 1){}",
+        &source[..start],
+        &source[end..]
+    )
+}
+
+fn workgroups(extent: Extent3d, [x, y]: [u32; 2]) -> [u32; 2] {
+    [extent.width.div_ceil(x), extent.height.div_ceil(y)]
+}
+
+impl Example09 {
+    pub fn new(e: &ExampleData) -> Self {
+        let shader_source = "ex09.wgsl";
+        let texture_format = e.swapchain_format;
+        let common = ExampleCommonState::new(&e.device, texture_format, shader_source, "ex09");
+
+        let bgl0 = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex09-bgl0".into(),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: STORAGE_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let bgl_blit = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex09-bgl-blit".into(),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let sampler = e.device.create_sampler(&SamplerDescriptor::default());
+
+        // Written via the queue each frame instead of recreated
+        let time_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex09-uni-time".into(),
+            contents: 0f32.to_le_bytes().as_ref(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let (texture, bg0, bg_blit) =
+            make_texture(e, &bgl0, &bgl_blit, &sampler, &time_buf, e.extent_3d());
+
+        Self {
+            common,
+            render_pipeline: PerPolygonMode::default(),
+            compute_pipelines: vec![],
+            bgl0,
+            bgl_blit,
+            sampler,
+            texture,
+            time_buf,
+            bg0,
+            bg_blit,
+            // 8x8
+            variant: 1,
+            run_benchmark: false,
+        }
+    }
+
+    fn make_render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: "ex09-rpassd".into(),
+            layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: "ex09-rpass-pld".into(),
+                bind_group_layouts: &[&self.bgl_blit],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &self.common.shader_module,
+                entry_point: "vs",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &self.common.shader_module,
+                entry_point: "fs",
+                targets: &[Some(e.swapchain_format.into())],
+            }),
+            primitive: PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                polygon_mode: self.common.polygon_mode,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn make_compute_pipelines(&self, e: &ExampleData) -> Vec<ComputePipeline> {
+        let source = read_shader(self.common.shader_source);
+        let layout = e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: "ex09-cpass-pld".into(),
+            bind_group_layouts: &[&self.bgl0],
+            push_constant_ranges: &[],
+        });
+
+        WORKGROUP_SIZES
+            .iter()
+            .map(|&size| {
+                let label = format!("ex09-cs-{}x{}", size[0], size[1]);
+                let module = e.device.create_shader_module(ShaderModuleDescriptor {
+                    label: Some(&label),
+                    source: wgpu::ShaderSource::Wgsl(with_workgroup_size(&source, size).into()),
+                });
+
+                e.device
+                    .create_compute_pipeline(&ComputePipelineDescriptor {
+                        label: Some(&label),
+                        layout: Some(&layout),
+                        module: &module,
+                        entry_point: "cs",
+                    })
+            })
+            .collect()
+    }
+
+    // Make sure pipelines and the texture are up to date
+    fn prepare(&mut self, e: &ExampleData) {
+        if self.common.dirty {
+            self.common.dirty = false;
+            self.render_pipeline.clear();
+            self.compute_pipelines = self.make_compute_pipelines(e);
+        }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = self.make_render_pipeline(e);
+            self.render_pipeline.insert(mode, pipeline);
+        }
+
+        if self.texture.size() != e.extent_3d() {
+            (self.texture, self.bg0, self.bg_blit) = make_texture(
+                e,
+                &self.bgl0,
+                &self.bgl_blit,
+                &self.sampler,
+                &self.time_buf,
+                e.extent_3d(),
+            );
+        }
+
+        e.queue.write_buffer(
+            &self.time_buf,
+            0,
+            self.common.time.as_secs_f32().to_le_bytes().as_ref(),
+        );
+    }
+
+    // Run every workgroup size variant many times and time it.
+    pub fn benchmark(&mut self, e: &ExampleData) -> Vec<WorkgroupTiming> {
+        self.prepare(e);
+
+        let extent = self.texture.size();
+        println!(
+            "Benchmarking {} workgroup sizes at {}x{}, {MEASURED_DISPATCHES} dispatches each",
+            WORKGROUP_SIZES.len(),
+            extent.width,
+            extent.height
+        );
+
+        // Creating a timestamp query set without the feature is a validation error
+        let queries = e
+            .device
+            .features()
+            .contains(Features::TIMESTAMP_QUERY)
+            .then(|| {
+                let resolve_buf = e.device.create_buffer(&BufferDescriptor {
+                    label: "ex09-query-resolve".into(),
+                    size: 2 * std::mem::size_of::<u64>() as u64,
+                    usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                });
+                Queries {
+                    query_set: e.device.create_query_set(&QuerySetDescriptor {
+                        label: "ex09-queries".into(),
+                        ty: QueryType::Timestamp,
+                        count: 2,
+                    }),
+                    read_buf: e.device.create_buffer(&BufferDescriptor {
+                        label: "ex09-query-read".into(),
+                        size: resolve_buf.size(),
+                        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    }),
+                    resolve_buf,
+                }
+            });
+        if queries.is_none() {
+            println!("No timestamp query support, only CPU timings are available");
+        }
+
+        let dispatch = |pipeline: &ComputePipeline, workgroups: [u32; 2], n: u32| {
+            let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+                label: "ex09-bench-ce".into(),
+            });
+            if let Some(q) = &queries {
+                ce.write_timestamp(&q.query_set, 0);
+            }
+            {
+                let mut cpass = ce.begin_compute_pass(&ComputePassDescriptor {
+                    label: "ex09-bench-cp".into(),
+                });
+                cpass.set_pipeline(pipeline);
+                cpass.set_bind_group(0, &self.bg0, &[]);
+                for _ in 0..n {
+                    cpass.dispatch_workgroups(workgroups[0], workgroups[1], 1);
+                }
+            }
+            if let Some(q) = &queries {
+                ce.write_timestamp(&q.query_set, 1);
+                ce.resolve_query_set(&q.query_set, 0..2, &q.resolve_buf, 0);
+                ce.copy_buffer_to_buffer(&q.resolve_buf, 0, &q.read_buf, 0, q.read_buf.size());
+            }
+
+            let start = Instant::now();
+            e.queue.submit(std::iter::once(ce.finish()));
+            e.device.poll(Maintain::Wait);
+            start.elapsed()
+        };
+
+        WORKGROUP_SIZES
+            .iter()
+            .zip(self.compute_pipelines.iter())
+            .map(|(&workgroup_size, pipeline)| {
+                let workgroups = workgroups(extent, workgroup_size);
+
+                dispatch(pipeline, workgroups, WARMUP_DISPATCHES);
+                let cpu = dispatch(pipeline, workgroups, MEASURED_DISPATCHES);
+
+                let gpu = queries.as_ref().map(|q| {
+                    let slice = q.read_buf.slice(..);
+                    slice.map_async(MapMode::Read, |_| {});
+                    e.device.poll(Maintain::Wait);
+                    let ticks = {
+                        let data = slice.get_mapped_range();
+                        let ts: &[u64] = bytemuck::cast_slice(&data);
+                        ts[1].saturating_sub(ts[0])
+                    };
+                    q.read_buf.unmap();
+
+                    let ns = ticks as f64 * e.queue.get_timestamp_period() as f64;
+                    Duration::from_nanos(ns as u64) / MEASURED_DISPATCHES
+                });
+
+                WorkgroupTiming {
+                    workgroup_size,
+                    workgroups,
+                    cpu: cpu / MEASURED_DISPATCHES,
+                    gpu,
+                }
+            })
+            .collect()
+    }
+
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        if self.run_benchmark {
+            self.run_benchmark = false;
+            print_timings(&self.benchmark(e));
+        }
+
+        self.prepare(e);
+
+        // Command encoder begin
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "ex09-ce".into(),
+        });
+
+        // Compute pass: Fill the storage texture
+        {
+            let [x, y] = WORKGROUP_SIZES[self.variant];
+            let mut ce = debug::group(&mut ce, &format!("ex09-compute-{x}x{y}"));
+            let workgroups = workgroups(self.texture.size(), [x, y]);
+            let mut cpass = ce.begin_compute_pass(&ComputePassDescriptor {
+                label: "ex09-cp".into(),
+            });
+            cpass.set_pipeline(&self.compute_pipelines[self.variant]);
+            cpass.set_bind_group(0, &self.bg0, &[]);
+            cpass.dispatch_workgroups(workgroups[0], workgroups[1], 1);
+        }
+
+        // Render pass: Sample the storage texture to the screen, to eyeball that all variants agree
+        {
+            let mut ce = debug::group(&mut ce, "ex09-show");
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "ex09-rp".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: screen_view,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
+            rpass.set_bind_group(0, &self.bg_blit, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        e.queue.submit(std::iter::once(ce.finish()));
+    }
+}
+
+fn make_texture(
+    e: &ExampleData,
+    bgl0: &BindGroupLayout,
+    bgl_blit: &BindGroupLayout,
+    sampler: &Sampler,
+    time_buf: &Buffer,
+    size: Extent3d,
+) -> (Texture, BindGroup, BindGroup) {
+    let texture = e.device.create_texture(&TextureDescriptor {
+        label: "ex09-texture".into(),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: STORAGE_FORMAT,
+        usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&TextureViewDescriptor::default());
+
+    let bg0 = e.device.create_bind_group(&BindGroupDescriptor {
+        label: "ex09-bg0".into(),
+        layout: bgl0,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: time_buf.as_entire_binding(),
+            },
+        ],
+    });
+    let bg_blit = e.device.create_bind_group(&BindGroupDescriptor {
+        label: "ex09-bg-blit".into(),
+        layout: bgl_blit,
+        entries: &[
+            BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    });
+
+    (texture, bg0, bg_blit)
+}
+
+pub fn print_timings(timings: &[WorkgroupTiming]) {
+    let fmt_us = |d: Duration| format!("{:.1}", d.as_secs_f64() * 1e6);
+
+    // Compare against the commonly recommended size
+    let reference = timings
+        .iter()
+        .find(|t| t.workgroup_size == [8, 8])
+        .map(|t| t.gpu.unwrap_or(t.cpu));
+
+    println!(
+        "{:<10} {:>12} {:>12} {:>14} {:>14} {:>8}",
+        "size", "invocations", "workgroups", "gpu us/disp", "cpu us/disp", "vs 8x8"
+    );
+    for t in timings {
+        let [x, y] = t.workgroup_size;
+        let [wx, wy] = t.workgroups;
+        let relative = reference
+            .map(|r| {
+                format!(
+                    "{:.2}x",
+                    t.gpu.unwrap_or(t.cpu).as_secs_f64() / r.as_secs_f64()
+                )
+            })
+            .unwrap_or_default();
+
+        println!(
+            "{:<10} {:>12} {:>12} {:>14} {:>14} {:>8}",
+            format!("{x}x{y}"),
+            x * y,
+            format!("{wx}x{wy}"),
+            t.gpu.map(fmt_us).unwrap_or_else(|| "-".into()),
+            fmt_us(t.cpu),
+            relative
+        );
+    }
+}
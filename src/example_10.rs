@@ -0,0 +1,614 @@
+/*
+Goals:
+    - Example 08 wants its quads "slightly transparent", but its pipeline has no blending. Explore `BlendState` here instead.
+    - Overlapping translucent quads, with presets for the usual blend modes:
+        alpha, premultiplied alpha, additive, multiply, min, max and replace
+    - A custom mode where each factor and operation can be cycled
+    - Print the blend equation when it changes
+    - Draw the same quads twice: In list order (left) and sorted back to front (right), to see which modes care about order
+
+Things we learned:
+    - The blend state is part of the pipeline, so every change means a new `RenderPipeline`.
+        Only the blend constant can change within a pass (`set_blend_constant`).
+    - Color and alpha blend separately, each as `src * src_factor <op> dst * dst_factor`,
+        where src is what the fragment shader returns and dst what's in the target.
+    - Alpha blending is only correct back to front, which is why translucent things get sorted.
+        Additive, multiply, min and max are commutative, so both halves look the same with them.
+    - Premultiplied alpha blends with `One` instead of `SrcAlpha`, so the shader multiplies color by alpha.
+    - With `Min` and `Max` the factors aren't used. WebGPU wants them to be `One`.
+    - The checkerboard has alpha 1, so the dst alpha factors always see 1 here.
+    - Blending happens on linear values. With an sRGB swapchain, 50% alpha doesn't look half way.
+ */
+use std::{f32::consts::TAU, mem::size_of};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BlendComponent, BlendFactor, BlendOperation, BlendState, Buffer,
+    BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoderDescriptor,
+    FragmentState, MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderStages, TextureView, VertexBufferLayout, VertexState,
+};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    keymap::{key, shift, Action},
+    params::{Param, Params, Value},
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "next-mode",
+        description: "Next blend mode",
+        keys: &[key(VirtualKeyCode::Space)],
+    },
+    Action {
+        name: "previous-mode",
+        description: "Previous blend mode",
+        keys: &[shift(VirtualKeyCode::Space)],
+    },
+    // Named like the parameters they cycle
+    Action {
+        name: "color-src",
+        description: "Custom mode: Next color source factor",
+        keys: &[key(VirtualKeyCode::Key1)],
+    },
+    Action {
+        name: "color-dst",
+        description: "Custom mode: Next color destination factor",
+        keys: &[key(VirtualKeyCode::Key2)],
+    },
+    Action {
+        name: "color-op",
+        description: "Custom mode: Next color operation",
+        keys: &[key(VirtualKeyCode::Key3)],
+    },
+    Action {
+        name: "alpha-src",
+        description: "Custom mode: Next alpha source factor",
+        keys: &[key(VirtualKeyCode::Key4)],
+    },
+    Action {
+        name: "alpha-dst",
+        description: "Custom mode: Next alpha destination factor",
+        keys: &[key(VirtualKeyCode::Key5)],
+    },
+    Action {
+        name: "alpha-op",
+        description: "Custom mode: Next alpha operation",
+        keys: &[key(VirtualKeyCode::Key6)],
+    },
+];
+
+const MODE_NAMES: &[&str] = &[
+    "alpha",
+    "premultiplied",
+    "additive",
+    "multiply",
+    "min",
+    "max",
+    "replace",
+    "custom",
+];
+const CUSTOM: usize = MODE_NAMES.len() - 1;
+
+const FACTORS: [BlendFactor; 13] = [
+    BlendFactor::Zero,
+    BlendFactor::One,
+    BlendFactor::Src,
+    BlendFactor::OneMinusSrc,
+    BlendFactor::SrcAlpha,
+    BlendFactor::OneMinusSrcAlpha,
+    BlendFactor::Dst,
+    BlendFactor::OneMinusDst,
+    BlendFactor::DstAlpha,
+    BlendFactor::OneMinusDstAlpha,
+    BlendFactor::SrcAlphaSaturated,
+    BlendFactor::Constant,
+    BlendFactor::OneMinusConstant,
+];
+const FACTOR_NAMES: &[&str] = &[
+    "zero",
+    "one",
+    "src",
+    "one-minus-src",
+    "src-alpha",
+    "one-minus-src-alpha",
+    "dst",
+    "one-minus-dst",
+    "dst-alpha",
+    "one-minus-dst-alpha",
+    "src-alpha-saturated",
+    "constant",
+    "one-minus-constant",
+];
+
+const OPERATIONS: [BlendOperation; 5] = [
+    BlendOperation::Add,
+    BlendOperation::Subtract,
+    BlendOperation::ReverseSubtract,
+    BlendOperation::Min,
+    BlendOperation::Max,
+];
+const OPERATION_NAMES: &[&str] = &["add", "subtract", "reverse-subtract", "min", "max"];
+
+// See `params.rs`. The custom mode starts out as alpha blending.
+pub const PARAMS: &[Param] = &[
+    Param::choice("mode", "Blend mode", MODE_NAMES, 0),
+    Param::choice(
+        "color-src",
+        "Custom mode: Color source factor",
+        FACTOR_NAMES,
+        4,
+    ),
+    Param::choice(
+        "color-dst",
+        "Custom mode: Color destination factor",
+        FACTOR_NAMES,
+        5,
+    ),
+    Param::choice(
+        "color-op",
+        "Custom mode: Color operation",
+        OPERATION_NAMES,
+        0,
+    ),
+    Param::choice(
+        "alpha-src",
+        "Custom mode: Alpha source factor",
+        FACTOR_NAMES,
+        1,
+    ),
+    Param::choice(
+        "alpha-dst",
+        "Custom mode: Alpha destination factor",
+        FACTOR_NAMES,
+        5,
+    ),
+    Param::choice(
+        "alpha-op",
+        "Custom mode: Alpha operation",
+        OPERATION_NAMES,
+        0,
+    ),
+    Param::bool(
+        "premultiply",
+        "Custom mode: Premultiply color by alpha in the shader",
+        false,
+    ),
+    Param::f32("opacity", "Alpha of the quads", 0.5, 0., 1., 0.05),
+    Param::f32(
+        "constant",
+        "Blend constant (every channel)",
+        0.5,
+        0.,
+        1.,
+        0.05,
+    ),
+];
+
+// Going around the carousel
+const COLORS: [[f32; 3]; 6] = [
+    [1., 0.1, 0.1],
+    [0.1, 1., 0.1],
+    [0.1, 0.2, 1.],
+    [1., 1., 0.1],
+    [0.1, 1., 1.],
+    [1., 0.1, 1.],
+];
+
+// Instance data
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Quad {
+    center: [f32; 2],
+    // Half the width
+    size: f32,
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Globals {
+    scale: [f32; 2],
+    premultiply: u32,
+    _pad: u32,
+}
+
+// The blend state of a mode, and whether the shader should premultiply
+fn blend_mode(params: &Params) -> (BlendState, bool) {
+    let component = |src, dst, op| BlendComponent {
+        src_factor: FACTORS[params.choice(src)],
+        dst_factor: FACTORS[params.choice(dst)],
+        operation: OPERATIONS[params.choice(op)],
+    };
+    let both = |src_factor, dst_factor, operation| {
+        let component = BlendComponent {
+            src_factor,
+            dst_factor,
+            operation,
+        };
+        BlendState {
+            color: component,
+            alpha: component,
+        }
+    };
+
+    match MODE_NAMES[params.choice("mode")] {
+        "alpha" => (BlendState::ALPHA_BLENDING, false),
+        "premultiplied" => (BlendState::PREMULTIPLIED_ALPHA_BLENDING, true),
+        "additive" => (
+            BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            },
+            false,
+        ),
+        "multiply" => (
+            BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::Zero,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            },
+            false,
+        ),
+        "min" => (
+            both(BlendFactor::One, BlendFactor::One, BlendOperation::Min),
+            false,
+        ),
+        "max" => (
+            both(BlendFactor::One, BlendFactor::One, BlendOperation::Max),
+            false,
+        ),
+        "replace" => (BlendState::REPLACE, false),
+        _ => (
+            BlendState {
+                color: component("color-src", "color-dst", "color-op"),
+                alpha: component("alpha-src", "alpha-dst", "alpha-op"),
+            },
+            params.bool("premultiply"),
+        ),
+    }
+}
+
+// The parameter value which picks this factor or operation
+fn index<T: PartialEq>(list: &[T], value: T) -> Value {
+    Value::Enum(list.iter().position(|v| *v == value).unwrap())
+}
+
+// What a factor multiplies with, for the color (`rgb`) or alpha (`a`) channels
+fn factor(factor: BlendFactor, channels: &str) -> String {
+    match factor {
+        BlendFactor::Zero => "0".to_string(),
+        BlendFactor::One => "1".to_string(),
+        BlendFactor::Src => format!("src.{channels}"),
+        BlendFactor::OneMinusSrc => format!("(1 - src.{channels})"),
+        BlendFactor::SrcAlpha => "src.a".to_string(),
+        BlendFactor::OneMinusSrcAlpha => "(1 - src.a)".to_string(),
+        BlendFactor::Dst => format!("dst.{channels}"),
+        BlendFactor::OneMinusDst => format!("(1 - dst.{channels})"),
+        BlendFactor::DstAlpha => "dst.a".to_string(),
+        BlendFactor::OneMinusDstAlpha => "(1 - dst.a)".to_string(),
+        // Is 1 for alpha
+        BlendFactor::SrcAlphaSaturated if channels == "a" => "1".to_string(),
+        BlendFactor::SrcAlphaSaturated => "min(src.a, 1 - dst.a)".to_string(),
+        BlendFactor::Constant => format!("constant.{channels}"),
+        BlendFactor::OneMinusConstant => format!("(1 - constant.{channels})"),
+    }
+}
+
+// E.g. `src.rgb * src.a + dst.rgb * (1 - src.a)`
+fn equation(component: BlendComponent, channels: &str) -> String {
+    let (src, dst) = (format!("src.{channels}"), format!("dst.{channels}"));
+    // None if it's 0
+    let term = |value: &str, f| match f {
+        BlendFactor::Zero => None,
+        BlendFactor::One => Some(value.to_string()),
+        f => Some(format!("{value} * {}", factor(f, channels))),
+    };
+    let (a, b) = (
+        term(&src, component.src_factor),
+        term(&dst, component.dst_factor),
+    );
+    match (component.operation, a, b) {
+        (BlendOperation::Min, ..) => format!("min({src}, {dst})"),
+        (BlendOperation::Max, ..) => format!("max({src}, {dst})"),
+        (_, None, None) => "0".to_string(),
+        (BlendOperation::Add, Some(a), Some(b)) => format!("{a} + {b}"),
+        (BlendOperation::Subtract, Some(a), Some(b)) => format!("{a} - {b}"),
+        (BlendOperation::ReverseSubtract, Some(a), Some(b)) => format!("{b} - {a}"),
+        (BlendOperation::Subtract, None, Some(b)) => format!("-{b}"),
+        (BlendOperation::ReverseSubtract, Some(a), None) => format!("-{a}"),
+        (_, Some(x), None) | (_, None, Some(x)) => x,
+    }
+}
+
+pub struct Example10 {
+    common: ExampleCommonState,
+    // Per polygon mode
+    quads_pipeline: PerPolygonMode<RenderPipeline>,
+    background_pipeline: Option<RenderPipeline>,
+    bgl0: BindGroupLayout,
+    bg0: BindGroup,
+    globals_buf: Buffer,
+    // The quads in list order, then sorted
+    quads_buf: Buffer,
+
+    // What `quads_pipeline` blends with, the equation is printed when it changes
+    blend: Option<(BlendState, bool)>,
+}
+
+impl Example for Example10 {
+    fn on_enter(&mut self, _data: &ExampleData) {
+        println!("Left: The quads in list order. Right: Sorted back to front.");
+        // Print the equation again
+        self.blend = None;
+    }
+
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
+    }
+
+    fn common(&mut self) -> &mut ExampleCommonState {
+        &mut self.common
+    }
+
+    fn handle_action(&mut self, action: &str) {
+        let params = &mut self.common.params;
+        match action {
+            "next-mode" => params.adjust("mode", 1),
+            "previous-mode" => params.adjust("mode", -1),
+            // Cycling a factor or operation switches to custom, starting from the current mode
+            action => {
+                let mode = params.choice("mode");
+                if mode != CUSTOM {
+                    let (blend, premultiply) = blend_mode(params);
+                    for (channels, component) in [("color", blend.color), ("alpha", blend.alpha)] {
+                        let src = index(&FACTORS, component.src_factor);
+                        let dst = index(&FACTORS, component.dst_factor);
+                        let op = index(&OPERATIONS, component.operation);
+                        params.set(&format!("{channels}-src"), src);
+                        params.set(&format!("{channels}-dst"), dst);
+                        params.set(&format!("{channels}-op"), op);
+                    }
+                    params.set("premultiply", Value::Bool(premultiply));
+                    params.set("mode", Value::Enum(CUSTOM));
+                }
+                params.adjust(action, 1);
+            }
+        }
+    }
+}
+
+impl Example10 {
+    pub fn new(e: &ExampleData) -> Self {
+        let shader_source = "ex10.wgsl";
+        let common = ExampleCommonState::new(&e.device, e.swapchain_format, shader_source, "ex10");
+
+        let bgl0 = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex10-bgl0".into(),
+                entries: &[
+                    // Scale, premultiply
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let globals_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex10-uni-globals".into(),
+            contents: bytemuck::bytes_of(&Globals::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bg0 = e.device.create_bind_group(&BindGroupDescriptor {
+            label: "ex10-bg0".into(),
+            layout: &bgl0,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: globals_buf.as_entire_binding(),
+            }],
+        });
+
+        let quads_buf = e.device.create_buffer(&BufferDescriptor {
+            label: "ex10-quads".into(),
+            size: (size_of::<Quad>() * COLORS.len() * 2) as _,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            common,
+            quads_pipeline: PerPolygonMode::default(),
+            background_pipeline: None,
+            bgl0,
+            bg0,
+            globals_buf,
+            quads_buf,
+            blend: None,
+        }
+    }
+
+    // The quads when `blend` is given, else the background
+    fn render_pipeline(&self, e: &ExampleData, blend: Option<BlendState>) -> RenderPipeline {
+        let module = &self.common.shader_module;
+        let quads = blend.is_some();
+        let instances = [VertexBufferLayout {
+            array_stride: size_of::<Quad>() as _,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32, 2 => Float32x4],
+        }];
+
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: if quads {
+                "ex10-rp-quads"
+            } else {
+                "ex10-rp-background"
+            }
+            .into(),
+            layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: "ex10-pld".into(),
+                bind_group_layouts: &[&self.bgl0],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module,
+                entry_point: if quads { "vs" } else { "vs_background" },
+                buffers: if quads { &instances } else { &[] },
+            },
+            fragment: Some(FragmentState {
+                module,
+                entry_point: if quads { "fs" } else { "fs_background" },
+                targets: &[Some(ColorTargetState {
+                    format: e.swapchain_format,
+                    blend,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                polygon_mode: if quads {
+                    self.common.polygon_mode
+                } else {
+                    wgpu::PolygonMode::Fill
+                },
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        let params = &self.common.params;
+        let (blend, premultiply) = blend_mode(params);
+
+        if self.blend != Some((blend, premultiply)) {
+            let mode = MODE_NAMES[params.choice("mode")];
+            println!("ex10 blend mode: {mode}");
+            println!("    rgb = {}", equation(blend.color, "rgb"));
+            println!("    a   = {}", equation(blend.alpha, "a"));
+            if premultiply {
+                println!("    (src.rgb is premultiplied by src.a in the shader)");
+            }
+            self.blend = Some((blend, premultiply));
+            self.quads_pipeline.clear();
+        }
+        if self.common.dirty || self.background_pipeline.is_none() {
+            self.common.dirty = false;
+            self.quads_pipeline.clear();
+            self.background_pipeline = Some(self.render_pipeline(e, None));
+        }
+        let mode = self.common.polygon_mode;
+        if self.quads_pipeline.get(mode).is_none() {
+            let pipeline = self.render_pipeline(e, Some(blend));
+            self.quads_pipeline.insert(mode, pipeline);
+        }
+
+        // Half the target for each set of quads, the quads are kept square
+        let [width, height] = e.viewport;
+        let half = width / 2.;
+        let globals = Globals {
+            scale: if half > height {
+                [height / half, 1.]
+            } else {
+                [1., half / height]
+            },
+            premultiply: premultiply as u32,
+            _pad: 0,
+        };
+        e.queue
+            .write_buffer(&self.globals_buf, 0, bytemuck::bytes_of(&globals));
+
+        // A carousel seen from slightly above, the ones further back are smaller
+        let opacity = params.f32("opacity");
+        let spin = self.common.time.as_secs_f32() * 0.5;
+        let mut quads: Vec<(f32, Quad)> = COLORS
+            .iter()
+            .enumerate()
+            .map(|(i, &[r, g, b])| {
+                let angle = spin + i as f32 * TAU / COLORS.len() as f32;
+                // 1 is the back
+                let depth = angle.sin();
+                let quad = Quad {
+                    center: [0.45 * angle.cos(), 0.15 * depth],
+                    size: 0.3 * (1. - 0.3 * depth),
+                    color: [r, g, b, opacity],
+                };
+                (depth, quad)
+            })
+            .collect();
+        let mut instances: Vec<Quad> = quads.iter().map(|&(_, quad)| quad).collect();
+        quads.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        instances.extend(quads.iter().map(|&(_, quad)| quad));
+        e.queue
+            .write_buffer(&self.quads_buf, 0, bytemuck::cast_slice(&instances));
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "ex10-ce".into(),
+        });
+
+        {
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "ex10-rp".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: screen_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: wgpu::LoadOp::Clear(Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            let constant = params.f32("constant") as f64;
+            rpass.set_blend_constant(Color {
+                r: constant,
+                g: constant,
+                b: constant,
+                a: constant,
+            });
+            rpass.set_bind_group(0, &self.bg0, &[]);
+
+            let quads_size = (size_of::<Quad>() * COLORS.len()) as u64;
+            for (i, label) in ["ex10-unsorted", "ex10-sorted"].into_iter().enumerate() {
+                // A gap of a pixel in between
+                rpass.set_viewport(i as f32 * half, 0., half - 1., height, 0., 1.);
+
+                rpass.set_pipeline(self.background_pipeline.as_ref().unwrap());
+                rpass.draw(0..3, 0..1);
+
+                rpass.set_pipeline(self.quads_pipeline.get(self.common.polygon_mode).unwrap());
+                let offset = i as u64 * quads_size;
+                rpass.set_vertex_buffer(0, self.quads_buf.slice(offset..offset + quads_size));
+                debug::marker(&mut rpass, label);
+                rpass.draw(0..6, 0..COLORS.len() as u32);
+            }
+        }
+
+        e.queue.submit(std::iter::once(ce.finish()));
+    }
+}
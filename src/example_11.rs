@@ -0,0 +1,618 @@
+/*
+Goals:
+    - Use the stencil buffer (see `depth_stencil.rs` for the attachment)
+    - One pass writes a mask into stencil: A draggable quad (like example 03 started out) and a spinning square
+    - The next pass draws different content inside and outside the mask, via the stencil test
+    - Cycle the compare functions and stencil operations, and print what each pass does with them
+    - Show the stencil values themselves
+
+Things we learned:
+    - The stencil test is `reference <compare> stencil`, with the reference set per pass (`set_stencil_reference`),
+        and the operation on pass/fail (and depth fail) is part of the pipeline.
+    - A pipeline which only writes stencil still needs a fragment stage here, since the pass has a color attachment.
+        `ColorWrites::empty()` keeps it out of the color target.
+    - Draws within a pass see each other's stencil writes, so the overlap of the two mask shapes can count up
+        with `increment-clamp`, or cancel out with `invert`.
+    - The stencil values can be shown without sampling the texture, via the stencil test itself:
+        Draw value v's color where `v == stencil`, for each v.
+        The value goes to the shader via the vertex index: On GL (naga's GLSL) the instance index doesn't
+        include the first instance, so `draw(0..3, v..v + 1)` showed value 0 everywhere.
+    - A pass with `None` stencil ops treats stencil as read only, pipelines in it must not write stencil.
+    - Front and back faces have separate stencil states. Dragging a vertex across flips the quad's winding,
+        so both faces get the same state here.
+ */
+use std::mem::size_of;
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, Buffer, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandEncoderDescriptor, CompareFunction, FragmentState, IndexFormat, MultisampleState,
+    Operations, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderStages, StencilFaceState, StencilOperation, StencilState, TextureView,
+    VertexBufferLayout, VertexState,
+};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    depth_stencil::{self, DepthStencil},
+    input::{InputEvent, MouseButton},
+    keymap::{key, Action},
+    params::Param,
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    // Named like the parameters they cycle
+    Action {
+        name: "mask-compare",
+        description: "Next compare function for the mask pass",
+        keys: &[key(VirtualKeyCode::Key1)],
+    },
+    Action {
+        name: "mask-op",
+        description: "Next stencil operation for the mask pass",
+        keys: &[key(VirtualKeyCode::Key2)],
+    },
+    Action {
+        name: "compare",
+        description: "Next compare function for the inside content",
+        keys: &[key(VirtualKeyCode::Key3)],
+    },
+    Action {
+        name: "more-reference",
+        description: "Increase the stencil reference value",
+        keys: &[key(VirtualKeyCode::Up)],
+    },
+    Action {
+        name: "less-reference",
+        description: "Decrease the stencil reference value",
+        keys: &[key(VirtualKeyCode::Down)],
+    },
+    Action {
+        name: "values",
+        description: "Toggle showing the stencil values",
+        keys: &[key(VirtualKeyCode::Space)],
+    },
+    Action {
+        name: "outline",
+        description: "Toggle the outlines of the mask shapes",
+        keys: &[key(VirtualKeyCode::O)],
+    },
+];
+
+const COMPARES: [CompareFunction; 8] = [
+    CompareFunction::Never,
+    CompareFunction::Less,
+    CompareFunction::Equal,
+    CompareFunction::LessEqual,
+    CompareFunction::Greater,
+    CompareFunction::NotEqual,
+    CompareFunction::GreaterEqual,
+    CompareFunction::Always,
+];
+const COMPARE_NAMES: &[&str] = &[
+    "never",
+    "less",
+    "equal",
+    "less-equal",
+    "greater",
+    "not-equal",
+    "greater-equal",
+    "always",
+];
+
+const OPERATIONS: [StencilOperation; 8] = [
+    StencilOperation::Keep,
+    StencilOperation::Zero,
+    StencilOperation::Replace,
+    StencilOperation::Invert,
+    StencilOperation::IncrementClamp,
+    StencilOperation::DecrementClamp,
+    StencilOperation::IncrementWrap,
+    StencilOperation::DecrementWrap,
+];
+const OPERATION_NAMES: &[&str] = &[
+    "keep",
+    "zero",
+    "replace",
+    "invert",
+    "increment-clamp",
+    "decrement-clamp",
+    "increment-wrap",
+    "decrement-wrap",
+];
+
+// See `params.rs`
+pub const PARAMS: &[Param] = &[
+    Param::choice(
+        "mask-compare",
+        "Mask pass: Where the shapes pass, `reference <compare> stencil`",
+        COMPARE_NAMES,
+        7,
+    ),
+    Param::choice(
+        "mask-op",
+        "Mask pass: What the shapes do to stencil where they pass",
+        OPERATION_NAMES,
+        2,
+    ),
+    Param::choice(
+        "compare",
+        "Content pass: Where the inside is drawn, the outside gets the rest",
+        COMPARE_NAMES,
+        2,
+    ),
+    Param::u32("reference", "Stencil reference value", 1, 0, 255, 1),
+    Param::bool(
+        "values",
+        "Show the stencil values instead of the content",
+        false,
+    ),
+    Param::bool("outline", "Outline the mask shapes", true),
+];
+
+// How close (clip space) the mouse must be to grab a corner
+const GRAB_DISTANCE: f32 = 0.08;
+
+// The quad's corners, then the square's. Triangles, then the outline.
+const INDICES: [u16; 11] = [0, 1, 2, 0, 2, 3, 0, 1, 2, 3, 0];
+
+// What passes where the compare function doesn't, such that inside and outside cover everything
+fn complement(compare: CompareFunction) -> CompareFunction {
+    match compare {
+        CompareFunction::Never => CompareFunction::Always,
+        CompareFunction::Less => CompareFunction::GreaterEqual,
+        CompareFunction::Equal => CompareFunction::NotEqual,
+        CompareFunction::LessEqual => CompareFunction::Greater,
+        CompareFunction::Greater => CompareFunction::LessEqual,
+        CompareFunction::NotEqual => CompareFunction::Equal,
+        CompareFunction::GreaterEqual => CompareFunction::Less,
+        CompareFunction::Always => CompareFunction::Never,
+    }
+}
+
+// E.g. `where 1 == stencil`
+fn condition(compare: CompareFunction, reference: u32) -> String {
+    let op = match compare {
+        CompareFunction::Never => return "never".to_string(),
+        CompareFunction::Always => return "everywhere".to_string(),
+        CompareFunction::Less => "<",
+        CompareFunction::Equal => "==",
+        CompareFunction::LessEqual => "<=",
+        CompareFunction::Greater => ">",
+        CompareFunction::NotEqual => "!=",
+        CompareFunction::GreaterEqual => ">=",
+    };
+    format!("where {reference} {op} stencil")
+}
+
+fn operation(op: StencilOperation, reference: u32) -> String {
+    match op {
+        StencilOperation::Keep => "stencil unchanged".to_string(),
+        StencilOperation::Zero => "stencil = 0".to_string(),
+        StencilOperation::Replace => format!("stencil = {reference}"),
+        StencilOperation::Invert => "stencil = !stencil (bitwise)".to_string(),
+        StencilOperation::IncrementClamp => "stencil += 1, up to 255".to_string(),
+        StencilOperation::DecrementClamp => "stencil -= 1, down to 0".to_string(),
+        StencilOperation::IncrementWrap => "stencil += 1, 255 wraps to 0".to_string(),
+        StencilOperation::DecrementWrap => "stencil -= 1, 0 wraps to 255".to_string(),
+    }
+}
+
+// The same test and operation for front and back faces
+fn stencil(compare: CompareFunction, pass_op: StencilOperation) -> StencilState {
+    let face = StencilFaceState {
+        compare,
+        fail_op: StencilOperation::Keep,
+        depth_fail_op: StencilOperation::Keep,
+        pass_op,
+    };
+    StencilState {
+        front: face,
+        back: face,
+        read_mask: 0xff,
+        write_mask: if pass_op == StencilOperation::Keep {
+            0
+        } else {
+            0xff
+        },
+    }
+}
+
+// Mask compare, mask operation, content compare
+type StencilSettings = (CompareFunction, StencilOperation, CompareFunction);
+
+struct Pipelines {
+    mask: RenderPipeline,
+    inside: RenderPipeline,
+    outside: RenderPipeline,
+    values: RenderPipeline,
+    outline: RenderPipeline,
+}
+
+pub struct Example11 {
+    common: ExampleCommonState,
+    // Per polygon mode
+    pipelines: PerPolygonMode<Pipelines>,
+    layout: PipelineLayout,
+    bg0: BindGroup,
+    time_buf: Buffer,
+    vertex_buf: Buffer,
+    index_buf: Buffer,
+    depth_stencil: DepthStencil,
+
+    // Clip space, counter clockwise from the bottom left
+    quad: [[f32; 2]; 4],
+    // Index of the grabbed corner
+    selected: Option<usize>,
+
+    // What `pipelines` were made with, and the reference, printed when they change
+    settings: Option<(StencilSettings, u32)>,
+}
+
+impl Example for Example11 {
+    fn on_enter(&mut self, _data: &ExampleData) {
+        // Print the passes again
+        self.settings = None;
+    }
+
+    fn on_exit(&mut self) {
+        self.selected = None;
+    }
+
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
+    }
+
+    fn common(&mut self) -> &mut ExampleCommonState {
+        &mut self.common
+    }
+
+    fn handle_action(&mut self, action: &str) {
+        let params = &mut self.common.params;
+        let name = match action {
+            "more-reference" | "less-reference" => {
+                params.adjust("reference", if action == "more-reference" { 1 } else { -1 });
+                "reference"
+            }
+            name => {
+                params.adjust(name, 1);
+                name
+            }
+        };
+        println!("{}.{}", self.common.label, params.describe(name));
+    }
+
+    fn handle_input(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::CursorMoved { position } => {
+                if let Some(i) = self.selected {
+                    self.quad[i] = position.clip;
+                }
+            }
+            InputEvent::Button {
+                button: MouseButton::Left,
+                pressed,
+                position,
+                ..
+            } => {
+                let [x, y] = position.clip;
+                self.selected = pressed
+                    .then(|| {
+                        self.quad.iter().position(|&[cx, cy]| {
+                            ((cx - x).powi(2) + (cy - y).powi(2)).sqrt() < GRAB_DISTANCE
+                        })
+                    })
+                    .flatten();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Example11 {
+    pub fn new(e: &ExampleData) -> Self {
+        let shader_source = "ex11.wgsl";
+        let common = ExampleCommonState::new(&e.device, e.swapchain_format, shader_source, "ex11");
+
+        let bgl0 = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex11-bgl0".into(),
+                entries: &[
+                    // Time
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let layout = e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: "ex11-pld".into(),
+            bind_group_layouts: &[&bgl0],
+            push_constant_ranges: &[],
+        });
+
+        let time_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex11-uni-time".into(),
+            contents: bytemuck::bytes_of(&0f32),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let bg0 = e.device.create_bind_group(&BindGroupDescriptor {
+            label: "ex11-bg0".into(),
+            layout: &bgl0,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: time_buf.as_entire_binding(),
+            }],
+        });
+
+        let vertex_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex11-vertices".into(),
+            contents: bytemuck::cast_slice(&[[0f32; 2]; 8]),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+        let index_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex11-indices".into(),
+            contents: bytemuck::cast_slice(&INDICES),
+            usage: BufferUsages::INDEX,
+        });
+
+        Self {
+            common,
+            pipelines: PerPolygonMode::default(),
+            layout,
+            bg0,
+            time_buf,
+            vertex_buf,
+            index_buf,
+            depth_stencil: DepthStencil::new("ex11-depth-stencil"),
+            quad: [[-0.6, -0.5], [0.3, -0.5], [0.3, 0.4], [-0.6, 0.4]],
+            selected: None,
+            settings: None,
+        }
+    }
+
+    // The shapes when `topology` is given, else a full screen triangle
+    fn pipeline(
+        &self,
+        e: &ExampleData,
+        fs: &str,
+        topology: Option<PrimitiveTopology>,
+        stencil: StencilState,
+        write_mask: ColorWrites,
+    ) -> RenderPipeline {
+        let module = &self.common.shader_module;
+        let shapes = [VertexBufferLayout {
+            array_stride: size_of::<[f32; 2]>() as _,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+        }];
+
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some(&format!("ex11-rp-{fs}")),
+            layout: Some(&self.layout),
+            vertex: VertexState {
+                module,
+                entry_point: if topology.is_some() {
+                    "vs_shape"
+                } else {
+                    "vs_full_screen"
+                },
+                buffers: if topology.is_some() { &shapes } else { &[] },
+            },
+            fragment: Some(FragmentState {
+                module,
+                entry_point: fs,
+                targets: &[Some(ColorTargetState {
+                    format: e.swapchain_format,
+                    blend: None,
+                    write_mask,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: topology.unwrap_or(PrimitiveTopology::TriangleList),
+                polygon_mode: match topology {
+                    Some(PrimitiveTopology::TriangleList) => self.common.polygon_mode,
+                    _ => wgpu::PolygonMode::Fill,
+                },
+                ..Default::default()
+            },
+            // Depth isn't used
+            depth_stencil: Some(depth_stencil::state(
+                CompareFunction::Always,
+                false,
+                stencil,
+            )),
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn pipelines(&self, e: &ExampleData, settings: StencilSettings) -> Pipelines {
+        let (mask_compare, mask_op, compare) = settings;
+        let keep = StencilOperation::Keep;
+        let color = ColorWrites::ALL;
+        Pipelines {
+            mask: self.pipeline(
+                e,
+                "fs_outline",
+                Some(PrimitiveTopology::TriangleList),
+                stencil(mask_compare, mask_op),
+                // Only stencil
+                ColorWrites::empty(),
+            ),
+            inside: self.pipeline(e, "fs_inside", None, stencil(compare, keep), color),
+            outside: self.pipeline(
+                e,
+                "fs_outside",
+                None,
+                stencil(complement(compare), keep),
+                color,
+            ),
+            values: self.pipeline(
+                e,
+                "fs_values",
+                None,
+                stencil(CompareFunction::Equal, keep),
+                color,
+            ),
+            outline: self.pipeline(
+                e,
+                "fs_outline",
+                Some(PrimitiveTopology::LineStrip),
+                stencil(CompareFunction::Always, keep),
+                color,
+            ),
+        }
+    }
+
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        let params = &self.common.params;
+        let settings = (
+            COMPARES[params.choice("mask-compare")],
+            OPERATIONS[params.choice("mask-op")],
+            COMPARES[params.choice("compare")],
+        );
+        let reference = params.u32("reference");
+
+        if self.settings != Some((settings, reference)) {
+            let (mask_compare, mask_op, compare) = settings;
+            println!("ex11 stencil, reference {reference}:");
+            println!(
+                "    mask pass: shapes {}: {}",
+                condition(mask_compare, reference),
+                operation(mask_op, reference)
+            );
+            println!(
+                "    content pass: inside {}, outside {}",
+                condition(compare, reference),
+                condition(complement(compare), reference)
+            );
+            if self.settings.map(|(s, _)| s) != Some(settings) {
+                self.pipelines.clear();
+            }
+            self.settings = Some((settings, reference));
+        }
+        if self.common.dirty {
+            self.common.dirty = false;
+            self.pipelines.clear();
+        }
+        let mode = self.common.polygon_mode;
+        if self.pipelines.get(mode).is_none() {
+            let pipelines = self.pipelines(e, settings);
+            self.pipelines.insert(mode, pipelines);
+        }
+        let pipelines = self.pipelines.get(mode).unwrap();
+
+        // The square spins around its center
+        let time = self.common.time.as_secs_f32();
+        let square = [0.25f32, 0.5, 0.75, 1.].map(|turns| {
+            let angle = time * 0.5 + turns * std::f32::consts::TAU + 0.785;
+            [0.35 + 0.4 * angle.cos(), 0.15 + 0.4 * angle.sin()]
+        });
+        let vertices = [self.quad, square].concat();
+        e.queue
+            .write_buffer(&self.vertex_buf, 0, bytemuck::cast_slice(&vertices));
+        e.queue
+            .write_buffer(&self.time_buf, 0, bytemuck::bytes_of(&time));
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "ex11-ce".into(),
+        });
+
+        // Clear stencil to 0, then the shapes write the mask
+        {
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "ex11-rp-mask".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: screen_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        // Gray where the values view shows nothing (8 and up)
+                        load: wgpu::LoadOp::Clear(Color {
+                            r: 0.5,
+                            g: 0.5,
+                            b: 0.5,
+                            a: 1.,
+                        }),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(self.depth_stencil.attachment(
+                    e,
+                    None,
+                    Some(Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: true,
+                    }),
+                )),
+            });
+
+            rpass.set_pipeline(&pipelines.mask);
+            rpass.set_stencil_reference(reference);
+            rpass.set_bind_group(0, &self.bg0, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+            rpass.set_index_buffer(self.index_buf.slice(..), IndexFormat::Uint16);
+            debug::marker(&mut rpass, "ex11-mask-quad");
+            rpass.draw_indexed(0..6, 0, 0..1);
+            debug::marker(&mut rpass, "ex11-mask-square");
+            rpass.draw_indexed(0..6, 4, 0..1);
+        }
+
+        // Stencil is read only from here on
+        {
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "ex11-rp-content".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: screen_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(self.depth_stencil.attachment(e, None, None)),
+            });
+
+            rpass.set_bind_group(0, &self.bg0, &[]);
+            if params.bool("values") {
+                // Value v's color where `v == stencil`
+                rpass.set_pipeline(&pipelines.values);
+                for value in 0..8 {
+                    rpass.set_stencil_reference(value);
+                    rpass.draw(value * 3..value * 3 + 3, 0..1);
+                }
+            } else {
+                rpass.set_stencil_reference(reference);
+                debug::marker(&mut rpass, "ex11-inside");
+                rpass.set_pipeline(&pipelines.inside);
+                rpass.draw(0..3, 0..1);
+                debug::marker(&mut rpass, "ex11-outside");
+                rpass.set_pipeline(&pipelines.outside);
+                rpass.draw(0..3, 0..1);
+            }
+
+            if params.bool("outline") {
+                rpass.set_pipeline(&pipelines.outline);
+                rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+                rpass.set_index_buffer(self.index_buf.slice(..), IndexFormat::Uint16);
+                rpass.draw_indexed(6..11, 0, 0..1);
+                rpass.draw_indexed(6..11, 4, 0..1);
+            }
+        }
+
+        e.queue.submit(std::iter::once(ce.finish()));
+    }
+}
@@ -0,0 +1,485 @@
+/*
+Goals:
+    - Draw with `draw_indirect`: The GPU decides how many instances are drawn
+    - Example 02's ring scaled up to tens of thousands of instances, most of them off screen
+    - A compute pass culls them against the viewport, or a region around the mouse,
+        compacts the survivors into an instance buffer and counts them into the indirect args
+    - Read the count back once a second, to see that culling works
+
+Things we learned:
+    - The indirect args are just four u32s in a buffer with `INDIRECT` usage (`wgpu::util::DrawIndirect`):
+        vertex count, instance count, first vertex and first instance.
+        The compute shader sees them as a struct, with the instance count `atomic<u32>` to append with `atomicAdd`.
+    - The count has to start at 0 every frame. `write_buffer` does that before the submit's passes run.
+    - The same buffer can be written as storage in the compute pass and then read as `INDIRECT` (or `VERTEX`)
+        in the render pass. wgpu puts the barriers in between, usages only conflict within a pass.
+    - The order of the survivors depends on which invocation got to the atomic first, so it changes every frame.
+        Fine here, but it matters for e.g. alpha blending (see example 10).
+    - The first instance has to be 0 unless `INDIRECT_FIRST_INSTANCE` is enabled.
+    - Reading back the count without stalling: Copy it to a `MAP_READ` buffer, `map_async`,
+        and look again on a later frame after `poll(Maintain::Poll)`. Like recording does, see `record.rs`.
+ */
+use std::{
+    mem::size_of,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt, DrawIndirect},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, Color,
+    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    FragmentState, Maintain, MapMode, MultisampleState, Operations, PipelineLayoutDescriptor,
+    PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, ShaderStages, TextureView, VertexBufferLayout, VertexState,
+};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    input::Position,
+    keymap::{key, Action},
+    params::Param,
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "cull",
+        description: "Cycle culling against the viewport, the mouse region, or not at all",
+        keys: &[key(VirtualKeyCode::Space)],
+    },
+    Action {
+        name: "more-instances",
+        description: "More instances",
+        keys: &[key(VirtualKeyCode::Up)],
+    },
+    Action {
+        name: "fewer-instances",
+        description: "Fewer instances",
+        keys: &[key(VirtualKeyCode::Down)],
+    },
+];
+
+const CULL_NAMES: &[&str] = &["viewport", "mouse", "off"];
+
+const MAX_INSTANCES: u32 = 200_000;
+
+// See `params.rs`
+pub const PARAMS: &[Param] = &[
+    Param::u32(
+        "instances",
+        "Number of instances",
+        50_000,
+        10_000,
+        MAX_INSTANCES,
+        10_000,
+    ),
+    Param::choice(
+        "cull",
+        "Cull against the viewport, a region around the mouse, or not at all",
+        CULL_NAMES,
+        0,
+    ),
+    Param::f32(
+        "region",
+        "Radius of the region around the mouse (clip space)",
+        0.4,
+        0.05,
+        2.,
+        0.05,
+    ),
+    Param::f32(
+        "inset",
+        "Move the viewport culling bounds in, to see them",
+        0.,
+        0.,
+        0.5,
+        0.05,
+    ),
+];
+
+// Invocations per workgroup of the culling shader
+const WORKGROUP_SIZE: u32 = 64;
+
+// Of an instance, from its center to a corner (spiral space)
+const INSTANCE_SIZE: f32 = 0.008;
+
+// How often the visible count is printed
+const PRINT_EVERY: Duration = Duration::from_secs(1);
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Cull {
+    mouse: [f32; 2],
+    aspect: [f32; 2],
+    instances: u32,
+    mode: u32,
+    time: f32,
+    region: f32,
+    inset: f32,
+    size: f32,
+}
+
+// Written by the culling shader
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    position: [f32; 2],
+    angle: f32,
+    index: u32,
+}
+
+// The visible count, on its way back from the GPU
+struct Readback {
+    instances: u32,
+    // Set by `map_async`'s callback: Unset while pending, then whether mapping worked
+    mapped: Arc<OnceLock<Result<(), BufferAsyncError>>>,
+}
+
+pub struct Example12 {
+    common: ExampleCommonState,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
+    cull_pipeline: Option<ComputePipeline>,
+    // Compute: Cull settings, instances, indirect args
+    bgl_cull: BindGroupLayout,
+    // Render: Cull settings
+    bgl_draw: BindGroupLayout,
+    bg_cull: BindGroup,
+    bg_draw: BindGroup,
+    cull_buf: Buffer,
+    // The survivors, room for `MAX_INSTANCES`
+    instance_buf: Buffer,
+    indirect_buf: Buffer,
+    // The instance count is copied here to be read
+    count_buf: Buffer,
+
+    readback: Option<Readback>,
+    last_print: Instant,
+}
+
+impl Example for Example12 {
+    fn on_enter(&mut self, _data: &ExampleData) {
+        self.last_print = Instant::now();
+    }
+
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
+    }
+
+    fn common(&mut self) -> &mut ExampleCommonState {
+        &mut self.common
+    }
+
+    fn handle_action(&mut self, action: &str) {
+        let params = &mut self.common.params;
+        let name = match action {
+            "more-instances" | "fewer-instances" => {
+                params.adjust("instances", if action == "more-instances" { 1 } else { -1 });
+                "instances"
+            }
+            name => {
+                params.adjust(name, 1);
+                name
+            }
+        };
+        println!("{}.{}", self.common.label, params.describe(name));
+    }
+
+    fn handle_scroll(&mut self, scroll_up: bool) {
+        let params = &mut self.common.params;
+        params.adjust("region", if scroll_up { 1 } else { -1 });
+    }
+}
+
+impl Example12 {
+    pub fn new(e: &ExampleData) -> Self {
+        let shader_source = "ex12.wgsl";
+        let common = ExampleCommonState::new(&e.device, e.swapchain_format, shader_source, "ex12");
+
+        let uniform = |binding, visibility| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bgl_cull = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex12-bgl-cull".into(),
+                entries: &[uniform(0, ShaderStages::COMPUTE), storage(1), storage(2)],
+            });
+        let bgl_draw = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex12-bgl-draw".into(),
+                entries: &[uniform(0, ShaderStages::VERTEX_FRAGMENT)],
+            });
+
+        let cull_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex12-uni-cull".into(),
+            contents: bytemuck::bytes_of(&Cull::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let instance_buf = e.device.create_buffer(&BufferDescriptor {
+            label: "ex12-instances".into(),
+            size: (size_of::<Instance>() as u32 * MAX_INSTANCES) as _,
+            usage: BufferUsages::STORAGE | BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+        let indirect_buf = e.device.create_buffer(&BufferDescriptor {
+            label: "ex12-indirect".into(),
+            size: size_of::<DrawIndirect>() as _,
+            usage: BufferUsages::STORAGE
+                | BufferUsages::INDIRECT
+                | BufferUsages::COPY_DST
+                | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let count_buf = e.device.create_buffer(&BufferDescriptor {
+            label: "ex12-count".into(),
+            size: size_of::<u32>() as _,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let bg_cull = e.device.create_bind_group(&BindGroupDescriptor {
+            label: "ex12-bg-cull".into(),
+            layout: &bgl_cull,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: cull_buf.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: instance_buf.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: indirect_buf.as_entire_binding(),
+                },
+            ],
+        });
+        let bg_draw = e.device.create_bind_group(&BindGroupDescriptor {
+            label: "ex12-bg-draw".into(),
+            layout: &bgl_draw,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: cull_buf.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            common,
+            render_pipeline: PerPolygonMode::default(),
+            cull_pipeline: None,
+            bgl_cull,
+            bgl_draw,
+            bg_cull,
+            bg_draw,
+            cull_buf,
+            instance_buf,
+            indirect_buf,
+            count_buf,
+            readback: None,
+            last_print: Instant::now(),
+        }
+    }
+
+    fn cull_pipeline(&self, e: &ExampleData) -> ComputePipeline {
+        e.device
+            .create_compute_pipeline(&ComputePipelineDescriptor {
+                label: "ex12-cp-cull".into(),
+                layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: "ex12-cull-pld".into(),
+                    bind_group_layouts: &[&self.bgl_cull],
+                    push_constant_ranges: &[],
+                })),
+                module: &self.common.shader_module,
+                entry_point: "cull",
+            })
+    }
+
+    fn render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: "ex12-rp".into(),
+            layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: "ex12-draw-pld".into(),
+                bind_group_layouts: &[&self.bgl_draw],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &self.common.shader_module,
+                entry_point: "vs",
+                buffers: &[VertexBufferLayout {
+                    array_stride: size_of::<Instance>() as _,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32, 2 => Uint32],
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &self.common.shader_module,
+                entry_point: "fs",
+                targets: &[Some(e.swapchain_format.into())],
+            }),
+            primitive: PrimitiveState {
+                polygon_mode: self.common.polygon_mode,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    // Print the count once it's back, start another readback once a second
+    fn read_count(&mut self, e: &ExampleData, instances: u32) {
+        // Doesn't wait, just runs the callback if the copy is done
+        e.device.poll(Maintain::Poll);
+        if let Some(readback) = &self.readback {
+            match readback.mapped.get() {
+                None => return,
+                // Not mapped, so the buffer is free for the next readback
+                Some(Err(err)) => println!("ex12: could not read back the visible count: {err}"),
+                Some(Ok(())) => {
+                    let slice = self.count_buf.slice(..);
+                    let visible: u32 = *bytemuck::from_bytes(&slice.get_mapped_range());
+                    self.count_buf.unmap();
+                    println!(
+                        "ex12: {visible} of {} instances visible ({:.1}%)",
+                        readback.instances,
+                        100. * visible as f32 / readback.instances as f32
+                    );
+                }
+            }
+            self.readback = None;
+        }
+
+        if self.last_print.elapsed() < PRINT_EVERY {
+            return;
+        }
+        self.last_print = Instant::now();
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "ex12-ce-count".into(),
+        });
+        // The instance count is the second u32
+        let offset = size_of::<u32>() as u64;
+        ce.copy_buffer_to_buffer(&self.indirect_buf, offset, &self.count_buf, 0, offset);
+        e.queue.submit(std::iter::once(ce.finish()));
+
+        let mapped = Arc::new(OnceLock::new());
+        let done = mapped.clone();
+        self.count_buf
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                let _ = done.set(result);
+            });
+        self.readback = Some(Readback { instances, mapped });
+    }
+
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        if self.common.dirty {
+            self.common.dirty = false;
+            self.render_pipeline.clear();
+            self.cull_pipeline = Some(self.cull_pipeline(e));
+        }
+        let mode = self.common.polygon_mode;
+        if self.render_pipeline.get(mode).is_none() {
+            let pipeline = self.render_pipeline(e);
+            self.render_pipeline.insert(mode, pipeline);
+        }
+
+        let params = &self.common.params;
+        let instances = params.u32("instances");
+        let [width, height] = e.viewport;
+        let cull = Cull {
+            mouse: Position::new(e.mouse, e.viewport, 1.).clip,
+            aspect: if width > height {
+                [height / width, 1.]
+            } else {
+                [1., width / height]
+            },
+            instances,
+            mode: params.choice("cull") as u32,
+            time: self.common.time.as_secs_f32(),
+            region: params.f32("region"),
+            inset: params.f32("inset"),
+            size: INSTANCE_SIZE,
+        };
+        e.queue
+            .write_buffer(&self.cull_buf, 0, bytemuck::bytes_of(&cull));
+        // The culling shader counts up from 0
+        let args = DrawIndirect {
+            vertex_count: 3,
+            instance_count: 0,
+            base_vertex: 0,
+            base_instance: 0,
+        };
+        e.queue.write_buffer(&self.indirect_buf, 0, args.as_bytes());
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "ex12-ce".into(),
+        });
+
+        {
+            let mut cpass = ce.begin_compute_pass(&ComputePassDescriptor {
+                label: "ex12-cp".into(),
+            });
+            cpass.set_pipeline(self.cull_pipeline.as_ref().unwrap());
+            cpass.set_bind_group(0, &self.bg_cull, &[]);
+            debug::marker(&mut cpass, &format!("ex12-cull x{instances}"));
+            cpass.dispatch_workgroups(instances.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        {
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "ex12-rp".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: screen_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: wgpu::LoadOp::Clear(Color {
+                            r: 0.05,
+                            g: 0.05,
+                            b: 0.1,
+                            a: 1.,
+                        }),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
+            rpass.set_bind_group(0, &self.bg_draw, &[]);
+            rpass.set_vertex_buffer(0, self.instance_buf.slice(..));
+            debug::marker(&mut rpass, "ex12-survivors");
+            rpass.draw_indirect(&self.indirect_buf, 0);
+        }
+
+        e.queue.submit(std::iter::once(ce.finish()));
+
+        self.read_count(e, instances);
+    }
+}
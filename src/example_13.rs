@@ -0,0 +1,443 @@
+/*
+Goals:
+    - A large static scene: A grid of thousands of tiles, one draw call each with its own uniforms (dynamic offset)
+    - Draw it two ways: Encode every draw again each frame, or record them once into `RenderBundle`s
+        and replay those with `execute_bundles`
+    - Measure the CPU time spent encoding the render pass, to see the difference
+    - Record the bundles again whenever what they captured changes
+
+Things we learned:
+    - A render bundle encoder is like a render pass without attachments: It is created with the formats
+        (and sample count) of the passes it may be executed in, and the pipelines have to match them.
+    - `wgpu::util::RenderEncoder` is implemented by both `RenderPass` and `RenderBundleEncoder`,
+        so the same code records either way.
+    - A bundle captures the pipeline, bind groups and dynamic offsets, not buffer contents:
+        Writing to the time uniform still animates a recorded scene.
+    - A bundle keeps the pipeline it was recorded with alive. After a rebuild (polygon mode, hot reload)
+        the old bundles would keep drawing the old way, so they have to be recorded again.
+    - Pass state doesn't leak into or out of bundles: After `execute_bundles` the pipeline and bind groups
+        have to be set again before the next draw.
+    - Most of the validation happens when a bundle is finished, so replaying one is cheap.
+ */
+use std::{
+    mem::size_of,
+    time::{Duration, Instant},
+};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt, RenderEncoder},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, Buffer, BufferBinding, BufferDescriptor, BufferSize, BufferUsages, Color,
+    CommandEncoderDescriptor, FragmentState, MultisampleState, Operations,
+    PipelineLayoutDescriptor, PrimitiveState, RenderBundle, RenderBundleDescriptor,
+    RenderBundleEncoderDescriptor, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, ShaderStages, TextureView, VertexState,
+};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    debug,
+    keymap::{key, Action},
+    params::Param,
+    util::{ExampleCommonState, PerPolygonMode},
+    Example, ExampleData,
+};
+
+// See `keymap.rs`
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "mode",
+        description: "Toggle re-encoding every frame and replaying render bundles",
+        keys: &[key(VirtualKeyCode::Space)],
+    },
+    Action {
+        name: "bigger-grid",
+        description: "More tiles",
+        keys: &[key(VirtualKeyCode::Up)],
+    },
+    Action {
+        name: "smaller-grid",
+        description: "Fewer tiles",
+        keys: &[key(VirtualKeyCode::Down)],
+    },
+];
+
+const MODE_NAMES: &[&str] = &["re-encode", "bundles"];
+
+const MAX_GRID: u32 = 128;
+
+// See `params.rs`
+pub const PARAMS: &[Param] = &[
+    Param::choice(
+        "mode",
+        "Encode all draws each frame, or replay them from render bundles",
+        MODE_NAMES,
+        0,
+    ),
+    Param::u32(
+        "grid",
+        "Tiles per side, one draw each",
+        64,
+        16,
+        MAX_GRID,
+        16,
+    ),
+];
+
+// How often the encode time is printed
+const PRINT_EVERY: Duration = Duration::from_secs(1);
+
+// Per draw, see `ex13.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Object {
+    position: [f32; 2],
+    size: f32,
+    phase: f32,
+}
+
+// Recorded for one grid size, one bundle per row
+struct Bundles {
+    grid: u32,
+    rows: Vec<RenderBundle>,
+}
+
+// CPU time spent encoding the render pass, since the last print
+struct EncodeTime {
+    mode: usize,
+    total: Duration,
+    frames: u32,
+    since: Instant,
+}
+
+pub struct Example13 {
+    common: ExampleCommonState,
+    // Per polygon mode
+    render_pipeline: PerPolygonMode<RenderPipeline>,
+    // Time
+    bgl0: BindGroupLayout,
+    // Object, dynamic offset
+    bgl1: BindGroupLayout,
+    bg0: BindGroup,
+    bg1: BindGroup,
+    time_buf: Buffer,
+    // Room for `MAX_GRID` squared objects, `stride` apart
+    object_buf: Buffer,
+    stride: u32,
+    // The grid size `object_buf` was written for
+    objects_grid: u32,
+
+    bundles: PerPolygonMode<Bundles>,
+    encode_time: EncodeTime,
+}
+
+impl Example for Example13 {
+    fn on_enter(&mut self, _data: &ExampleData) {
+        self.reset_encode_time();
+    }
+
+    fn render(&mut self, data: &ExampleData, target: &TextureView) {
+        self.do_render(data, target);
+    }
+
+    fn common(&mut self) -> &mut ExampleCommonState {
+        &mut self.common
+    }
+
+    fn handle_action(&mut self, action: &str) {
+        let params = &mut self.common.params;
+        let name = match action {
+            "bigger-grid" | "smaller-grid" => {
+                params.adjust("grid", if action == "bigger-grid" { 1 } else { -1 });
+                "grid"
+            }
+            name => {
+                params.adjust(name, 1);
+                name
+            }
+        };
+        println!("{}.{}", self.common.label, params.describe(name));
+    }
+}
+
+impl Example13 {
+    pub fn new(e: &ExampleData) -> Self {
+        let shader_source = "ex13.wgsl";
+        let common = ExampleCommonState::new(&e.device, e.swapchain_format, shader_source, "ex13");
+
+        let uniform = |has_dynamic_offset| BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bgl0 = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex13-bgl0".into(),
+                entries: &[uniform(false)],
+            });
+        let bgl1 = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "ex13-bgl1".into(),
+                entries: &[uniform(true)],
+            });
+
+        let time_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "ex13-uni-time".into(),
+            contents: bytemuck::bytes_of(&0f32),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        // Dynamic offsets have to be multiples of this
+        let stride = e
+            .device
+            .limits()
+            .min_uniform_buffer_offset_alignment
+            .max(size_of::<Object>() as u32);
+        let object_buf = e.device.create_buffer(&BufferDescriptor {
+            label: "ex13-uni-objects".into(),
+            size: (stride * MAX_GRID * MAX_GRID) as _,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bg0 = e.device.create_bind_group(&BindGroupDescriptor {
+            label: "ex13-bg0".into(),
+            layout: &bgl0,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: time_buf.as_entire_binding(),
+            }],
+        });
+        // One object's worth, moved along by the dynamic offset
+        let bg1 = e.device.create_bind_group(&BindGroupDescriptor {
+            label: "ex13-bg1".into(),
+            layout: &bgl1,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(BufferBinding {
+                    buffer: &object_buf,
+                    offset: 0,
+                    size: BufferSize::new(size_of::<Object>() as _),
+                }),
+            }],
+        });
+
+        Self {
+            common,
+            render_pipeline: PerPolygonMode::default(),
+            bgl0,
+            bgl1,
+            bg0,
+            bg1,
+            time_buf,
+            object_buf,
+            stride,
+            objects_grid: 0,
+            bundles: PerPolygonMode::default(),
+            encode_time: EncodeTime {
+                mode: 0,
+                total: Duration::ZERO,
+                frames: 0,
+                since: Instant::now(),
+            },
+        }
+    }
+
+    fn render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: "ex13-rp".into(),
+            layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: "ex13-pld".into(),
+                bind_group_layouts: &[&self.bgl0, &self.bgl1],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &self.common.shader_module,
+                entry_point: "vs",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &self.common.shader_module,
+                entry_point: "fs",
+                targets: &[Some(e.swapchain_format.into())],
+            }),
+            primitive: PrimitiveState {
+                polygon_mode: self.common.polygon_mode,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    // The tiles, row by row
+    fn write_objects(&mut self, e: &ExampleData, grid: u32) {
+        let stride = self.stride as usize;
+        let size = 1. / grid as f32;
+        let mut data = vec![0u8; stride * (grid * grid) as usize];
+        for (i, chunk) in data.chunks_exact_mut(stride).enumerate() {
+            let [x, y] = [i as u32 % grid, i as u32 / grid];
+            let object = Object {
+                position: [
+                    -1. + (x as f32 * 2. + 1.) * size,
+                    1. - (y as f32 * 2. + 1.) * size,
+                ],
+                size,
+                phase: (x + y) as f32 * 0.2 + (x * 7 % 5) as f32,
+            };
+            chunk[..size_of::<Object>()].copy_from_slice(bytemuck::bytes_of(&object));
+        }
+        e.queue.write_buffer(&self.object_buf, 0, &data);
+        self.objects_grid = grid;
+    }
+
+    // Everything one row of tiles needs, the same for a pass or a bundle
+    fn encode_row<'a>(&'a self, encoder: &mut impl RenderEncoder<'a>, grid: u32, row: u32) {
+        encoder.set_pipeline(self.render_pipeline.get(self.common.polygon_mode).unwrap());
+        encoder.set_bind_group(0, &self.bg0, &[]);
+        for i in row * grid..(row + 1) * grid {
+            encoder.set_bind_group(1, &self.bg1, &[i * self.stride]);
+            encoder.draw(0..6, 0..1);
+        }
+    }
+
+    fn record_bundles(&self, e: &ExampleData, grid: u32) -> Bundles {
+        let start = Instant::now();
+        let rows = (0..grid)
+            .map(|row| {
+                let mut encoder =
+                    e.device
+                        .create_render_bundle_encoder(&RenderBundleEncoderDescriptor {
+                            label: "ex13-rbe".into(),
+                            color_formats: &[Some(e.swapchain_format)],
+                            depth_stencil: None,
+                            sample_count: 1,
+                            multiview: None,
+                        });
+                self.encode_row(&mut encoder, grid, row);
+                encoder.finish(&RenderBundleDescriptor {
+                    label: "ex13-bundle".into(),
+                })
+            })
+            .collect();
+        println!(
+            "ex13: recorded {grid} bundles ({} draws) in {:.2} ms",
+            grid * grid,
+            start.elapsed().as_secs_f64() * 1000.
+        );
+        Bundles { grid, rows }
+    }
+
+    fn reset_encode_time(&mut self) {
+        self.encode_time.total = Duration::ZERO;
+        self.encode_time.frames = 0;
+        self.encode_time.since = Instant::now();
+    }
+
+    // Average over the last second, started over when the mode changes
+    fn add_encode_time(&mut self, mode: usize, grid: u32, elapsed: Duration) {
+        if mode != self.encode_time.mode {
+            self.encode_time.mode = mode;
+            self.reset_encode_time();
+        }
+        let time = &mut self.encode_time;
+        time.total += elapsed;
+        time.frames += 1;
+        if time.since.elapsed() < PRINT_EVERY {
+            return;
+        }
+        println!(
+            "ex13: {}, {} draws: {:.3} ms encoding per frame (over {} frames)",
+            MODE_NAMES[mode],
+            grid * grid,
+            time.total.as_secs_f64() * 1000. / time.frames as f64,
+            time.frames
+        );
+        self.reset_encode_time();
+    }
+
+    pub fn do_render(&mut self, e: &ExampleData, screen_view: &TextureView) {
+        if self.common.dirty {
+            self.common.dirty = false;
+            self.render_pipeline.clear();
+            // Recorded with the old pipelines
+            self.bundles.clear();
+        }
+        let polygon_mode = self.common.polygon_mode;
+        if self.render_pipeline.get(polygon_mode).is_none() {
+            let pipeline = self.render_pipeline(e);
+            self.render_pipeline.insert(polygon_mode, pipeline);
+        }
+
+        let params = &self.common.params;
+        let mode = params.choice("mode");
+        let grid = params.u32("grid");
+        if self.objects_grid != grid {
+            self.write_objects(e, grid);
+        }
+        if self
+            .bundles
+            .get(polygon_mode)
+            .is_some_and(|bundles| bundles.grid != grid)
+        {
+            self.bundles.clear();
+        }
+        // Recorded on the first frame that replays them, for each polygon mode
+        if mode == 1 && self.bundles.get(polygon_mode).is_none() {
+            let bundles = self.record_bundles(e, grid);
+            self.bundles.insert(polygon_mode, bundles);
+        }
+
+        let time = self.common.time.as_secs_f32();
+        e.queue
+            .write_buffer(&self.time_buf, 0, bytemuck::bytes_of(&time));
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "ex13-ce".into(),
+        });
+
+        let start = Instant::now();
+        {
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "ex13-rp".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: screen_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: wgpu::LoadOp::Clear(Color {
+                            r: 0.02,
+                            g: 0.02,
+                            b: 0.05,
+                            a: 1.,
+                        }),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            if mode == 1 {
+                debug::marker(&mut rpass, "ex13-bundles");
+                rpass.execute_bundles(self.bundles.get(polygon_mode).unwrap().rows.iter());
+            } else {
+                debug::marker(&mut rpass, "ex13-re-encode");
+                for row in 0..grid {
+                    self.encode_row(&mut rpass, grid, row);
+                }
+            }
+        }
+        let elapsed = start.elapsed();
+
+        e.queue.submit(std::iter::once(ce.finish()));
+
+        self.add_encode_time(mode, grid, elapsed);
+    }
+}
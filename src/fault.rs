@@ -0,0 +1,122 @@
+/*
+Goals:
+    - A validation error or a panic in one example shouldn't take the whole zoo down
+    - Say which example broke and in which frame, then show a placeholder instead of it
+
+Things we learned:
+    - Errors outside an error scope go to the device's uncaptured error handler, which panics by default.
+    - Error scopes are a stack, so push out of memory then validation, and pop validation first.
+        A scope keeps only its first error, which is the interesting one:
+        e.g. a bad render pass makes the command buffer invalid, and submitting it is another error.
+    - On native `pop_error_scope` is ready right away, blocking on it doesn't stall anything.
+    - `catch_unwind` wants `AssertUnwindSafe` for the example. After a panic it might be half updated,
+        which is why a faulted example isn't rendered again until its shaders change or it's reset.
+ */
+use std::{any::Any, panic::AssertUnwindSafe};
+
+use wgpu::{
+    CommandEncoderDescriptor, Device, ErrorFilter, FragmentState, MultisampleState, Operations,
+    PipelineLayoutDescriptor, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, TextureView, VertexState,
+};
+
+use crate::{util::ExampleCommonState, ExampleData};
+
+// Run `f` in validation and out of memory error scopes, and catch a panic.
+// Gives the panic message or the first error instead, if any.
+pub fn capture<T>(device: &Device, f: impl FnOnce() -> T) -> Result<T, String> {
+    device.push_error_scope(ErrorFilter::OutOfMemory);
+    device.push_error_scope(ErrorFilter::Validation);
+
+    let result = std::panic::catch_unwind(AssertUnwindSafe(f));
+
+    let validation = pollster::block_on(device.pop_error_scope());
+    let out_of_memory = pollster::block_on(device.pop_error_scope());
+
+    match (result, validation.or(out_of_memory)) {
+        (Err(panic), _) => Err(format!("panic: {}", panic_message(panic.as_ref()))),
+        (Ok(_), Some(error)) => Err(error.to_string()),
+        (Ok(value), None) => Ok(value),
+    }
+}
+
+// `panic!` gives a `&str` or a `String`, depending on whether it formatted anything
+pub fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("(no message)")
+}
+
+// Shown instead of a faulted example
+pub struct Placeholder {
+    common: ExampleCommonState,
+    render_pipeline: Option<RenderPipeline>,
+}
+
+impl Placeholder {
+    pub fn new(e: &ExampleData) -> Self {
+        Self {
+            common: ExampleCommonState::new(&e.device, e.swapchain_format, "fault.wgsl", "fault"),
+            render_pipeline: None,
+        }
+    }
+
+    pub fn common(&mut self) -> &mut ExampleCommonState {
+        &mut self.common
+    }
+
+    fn make_render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: "fault-rpassd".into(),
+            layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: "fault-rpass-pld".into(),
+                bind_group_layouts: &[],
+                push_constant_ranges: &[],
+            })),
+            vertex: VertexState {
+                module: &self.common.shader_module,
+                entry_point: "vs",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &self.common.shader_module,
+                entry_point: "fs",
+                targets: &[Some(e.swapchain_format.into())],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    pub fn render(&mut self, e: &ExampleData, target: &TextureView) {
+        if self.common.dirty || self.render_pipeline.is_none() {
+            self.common.dirty = false;
+            self.render_pipeline = Some(self.make_render_pipeline(e));
+        }
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "fault-ce".into(),
+        });
+
+        {
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "fault-rp".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(self.render_pipeline.as_ref().unwrap());
+            rpass.draw(0..3, 0..1);
+        }
+
+        e.queue.submit(std::iter::once(ce.finish()));
+    }
+}
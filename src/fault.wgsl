@@ -0,0 +1,16 @@
+// Full screen triangle, draw 3
+@vertex
+fn vs(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * vec2<f32>(2., -2.) + vec2<f32>(-1., 1.), 0., 1.);
+}
+
+// Diagonal warning stripes, 32 pixels wide
+@fragment
+fn fs(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let stripe = u32((position.x + position.y) / 32.) % 2u;
+    if (stripe == 0u) {
+        return vec4<f32>(0.5, 0.05, 0.05, 1.);
+    }
+    return vec4<f32>(0.05, 0.05, 0.05, 1.);
+}
@@ -0,0 +1,114 @@
+/*
+The zoo without a window: `cargo run -- headless --listen /tmp/zoo.sock --size 800x600`.
+Examples render into a texture, and only when asked to through the socket (see `remote.rs`),
+e.g. by a test which clicks around and compares frames.
+
+Time doesn't follow the clock, every frame is 1/60 s. So the same requests give the same frames.
+
+Things we learned:
+    - No surface is needed for an adapter, `compatible_surface: None` is fine.
+    - `WGPU_BACKEND=gl` runs this on Mesa's llvmpipe, i.e. without any GPU.
+        It lacks some of the features the examples want (e.g. line polygon mode, texture arrays),
+        the examples using those fault and show the placeholder.
+ */
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use wgpu::{Texture, TextureDescriptor, TextureDimension, TextureView, TextureViewDescriptor};
+
+use crate::{
+    debug,
+    registry::Registry,
+    remote::{self, Request, Server},
+    util, ExampleData,
+};
+
+// What examples render into instead of a swapchain texture
+pub fn target(e: &ExampleData) -> (Texture, TextureView) {
+    let texture = e.device.create_texture(&TextureDescriptor {
+        label: "headless".into(),
+        size: e.extent_3d(),
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: e.swapchain_format,
+        usage: e.surface_usage,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    (texture, view)
+}
+
+// The example's next frame, 1/60 s after the last
+pub fn render_frame(examples: &mut Registry, index: usize, e: &ExampleData, target: &TextureView) {
+    debug::begin_frame();
+    let name = examples.name(index);
+    let common = examples.get(index, e).common();
+    common.increase_time(Duration::from_secs_f32(1. / 60.));
+    let _scope = debug::scope(&format!("{name} frame {}", common.frame));
+
+    examples.render(index, e, target, |ex, e, target| ex.render(e, target));
+    examples.get(index, e).common().increase_frame();
+}
+
+// Answer requests until told to quit
+pub fn run(mut e: ExampleData, mut examples: Registry, mut example_index: usize, server: Server) {
+    let (texture, view) = target(&e);
+
+    examples.set_active(&[example_index], &e);
+
+    while let Some(pending) = server.next() {
+        let result = match pending.request.clone() {
+            Request::Example { name } => examples
+                .find(&name)
+                .ok_or_else(|| format!("no example \"{name}\""))
+                .map(|index| {
+                    example_index = index;
+                    examples.set_active(&[index], &e);
+                    Value::Null
+                }),
+            Request::Set { name, value } => examples
+                .set_param(example_index, &name, &remote::value_text(&value), &e)
+                .map(|param| json!({ "param": param })),
+            Request::Step { frames } => {
+                for _ in 0..frames {
+                    render_frame(&mut examples, example_index, &e, &view);
+                }
+                Ok(Value::Null)
+            }
+            Request::Frame => {
+                render_frame(&mut examples, example_index, &e, &view);
+                let bytes = util::read_texture(&e.device, &e.queue, &texture);
+                remote::frame(texture.size(), texture.format(), &bytes)
+            }
+            Request::FrameLog => Ok(remote::frame_log()),
+            // Time only moves for `step` and `frame`, i.e. it's always paused
+            Request::Status => Ok(remote::status(
+                &mut examples,
+                &[example_index],
+                example_index,
+                &e,
+                true,
+            )),
+            Request::Quit => {
+                pending.reply(Ok(Value::Null));
+                break;
+            }
+            // Cursor, click, scroll
+            request => {
+                if let Some((mouse, events)) = request.input(e.mouse, e.viewport, e.scale_factor())
+                {
+                    e.mouse = mouse;
+                    let ex = examples.get(example_index, &e);
+                    for event in &events {
+                        ex.handle_input(event);
+                    }
+                }
+                Ok(Value::Null)
+            }
+        };
+        pending.reply(result);
+    }
+
+    examples.set_active(&[], &e);
+}
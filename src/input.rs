@@ -0,0 +1,263 @@
+/*
+Input events handed to examples, see `Example::handle_input`.
+
+These don't depend on winit, such that they can be made up when there is no window
+(recording, replay, headless runs). The runner converts winit's events into these.
+
+Positions are given in every coordinate space an example might want:
+    - window: Physical pixels, origin top left. This is what winit gives us, and what the
+        fragment shader's `@builtin(position)` uses (for a full window render target).
+    - logical: Window divided by the DPI scale factor.
+    - clip: -1..1, origin in the middle, y up. What vertex shaders output.
+    - uv: 0..1, origin top left. Like texture coordinates.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub window: [f32; 2],
+    pub logical: [f32; 2],
+    pub clip: [f32; 2],
+    pub uv: [f32; 2],
+}
+
+impl Position {
+    // From physical pixels, given the window's physical size
+    pub fn new(window: [f32; 2], size: [f32; 2], scale_factor: f32) -> Self {
+        // A minimized window may have a zero size
+        let uv = [window[0] / size[0].max(1.), window[1] / size[1].max(1.)];
+
+        Self {
+            window,
+            logical: [window[0] / scale_factor, window[1] / scale_factor],
+            clip: [uv[0] * 2. - 1., uv[1] * -2. + 1.],
+            uv,
+        }
+    }
+
+    // From clip space, e.g. when there is no real cursor
+    pub fn from_clip(clip: [f32; 2], size: [f32; 2], scale_factor: f32) -> Self {
+        let uv = [(clip[0] + 1.) / 2., (1. - clip[1]) / 2.];
+        Self::new([uv[0] * size[0], uv[1] * size[1]], size, scale_factor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u16),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    // Windows key, command key
+    pub logo: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollDelta {
+    // Mouse wheels, [horizontal, vertical]. Up is positive.
+    Lines([f32; 2]),
+    // Touchpads, in physical pixels.
+    // The runner adds up their many small deltas to about a line's worth, see `PixelScroll`.
+    Pixels([f32; 2]),
+}
+
+// About how far a touchpad scrolls for a mouse wheel's line, in physical pixels
+pub const PIXELS_PER_LINE: f32 = 20.;
+
+// Adds up touchpad deltas (dozens per gesture, a few pixels each),
+// giving the sum once it's at least a line's worth in either direction
+#[derive(Debug, Default)]
+pub struct PixelScroll([f32; 2]);
+
+impl PixelScroll {
+    pub fn add(&mut self, [x, y]: [f32; 2]) -> Option<[f32; 2]> {
+        self.0 = [self.0[0] + x, self.0[1] + y];
+        if self.0.iter().any(|v| v.abs() >= PIXELS_PER_LINE) {
+            Some(std::mem::take(&mut self.0))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    CursorMoved {
+        position: Position,
+    },
+    Button {
+        button: MouseButton,
+        pressed: bool,
+        position: Position,
+        modifiers: Modifiers,
+    },
+    Scroll {
+        delta: ScrollDelta,
+        position: Position,
+        modifiers: Modifiers,
+    },
+    ModifiersChanged(Modifiers),
+}
+
+impl InputEvent {
+    // Where the event happened, if it has a position
+    pub fn position(&self) -> Option<Position> {
+        match *self {
+            InputEvent::CursorMoved { position }
+            | InputEvent::Button { position, .. }
+            | InputEvent::Scroll { position, .. } => Some(position),
+            InputEvent::ModifiersChanged(_) => None,
+        }
+    }
+
+    // The same event with another position, e.g. relative to a split screen cell
+    pub fn with_position(mut self, new: Position) -> Self {
+        match &mut self {
+            InputEvent::CursorMoved { position }
+            | InputEvent::Button { position, .. }
+            | InputEvent::Scroll { position, .. } => *position = new,
+            InputEvent::ModifiersChanged(_) => {}
+        }
+        self
+    }
+
+    // For scroll events, whether it's a step up or down.
+    // Line deltas need at least half a line, pixel deltas a line's worth.
+    pub fn scroll_up(&self) -> Option<bool> {
+        let vertical = match self {
+            InputEvent::Scroll {
+                delta: ScrollDelta::Lines([_, vertical]),
+                ..
+            } if vertical.abs() > 0.5 => *vertical,
+            InputEvent::Scroll {
+                delta: ScrollDelta::Pixels([_, vertical]),
+                ..
+            } if vertical.abs() >= PIXELS_PER_LINE => *vertical,
+            _ => return None,
+        };
+        Some(vertical > 0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: [f32; 2] = [800., 600.];
+
+    #[test]
+    fn corners() {
+        let top_left = Position::new([0., 0.], SIZE, 1.);
+        assert_eq!(top_left.clip, [-1., 1.]);
+        assert_eq!(top_left.uv, [0., 0.]);
+
+        let bottom_right = Position::new(SIZE, SIZE, 1.);
+        assert_eq!(bottom_right.clip, [1., -1.]);
+        assert_eq!(bottom_right.uv, [1., 1.]);
+    }
+
+    #[test]
+    fn center() {
+        let center = Position::new([400., 300.], SIZE, 1.);
+        assert_eq!(center.clip, [0., 0.]);
+        assert_eq!(center.uv, [0.5, 0.5]);
+    }
+
+    #[test]
+    fn clip_y_is_up() {
+        let upper = Position::new([400., 100.], SIZE, 1.);
+        let lower = Position::new([400., 500.], SIZE, 1.);
+        assert!(upper.clip[1] > 0.);
+        assert!(lower.clip[1] < 0.);
+        assert!(upper.uv[1] < lower.uv[1]);
+    }
+
+    #[test]
+    fn scale_factor_only_affects_logical() {
+        let one = Position::new([200., 150.], SIZE, 1.);
+        let two = Position::new([200., 150.], SIZE, 2.);
+
+        assert_eq!(one.logical, [200., 150.]);
+        assert_eq!(two.logical, [100., 75.]);
+        assert_eq!(two.window, one.window);
+        assert_eq!(two.clip, one.clip);
+        assert_eq!(two.uv, one.uv);
+    }
+
+    #[test]
+    fn fractional_scale_factor() {
+        let p = Position::new([300., 150.], SIZE, 1.5);
+        assert_eq!(p.logical, [200., 100.]);
+    }
+
+    #[test]
+    fn from_clip_roundtrip() {
+        for clip in [[0., 0.], [-1., 1.], [1., -1.], [0.25, -0.5]] {
+            let p = Position::from_clip(clip, SIZE, 2.);
+            assert_eq!(p.clip, clip);
+            assert_eq!(Position::new(p.window, SIZE, 2.), p);
+        }
+        assert_eq!(
+            Position::from_clip([0.5, 0.5], SIZE, 2.).window,
+            [600., 150.]
+        );
+    }
+
+    #[test]
+    fn zero_size_window() {
+        let p = Position::new([0., 0.], [0., 0.], 1.);
+        assert!(p.clip.iter().chain(p.uv.iter()).all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn scroll_steps() {
+        let scroll = |delta| InputEvent::Scroll {
+            delta,
+            position: Position::new([0., 0.], SIZE, 1.),
+            modifiers: Modifiers::default(),
+        };
+
+        assert_eq!(scroll(ScrollDelta::Lines([0., 1.])).scroll_up(), Some(true));
+        assert_eq!(
+            scroll(ScrollDelta::Lines([0., -1.])).scroll_up(),
+            Some(false)
+        );
+        assert_eq!(scroll(ScrollDelta::Lines([0., 0.2])).scroll_up(), None);
+        assert_eq!(scroll(ScrollDelta::Lines([3., 0.])).scroll_up(), None);
+        assert_eq!(
+            scroll(ScrollDelta::Pixels([0., 20.])).scroll_up(),
+            Some(true)
+        );
+        assert_eq!(
+            scroll(ScrollDelta::Pixels([0., -25.])).scroll_up(),
+            Some(false)
+        );
+        assert_eq!(scroll(ScrollDelta::Pixels([0., 2.])).scroll_up(), None);
+        assert_eq!(
+            InputEvent::ModifiersChanged(Modifiers::default()).scroll_up(),
+            None
+        );
+    }
+
+    #[test]
+    fn touchpad_steps() {
+        // A gesture of 3 pixel deltas is a step per line's worth, not one per delta
+        let mut pixels = PixelScroll::default();
+        let deltas: Vec<_> = (0..30).filter_map(|_| pixels.add([0., 3.])).collect();
+        assert_eq!(deltas.len(), 30 * 3 / PIXELS_PER_LINE as usize);
+        assert!(deltas.iter().all(|&[_, y]| y >= PIXELS_PER_LINE));
+
+        // Turning around cancels out what was left
+        let mut pixels = PixelScroll::default();
+        assert_eq!(pixels.add([0., 15.]), None);
+        assert_eq!(pixels.add([0., -15.]), None);
+        assert_eq!(pixels.add([0., -15.]), None);
+        assert_eq!(pixels.add([0., -6.]), Some([0., -21.]));
+    }
+}
@@ -0,0 +1,460 @@
+/*
+Key bindings.
+
+Actions are declared with default keys, either by the runner (the "global" namespace)
+or by an example (its namespace is its label, e.g. "ex01"). Key presses are looked up here
+instead of the runner matching on keys and then also forwarding them to the example.
+
+A key chord may only be bound once among the global actions and the active example's actions.
+Clashes are reported when registering. If one happens anyway, the example's action wins.
+
+Defaults can be overridden in a config file, one binding per line:
+
+    # namespace.action = keys
+    global.next-example = N, Right
+    ex01.move-up = I
+    ex07.save = Ctrl+S
+    # Nothing after "=" unbinds
+    ex07.clear =
+
+Key names are winit's `VirtualKeyCode` variants, e.g. `A`, `Key1`, `Space`, `F5`, `LBracket`.
+Modifiers are `Ctrl+`, `Shift+`, `Alt+` and `Logo+`, and must match exactly.
+ */
+use std::{fmt, path::Path};
+
+use serde::{de::value::StrDeserializer, Deserialize};
+use winit::event::VirtualKeyCode;
+
+use crate::input::Modifiers;
+
+pub const GLOBAL: &str = "global";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    pub key: VirtualKeyCode,
+    pub modifiers: Modifiers,
+}
+
+const NO_MODIFIERS: Modifiers = Modifiers {
+    shift: false,
+    ctrl: false,
+    alt: false,
+    logo: false,
+};
+
+// Just the key
+pub const fn key(key: VirtualKeyCode) -> KeyChord {
+    KeyChord {
+        key,
+        modifiers: NO_MODIFIERS,
+    }
+}
+
+// Ctrl + key
+pub const fn ctrl(key: VirtualKeyCode) -> KeyChord {
+    KeyChord {
+        key,
+        modifiers: Modifiers {
+            ctrl: true,
+            ..NO_MODIFIERS
+        },
+    }
+}
+
+// Shift + key
+pub const fn shift(key: VirtualKeyCode) -> KeyChord {
+    KeyChord {
+        key,
+        modifiers: Modifiers {
+            shift: true,
+            ..NO_MODIFIERS
+        },
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Modifiers {
+            shift,
+            ctrl,
+            alt,
+            logo,
+        } = self.modifiers;
+        for (held, name) in [
+            (ctrl, "Ctrl"),
+            (shift, "Shift"),
+            (alt, "Alt"),
+            (logo, "Logo"),
+        ] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+impl std::str::FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or_default();
+
+        let mut modifiers = NO_MODIFIERS;
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "ctrl" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "logo" => modifiers.logo = true,
+                _ => return Err(format!("unknown modifier \"{part}\" in \"{s}\"")),
+            }
+        }
+
+        // The variant names, via winit's serde support
+        let key =
+            VirtualKeyCode::deserialize(StrDeserializer::<serde::de::value::Error>::new(key_name))
+                .map_err(|_| format!("unknown key \"{key_name}\" in \"{s}\""))?;
+
+        Ok(KeyChord { key, modifiers })
+    }
+}
+
+// Something a key press can do
+pub struct Action {
+    // Used to dispatch and in the config file, kebab-case
+    pub name: &'static str,
+    pub description: &'static str,
+    pub keys: &'static [KeyChord],
+}
+
+struct Binding {
+    namespace: String,
+    action: &'static str,
+    description: &'static str,
+    keys: Vec<KeyChord>,
+}
+
+// Two actions which can be active at the same time share a key
+#[derive(Debug)]
+pub struct Conflict {
+    pub chord: KeyChord,
+    pub first: String,
+    pub second: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is bound to both {} and {}",
+            self.chord, self.first, self.second
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Example,
+}
+
+#[derive(Default)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    // Register the actions of a namespace, returning the clashes with what's already registered
+    pub fn register(&mut self, namespace: &str, actions: &[Action]) -> Vec<Conflict> {
+        for action in actions {
+            self.bindings.push(Binding {
+                namespace: namespace.to_string(),
+                action: action.name,
+                description: action.description,
+                keys: action.keys.to_vec(),
+            });
+        }
+        self.find_conflicts(Some(namespace))
+    }
+
+    // Clashes involving the given namespace, or all of them
+    fn find_conflicts(&self, involving: Option<&str>) -> Vec<Conflict> {
+        // Global actions are active together with any example's, examples aren't active together
+        let overlaps = |a: &str, b: &str| a == b || a == GLOBAL || b == GLOBAL;
+
+        let mut conflicts = vec![];
+        for (i, a) in self.bindings.iter().enumerate() {
+            for b in &self.bindings[i + 1..] {
+                if let Some(namespace) = involving {
+                    if a.namespace != namespace && b.namespace != namespace {
+                        continue;
+                    }
+                }
+                if !overlaps(&a.namespace, &b.namespace) {
+                    continue;
+                }
+                for &chord in a.keys.iter().filter(|k| b.keys.contains(k)) {
+                    conflicts.push(Conflict {
+                        chord,
+                        first: format!("{}.{}", a.namespace, a.action),
+                        second: format!("{}.{}", b.namespace, b.action),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    // Every clash, e.g. after overriding
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.find_conflicts(None)
+    }
+
+    // Apply overrides from the text of a config file, see the top of this file.
+    // Returns one message per line which couldn't be used.
+    pub fn apply_overrides(&mut self, config: &str) -> Vec<String> {
+        let mut errors = vec![];
+
+        for (line_number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line_number = line_number + 1;
+
+            let Some((name, keys)) = line.split_once('=') else {
+                errors.push(format!(
+                    "line {line_number}: expected \"namespace.action = keys\""
+                ));
+                continue;
+            };
+            let Some((namespace, action)) = name.trim().split_once('.') else {
+                errors.push(format!("line {line_number}: expected \"namespace.action\""));
+                continue;
+            };
+
+            let keys: Result<Vec<KeyChord>, String> = keys
+                .split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(str::parse)
+                .collect();
+            let keys = match keys {
+                Ok(keys) => keys,
+                Err(err) => {
+                    errors.push(format!("line {line_number}: {err}"));
+                    continue;
+                }
+            };
+
+            match self
+                .bindings
+                .iter_mut()
+                .find(|b| b.namespace == namespace && b.action == action)
+            {
+                Some(binding) => binding.keys = keys,
+                None => errors.push(format!(
+                    "line {line_number}: no action \"{action}\" in \"{namespace}\""
+                )),
+            }
+        }
+
+        errors
+    }
+
+    // Read overrides from a file if it exists, printing problems
+    pub fn load_overrides(&mut self, path: &Path) {
+        let Ok(config) = std::fs::read_to_string(path) else {
+            return;
+        };
+        println!("Key bindings from {path:?}");
+
+        for error in self.apply_overrides(&config) {
+            println!("{path:?} {error}");
+        }
+        for conflict in self.conflicts() {
+            println!("Key binding conflict: {conflict}");
+        }
+    }
+
+    // Which action a key press is for, if any.
+    // The example's actions come first, such that the runner can't steal its keys.
+    pub fn lookup(&self, namespace: &str, chord: KeyChord) -> Option<(Scope, &'static str)> {
+        let find = |namespace: &str| {
+            self.bindings
+                .iter()
+                .find(|b| b.namespace == namespace && b.keys.contains(&chord))
+                .map(|b| b.action)
+        };
+
+        find(namespace)
+            .map(|action| (Scope::Example, action))
+            .or_else(|| find(GLOBAL).map(|action| (Scope::Global, action)))
+    }
+
+    // The effective bindings for the global and the given namespace
+    pub fn help(&self, namespace: &str) -> String {
+        let mut help = String::new();
+
+        for ns in [GLOBAL, namespace] {
+            help += &format!("{ns}:\n");
+            for binding in self.bindings.iter().filter(|b| b.namespace == ns) {
+                let keys = match binding.keys.as_slice() {
+                    [] => "(unbound)".to_string(),
+                    keys => keys
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                };
+                help += &format!(
+                    "    {:<24} {:<20} {}\n",
+                    binding.action, keys, binding.description
+                );
+            }
+        }
+
+        help
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLOBALS: &[Action] = &[
+        Action {
+            name: "next",
+            description: "",
+            keys: &[key(VirtualKeyCode::N)],
+        },
+        Action {
+            name: "fill",
+            description: "",
+            keys: &[key(VirtualKeyCode::W)],
+        },
+    ];
+
+    const UP_ON_W: &[Action] = &[Action {
+        name: "up",
+        description: "",
+        keys: &[key(VirtualKeyCode::W)],
+    }];
+
+    const CLEAR_ON_SPACE: &[Action] = &[Action {
+        name: "clear",
+        description: "",
+        keys: &[key(VirtualKeyCode::Space)],
+    }];
+
+    const A_TWICE: &[Action] = &[
+        Action {
+            name: "a",
+            description: "",
+            keys: &[key(VirtualKeyCode::A)],
+        },
+        Action {
+            name: "b",
+            description: "",
+            keys: &[key(VirtualKeyCode::B), key(VirtualKeyCode::A)],
+        },
+    ];
+
+    fn keymap() -> Keymap {
+        let mut keymap = Keymap::default();
+        assert!(keymap.register(GLOBAL, GLOBALS).is_empty());
+        keymap
+    }
+
+    #[test]
+    fn parse_chords() {
+        assert_eq!("A".parse(), Ok(key(VirtualKeyCode::A)));
+        assert_eq!("Key1".parse(), Ok(key(VirtualKeyCode::Key1)));
+        assert_eq!("ctrl+Z".parse(), Ok(ctrl(VirtualKeyCode::Z)));
+        assert_eq!(" Ctrl + Z ".parse(), Ok(ctrl(VirtualKeyCode::Z)));
+        assert!("Hyper+Z".parse::<KeyChord>().is_err());
+        assert!("NotAKey".parse::<KeyChord>().is_err());
+        assert!("".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        for chord in [key(VirtualKeyCode::LBracket), ctrl(VirtualKeyCode::F5)] {
+            assert_eq!(chord.to_string().parse(), Ok(chord));
+        }
+        assert_eq!(ctrl(VirtualKeyCode::S).to_string(), "Ctrl+S");
+    }
+
+    #[test]
+    fn example_clashing_with_global() {
+        let mut keymap = keymap();
+        let conflicts = keymap.register("ex01", UP_ON_W);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].chord, key(VirtualKeyCode::W));
+
+        // The example wins
+        assert_eq!(
+            keymap.lookup("ex01", key(VirtualKeyCode::W)),
+            Some((Scope::Example, "up"))
+        );
+        assert_eq!(
+            keymap.lookup("ex02", key(VirtualKeyCode::W)),
+            Some((Scope::Global, "fill"))
+        );
+    }
+
+    #[test]
+    fn examples_may_share_keys() {
+        let mut keymap = keymap();
+        assert!(keymap.register("ex01", CLEAR_ON_SPACE).is_empty());
+        assert!(keymap.register("ex02", CLEAR_ON_SPACE).is_empty());
+    }
+
+    #[test]
+    fn clash_within_example() {
+        let mut keymap = keymap();
+        let conflicts = keymap.register("ex01", A_TWICE);
+        assert_eq!(conflicts.len(), 1);
+    }
+
+    #[test]
+    fn modifiers_must_match() {
+        let keymap = keymap();
+        assert_eq!(keymap.lookup("ex01", ctrl(VirtualKeyCode::N)), None);
+    }
+
+    #[test]
+    fn overrides() {
+        let mut keymap = keymap();
+        let errors = keymap.apply_overrides(
+            "# comment\n\
+             global.next = M, Ctrl+N\n\
+             global.fill =\n\
+             global.nope = A\n\
+             global.next = NotAKey\n\
+             garbage\n",
+        );
+        assert_eq!(errors.len(), 3, "{errors:?}");
+
+        assert_eq!(
+            keymap.lookup("ex01", key(VirtualKeyCode::M)),
+            Some((Scope::Global, "next"))
+        );
+        assert_eq!(
+            keymap.lookup("ex01", ctrl(VirtualKeyCode::N)),
+            Some((Scope::Global, "next"))
+        );
+        assert_eq!(keymap.lookup("ex01", key(VirtualKeyCode::N)), None);
+        assert_eq!(keymap.lookup("ex01", key(VirtualKeyCode::W)), None);
+        assert!(keymap.help("ex01").contains("(unbound)"));
+    }
+
+    #[test]
+    fn overrides_can_introduce_conflicts() {
+        let mut keymap = keymap();
+        assert!(keymap.apply_overrides("global.fill = N").is_empty());
+        assert_eq!(keymap.conflicts().len(), 1);
+    }
+}
@@ -1,10 +1,18 @@
-use std::{path::Path, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use input::{InputEvent, Modifiers, MouseButton, PixelScroll, Position, ScrollDelta};
+use keymap::{key, shift, Action, KeyChord, Keymap, Scope};
 use notify::{PollWatcher, Watcher};
+use registry::{Factory, Registry, Unload};
+use serde_json::{json, Value};
 use util::ExampleCommonState;
 use wgpu::{
-    Backends, Device, Extent3d, Features, Limits, PolygonMode, Queue, Surface,
-    SurfaceConfiguration, TextureFormat, TextureFormatFeatureFlags,
+    Adapter, AdapterInfo, Backends, Device, Extent3d, Features, Limits, PolygonMode, Queue,
+    Surface, SurfaceConfiguration, Texture, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor,
 };
 use winit::{
     event::{
@@ -14,7 +22,27 @@ use winit::{
     window::Window,
 };
 
+mod bench;
+mod console;
+mod debug;
+mod depth_stencil;
+mod fault;
+mod headless;
+mod input;
+mod keymap;
+mod params;
+mod record;
+mod registry;
+mod remote;
+#[cfg(test)]
+mod shader_check;
+#[cfg(test)]
+mod shader_test;
+mod split;
+mod trace;
+mod translate;
 pub mod util;
+mod xray;
 
 mod example_01;
 mod example_02;
@@ -24,13 +52,28 @@ mod example_05;
 mod example_06;
 mod example_07;
 mod example_08;
+mod example_09;
+mod example_10;
+mod example_11;
+mod example_12;
+mod example_13;
 
 pub trait Example {
-    // Keyboard
-    fn handle_key(&mut self, _key: VirtualKeyCode) {}
+    // A key bound to one of the example's actions was pressed, by the action's name.
+    // The actions are declared when registering the example, see `registry.rs` and `keymap.rs`.
+    fn handle_action(&mut self, _action: &str) {}
+
+    // The example is now on screen (first time, or switched to)
+    fn on_enter(&mut self, _data: &ExampleData) {}
+
+    // The example is no longer on screen.
+    // A good time to reset input state, e.g. a held mouse button won't be released here.
+    fn on_exit(&mut self) {}
 
     // Render!
-    fn render(&mut self, data: &ExampleData);
+    // The target is typically the swapchain, which the runner acquires and presents.
+    // It might also be an offscreen texture with the same size and format, e.g. for the x-ray lens.
+    fn render(&mut self, data: &ExampleData, target: &TextureView);
 
     // Mouse scroll registered, either up or down
     fn handle_scroll(&mut self, _scroll_up: bool) {}
@@ -39,6 +82,26 @@ pub trait Example {
     // If `!pressed` that means released.
     fn handle_click(&mut self, _position: [f32; 2], _pressed: bool) {}
 
+    // Every mouse event: Cursor motion, all buttons, scroll, modifiers.
+    // By default left clicks go to `handle_click` and scroll steps to `handle_scroll`,
+    // override to get the rest.
+    fn handle_input(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Button {
+                button: MouseButton::Left,
+                pressed,
+                position,
+                ..
+            } => self.handle_click(position.clip, pressed),
+            InputEvent::Scroll { .. } => {
+                if let Some(scroll_up) = event.scroll_up() {
+                    self.handle_scroll(scroll_up);
+                }
+            }
+            _ => {}
+        }
+    }
+
     // Used via main runner to:
     //  - increase example elapsed time
     //  - increase example frame #
@@ -47,15 +110,22 @@ pub trait Example {
 }
 
 pub struct ExampleData {
-    window: Window,
+    // None when headless, see `headless.rs`
+    window: Option<Window>,
     device: Device,
     queue: Queue,
-    surface: Surface,
+    surface: Option<Surface>,
     swapchain_format: TextureFormat,
+    // Has `COPY_SRC` if the swapchain can be copied from, e.g. for recording (see `record.rs`)
+    surface_usage: TextureUsages,
 
-    max_sample_count: u32,
+    // MSAA sample counts supported by the swapchain format, ascending.
+    // Always contains 1.
+    sample_counts: Vec<u32>,
 
+    // Window pixels, relative to the target (see `viewport`)
     mouse: [f32; 2],
+    // Size of the target examples render into, see `extent_3d`
     viewport: [f32; 2],
 }
 
@@ -63,15 +133,16 @@ fn configure_surface(
     surface: &mut Surface,
     device: &Device,
     format: TextureFormat,
+    usage: TextureUsages,
     window: &Window,
 ) -> [f32; 2] {
     let size = window.inner_size();
     let viewport = [size.width as f32, size.height as f32];
 
     surface.configure(
-        &device,
+        device,
         &SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format,
             width: size.width,
             height: size.height,
@@ -85,18 +156,30 @@ fn configure_surface(
 
 impl ExampleData {
     fn configure_surface(&mut self) {
-        self.viewport = configure_surface(
-            &mut self.surface,
-            &self.device,
-            self.swapchain_format,
-            &self.window,
-        );
+        if let (Some(surface), Some(window)) = (self.surface.as_mut(), self.window.as_ref()) {
+            self.viewport = configure_surface(
+                surface,
+                &self.device,
+                self.swapchain_format,
+                self.surface_usage,
+                window,
+            );
+        }
     }
 
+    // 1 when headless
+    fn scale_factor(&self) -> f32 {
+        self.window
+            .as_ref()
+            .map_or(1., |window| window.scale_factor() as f32)
+    }
+
+    // The size of the target examples render into.
+    // Usually the window, but e.g. a cell when split screen.
     fn extent_3d(&self) -> Extent3d {
         Extent3d {
-            width: self.window.inner_size().width,
-            height: self.window.inner_size().height,
+            width: self.viewport[0] as u32,
+            height: self.viewport[1] as u32,
             depth_or_array_layers: 1,
         }
     }
@@ -105,30 +188,266 @@ impl ExampleData {
         [self.mouse[0] as u32, self.mouse[1] as u32]
     }
 
+    // The mouse in every coordinate space
+    fn mouse_position(&self) -> Position {
+        Position::new(self.mouse, self.viewport, self.scale_factor())
+    }
+
     fn mouse_clip_space(&self) -> [f32; 2] {
         // Set mouse position to the -1..1 range using wgpu's coordinate system,
         // i.e. origin middle of screen, top right is (1., 1.)
 
-        let x = (self.mouse[0] as f32 / self.window.inner_size().width as f32)
-            .max(0.0)
-            .min(1.0)
-            * 2.
-            - 1.;
-        let y = (self.mouse[1] as f32 / self.window.inner_size().height as f32)
-            .max(0.0)
-            .min(1.0)
-            * -2.
-            + 1.0;
+        let x = (self.mouse[0] / self.viewport[0]).clamp(0.0, 1.0) * 2. - 1.;
+        let y = (self.mouse[1] / self.viewport[1]).clamp(0.0, 1.0) * -2. + 1.0;
         [x, y]
     }
 }
 
-fn setup() -> (EventLoop<()>, ExampleData) {
+// The runner's actions, active for every example
+const GLOBAL_ACTIONS: &[Action] = &[
+    Action {
+        name: "polygon-mode-fill",
+        description: "Polygon mode towards fill (point -> line -> fill)",
+        keys: &[key(VirtualKeyCode::W)],
+    },
+    Action {
+        name: "polygon-mode-point",
+        description: "Polygon mode towards point (fill -> line -> point)",
+        keys: &[key(VirtualKeyCode::S)],
+    },
+    Action {
+        name: "previous-example",
+        description: "Previous example",
+        keys: &[key(VirtualKeyCode::P)],
+    },
+    Action {
+        name: "next-example",
+        description: "Next example",
+        keys: &[key(VirtualKeyCode::N)],
+    },
+    Action {
+        name: "lens",
+        description: "Toggle the x-ray lens",
+        keys: &[key(VirtualKeyCode::X)],
+    },
+    Action {
+        name: "lens-shape",
+        description: "X-ray lens: Rectangle or circle",
+        keys: &[key(VirtualKeyCode::C)],
+    },
+    Action {
+        name: "lens-mode",
+        description: "X-ray lens: Cycle lines, points, debug view",
+        keys: &[key(VirtualKeyCode::V)],
+    },
+    Action {
+        name: "split-screen",
+        description: "Toggle showing several examples at once",
+        keys: &[key(VirtualKeyCode::F2)],
+    },
+    Action {
+        name: "screenshot",
+        description: "Save the next frame as a PNG",
+        keys: &[key(VirtualKeyCode::F12)],
+    },
+    Action {
+        name: "record",
+        description: "Start/stop recording a GIF (see `record` in the console for more)",
+        keys: &[key(VirtualKeyCode::F10)],
+    },
+    Action {
+        name: "print-frame",
+        description: "Print the debug groups and markers of the next frame",
+        keys: &[key(VirtualKeyCode::F3)],
+    },
+    Action {
+        name: "reset-example",
+        description: "Construct the example again, e.g. after it faulted",
+        keys: &[key(VirtualKeyCode::F4)],
+    },
+    Action {
+        name: "next-param",
+        description: "Select the example's next parameter",
+        keys: &[key(VirtualKeyCode::Tab)],
+    },
+    Action {
+        name: "previous-param",
+        description: "Select the example's previous parameter",
+        keys: &[shift(VirtualKeyCode::Tab)],
+    },
+    Action {
+        name: "increase-param",
+        description: "Increase the selected parameter (toggle, next option)",
+        keys: &[key(VirtualKeyCode::Equals)],
+    },
+    Action {
+        name: "decrease-param",
+        description: "Decrease the selected parameter (toggle, previous option)",
+        keys: &[key(VirtualKeyCode::Minus)],
+    },
+    Action {
+        name: "print-params",
+        description: "Print the example's parameters",
+        keys: &[key(VirtualKeyCode::F6)],
+    },
+    Action {
+        name: "shader-dump",
+        description: "Dump the example's shader as naga IR, SPIR-V, GLSL, HLSL and MSL (again on every hot reload)",
+        keys: &[key(VirtualKeyCode::F7)],
+    },
+    Action {
+        name: "pause",
+        description: "Pause/resume the example's time",
+        keys: &[key(VirtualKeyCode::F8)],
+    },
+    Action {
+        name: "help",
+        description: "Print the key bindings",
+        keys: &[key(VirtualKeyCode::F1)],
+    },
+    Action {
+        name: "quit",
+        description: "Quit",
+        keys: &[key(VirtualKeyCode::Escape)],
+    },
+];
+
+// Key binding overrides, see `keymap.rs`
+const KEYBINDINGS_PATH: &str = "keybindings.cfg";
+// Parameter values, see `params.rs`
+const PARAMS_PATH: &str = "params.cfg";
+
+// E.g. `screenshot-ex07-<millis>.png`
+fn screenshot_path(label: &str) -> PathBuf {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    PathBuf::from(format!(
+        "screenshot-{label}-{}.png",
+        since_epoch.as_millis()
+    ))
+}
+
+// Read the frame just rendered back from the swapchain texture (like `record.rs`), see `util::read_texture`.
+// Not rendering it again into a texture of our own, which would step the examples a second time.
+fn read_frame(e: &ExampleData, texture: &Texture) -> Result<Vec<u8>, String> {
+    if !texture.usage().contains(TextureUsages::COPY_SRC) {
+        return Err("the surface can't be copied from (no COPY_SRC)".to_string());
+    }
+    Ok(util::read_texture(&e.device, &e.queue, texture))
+}
+
+// Save the frame just rendered as a PNG
+fn screenshot(e: &ExampleData, texture: &Texture, path: &Path) -> Result<(), String> {
+    let bytes = read_frame(e, texture)?;
+    util::save_png(path, texture.size(), texture.format(), &bytes)
+}
+
+// Write what naga makes of the example's shader to `shader-dump/<example>/`, see `translate.rs`
+fn dump_shader(common: &ExampleCommonState, device: &Device) {
+    match translate::dump(common.label, common.shader_source, device.features()) {
+        Ok(message) => println!("{message}"),
+        Err(err) => println!("Shader dump: {err}"),
+    }
+}
+
+// Recompile every shader, e.g. when a wgsl file changed, and dump the `dumping` examples' shaders again
+fn recompile_shaders(
+    examples: &mut Registry,
+    lens: Option<&mut xray::XrayLens>,
+    split: Option<&mut split::SplitScreen>,
+    dumping: &[usize],
+    e: &ExampleData,
+) {
+    let device = &e.device;
+    examples.recompile_shaders(device);
+    for &i in dumping {
+        dump_shader(examples.get(i, e).common(), device);
+    }
+
+    let lens = lens.map(|lens| lens.common());
+    let split = split.map(|split| split.common());
+    for common in lens.into_iter().chain(split) {
+        let result = fault::capture(device, || {
+            common.recreate_shader(device);
+            common.dirty = true;
+        });
+        if let Err(err) = result {
+            println!("{} shader error: {err}", common.label);
+        }
+    }
+}
+
+// MSAA sample counts the format supports, ascending.
+// Not every count is necessarily supported, e.g. 16 is often missing.
+fn sample_counts(adapter: &Adapter, format: TextureFormat) -> Vec<u32> {
+    let tff = adapter.get_texture_format_features(format).flags;
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&count| tff.sample_count_supported(count))
+        .collect()
+}
+
+// Used for GPU timings when available, e.g. in example 09
+const OPTIONAL_FEATURES: Features = Features::TIMESTAMP_QUERY;
+// What the examples use, shaders are checked against these too (see `shader_check.rs`)
+const WANTED_FEATURES: Features = Features::POLYGON_MODE_LINE
+    .union(Features::POLYGON_MODE_POINT)
+    .union(Features::PUSH_CONSTANTS)
+    .union(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    .union(Features::CLEAR_TEXTURE)
+    .union(Features::TEXTURE_BINDING_ARRAY)
+    .union(Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING);
+
+// The features the examples want, as far as the adapter has them.
+// Examples which need a missing one fault when they use it (see `fault.rs`), the others still run.
+// With `trace`, wgpu writes an API trace into that directory.
+fn request_device(adapter: &Adapter, trace: Option<&Path>) -> (Device, Queue) {
+    let missing = WANTED_FEATURES - adapter.features();
+    if !missing.is_empty() {
+        println!("The adapter is missing {missing:?}, examples using them will fault");
+    }
+    let adapter_limits = adapter.limits();
+
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("device-descr-setup"),
+            features: (OPTIONAL_FEATURES | WANTED_FEATURES) & adapter.features(),
+            limits: Limits {
+                // https://docs.rs/wgpu/latest/wgpu/struct.Limits.html#structfield.max_push_constant_size
+                // Seems this amount should be supported by all backends
+                max_push_constant_size: 128.min(adapter_limits.max_push_constant_size),
+                max_sampled_textures_per_shader_stage:
+                    1024.min(adapter_limits.max_sampled_textures_per_shader_stage),
+                ..Default::default()
+            },
+        },
+        trace,
+    ))
+    .unwrap();
+
+    dbg!(device.features());
+
+    // Examples render inside error scopes (see `fault.rs`), anything else just gets logged
+    // instead of the default panic
+    device.on_uncaptured_error(Box::new(|error| println!("Uncaptured wgpu error: {error}")));
+    dbg!(device.limits());
+
+    (device, queue)
+}
+
+// Vulkan, unless e.g. `WGPU_BACKEND=gl`
+fn backends() -> Backends {
+    wgpu::util::backend_bits_from_env().unwrap_or(Backends::VULKAN)
+}
+
+// With `trace`, wgpu writes an API trace into that directory
+fn setup(trace: Option<&Path>) -> (EventLoop<()>, ExampleData) {
     let event_loop = winit::event_loop::EventLoop::new();
     let window = winit::window::Window::new(&event_loop).unwrap();
 
     let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-        backends: Backends::VULKAN,
+        backends: backends(),
         ..Default::default()
     });
     let mut surface = unsafe { instance.create_surface(&window).unwrap() };
@@ -148,6 +467,8 @@ fn setup() -> (EventLoop<()>, ExampleData) {
     //  - usages (e.g. COPY_SRC, ..)
     dbg!(&swapchain_capabilities);
 
+    let surface_usage = TextureUsages::RENDER_ATTACHMENT
+        | (swapchain_capabilities.usages & TextureUsages::COPY_SRC);
     let swapchain_formats = swapchain_capabilities.formats;
     let swapchain_format = swapchain_formats[0];
 
@@ -159,81 +480,413 @@ fn setup() -> (EventLoop<()>, ExampleData) {
         dbg!(f, adapter.get_texture_format_features(f));
     }
 
-    let tff = adapter.get_texture_format_features(swapchain_format).flags;
-
-    // Let's do this the way the example does
-    let max_sample_count = if tff.contains(TextureFormatFeatureFlags::MULTISAMPLE_X16) {
-        16
-    } else if tff.contains(TextureFormatFeatureFlags::MULTISAMPLE_X8) {
-        8
-    } else if tff.contains(TextureFormatFeatureFlags::MULTISAMPLE_X4) {
-        4
-    } else if tff.contains(TextureFormatFeatureFlags::MULTISAMPLE_X2) {
-        2
-    } else {
-        1
-    };
+    let sample_counts = sample_counts(&adapter, swapchain_format);
+    dbg!(&sample_counts);
 
-    let (device, queue) = pollster::block_on(adapter.request_device(
-        &wgpu::DeviceDescriptor {
-            label: Some("device-descr-setup"),
-            features: Features::default()
-                | Features::POLYGON_MODE_LINE
-                | Features::POLYGON_MODE_POINT
-                | Features::PUSH_CONSTANTS
-                | Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
-                | Features::CLEAR_TEXTURE
-                | Features::TEXTURE_BINDING_ARRAY
-                | Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
-            limits: Limits {
-                // https://docs.rs/wgpu/latest/wgpu/struct.Limits.html#structfield.max_push_constant_size
-                // Seems this amount should be supported by all backends
-                max_push_constant_size: 128,
-                max_sampled_textures_per_shader_stage: 1024,
-                ..Default::default()
-            },
-        },
-        Some(&Path::new("trace.txt")),
-    ))
-    .unwrap();
+    let (device, queue) = request_device(&adapter, trace);
 
-    dbg!(device.features());
-    dbg!(device.limits());
-
-    let viewport = configure_surface(&mut surface, &device, swapchain_format, &window);
+    let viewport = configure_surface(
+        &mut surface,
+        &device,
+        swapchain_format,
+        surface_usage,
+        &window,
+    );
 
     (
         event_loop,
         ExampleData {
-            window,
+            window: Some(window),
             device,
             queue,
-            surface,
+            surface: Some(surface),
             swapchain_format,
+            surface_usage,
             mouse: [0., 0.],
             viewport,
-            max_sample_count,
+            sample_counts,
         },
     )
 }
 
+// No window, examples render into a texture of this size, see `headless.rs`
+fn setup_headless(trace: Option<&Path>, size: [u32; 2]) -> (ExampleData, AdapterInfo) {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: backends(),
+        ..Default::default()
+    });
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()))
+        .expect("no adapter, try another backend, e.g. WGPU_BACKEND=gl");
+    dbg!(adapter.get_info());
+
+    // What a swapchain typically has
+    let swapchain_format = TextureFormat::Bgra8UnormSrgb;
+    let sample_counts = sample_counts(&adapter, swapchain_format);
+    dbg!(&sample_counts);
+
+    let (device, queue) = request_device(&adapter, trace);
+
+    let example_data = ExampleData {
+        window: None,
+        device,
+        queue,
+        surface: None,
+        swapchain_format,
+        // Like the texture examples render into, see `headless.rs`
+        surface_usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        mouse: [0., 0.],
+        viewport: size.map(|v| v as f32),
+        sample_counts,
+    };
+    (example_data, adapter.get_info())
+}
+
+// Wait for what's left of the recording to be written
+fn finish_recording(recorder: &mut Option<record::Recorder>, device: &Device) {
+    if let Some(recorder) = recorder.take() {
+        match recorder.finish(device) {
+            Ok(summary) => println!("{summary}"),
+            Err(err) => println!("Recording failed: {err}"),
+        }
+    }
+}
+
+// `--listen <address>`, see `remote.rs`
+fn listen(address: &str) -> remote::Server {
+    let server = remote::Server::listen(address)
+        .unwrap_or_else(|err| panic!("Could not listen on {address:?}: {err}"));
+    println!("Listening on {address:?}");
+    server
+}
+
+// Every example
+fn registry() -> Registry {
+    Registry::new(vec![
+        Factory {
+            name: "ex01",
+            actions: example_01::ACTIONS,
+            params: &[],
+            unload: Unload::Keep,
+            make: |e| Box::new(example_01::Example01::new(e)),
+        },
+        Factory {
+            name: "ex02",
+            actions: example_02::ACTIONS,
+            params: example_02::PARAMS,
+            unload: Unload::Keep,
+            make: |e| Box::new(example_02::Example02::new(e)),
+        },
+        Factory {
+            name: "ex03",
+            actions: example_03::ACTIONS,
+            params: example_03::PARAMS,
+            unload: Unload::Keep,
+            make: |e| Box::new(example_03::Example03::new(e)),
+        },
+        Factory {
+            name: "ex04",
+            actions: example_04::ACTIONS,
+            params: &[],
+            unload: Unload::Keep,
+            make: |e| Box::new(example_04::Example04::new(e)),
+        },
+        Factory {
+            name: "ex05",
+            actions: example_05::ACTIONS,
+            params: &[],
+            unload: Unload::Keep,
+            make: |e| Box::new(example_05::Example05::new(e)),
+        },
+        Factory {
+            name: "ex06",
+            actions: &[],
+            params: &[],
+            unload: Unload::Keep,
+            make: |e| Box::new(example_06::Example06::new(e)),
+        },
+        Factory {
+            name: "ex07",
+            actions: example_07::ACTIONS,
+            params: example_07::PARAMS,
+            unload: Unload::Keep,
+            make: |e| Box::new(example_07::Example07::new(e)),
+        },
+        // 256 textures, no need to keep them around
+        Factory {
+            name: "ex08",
+            actions: example_08::ACTIONS,
+            params: &[],
+            unload: Unload::OnExit,
+            make: |e| Box::new(example_08::Example08::new(e)),
+        },
+        Factory {
+            name: "ex09",
+            actions: example_09::ACTIONS,
+            params: &[],
+            unload: Unload::Keep,
+            make: |e| Box::new(example_09::Example09::new(e)),
+        },
+        Factory {
+            name: "ex10",
+            actions: example_10::ACTIONS,
+            params: example_10::PARAMS,
+            unload: Unload::Keep,
+            make: |e| Box::new(example_10::Example10::new(e)),
+        },
+        Factory {
+            name: "ex11",
+            actions: example_11::ACTIONS,
+            params: example_11::PARAMS,
+            unload: Unload::Keep,
+            make: |e| Box::new(example_11::Example11::new(e)),
+        },
+        Factory {
+            name: "ex12",
+            actions: example_12::ACTIONS,
+            params: example_12::PARAMS,
+            unload: Unload::Keep,
+            make: |e| Box::new(example_12::Example12::new(e)),
+        },
+        Factory {
+            name: "ex13",
+            actions: example_13::ACTIONS,
+            params: example_13::PARAMS,
+            unload: Unload::Keep,
+            make: |e| Box::new(example_13::Example13::new(e)),
+        },
+    ])
+}
+
 fn main() {
-    println!("[P]revious example\n[N]ext example");
-    let (event_loop, mut example_data) = setup();
-
-    let mut examples: Vec<Box<dyn Example>> = vec![
-        Box::new(example_01::Example01::new(&example_data)),
-        Box::new(example_02::Example02::new(&example_data)),
-        Box::new(example_03::Example03::new(&example_data)),
-        Box::new(example_04::Example04::new(&example_data)),
-        Box::new(example_05::Example05::new(&example_data)),
-        Box::new(example_06::Example06::new(&example_data)),
-        Box::new(example_07::Example07::new(&example_data)),
-        Box::new(example_08::Example08::new(&example_data)),
-    ];
-
-    let mut example_index = 7;
+    let args: Vec<String> = std::env::args().collect();
+    // The value after a flag, e.g. `--example ex07`
+    let arg = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+
+    // `cargo run -- trace-summary <dir>` summarizes a trace from `--trace`, no window needed
+    if args.get(1).map(String::as_str) == Some("trace-summary") {
+        let Some(dir) = args.get(2) else {
+            println!("Usage: trace-summary <trace directory>");
+            return;
+        };
+        if let Err(err) = trace::summarize_dir(Path::new(dir)) {
+            println!("{err}");
+        }
+        return;
+    }
+
+    // `cargo run -- --trace` writes an API trace to `traces/<timestamp>`
+    let trace_dir = args.iter().any(|arg| arg == "--trace").then(|| {
+        let dir = Path::new("traces").join(util::timestamp());
+        // wgpu doesn't create it
+        std::fs::create_dir_all(&dir).unwrap();
+        println!("Writing API trace to {dir:?}");
+        dir
+    });
+
+    // `cargo run -- --param ex02.radius=0.5 --param ex07.color=cyan` sets parameters for this run
+    let param_args: Vec<String> = args
+        .windows(2)
+        .filter(|pair| pair[0] == "--param")
+        .map(|pair| pair[1].clone())
+        .collect();
+
+    // `cargo run -- --example ex07` (or `--example 7`) starts with example 7
+    let first_example = |examples: &Registry| match arg("--example") {
+        Some(name) => examples.find(name).unwrap_or_else(|| {
+            let names: Vec<_> = (0..examples.len()).map(|i| examples.name(i)).collect();
+            println!("No example \"{name}\", try one of: {}", names.join(", "));
+            std::process::exit(2);
+        }),
+        None => examples.find("ex08").unwrap(),
+    };
+
+    // Without a window, `--size 800x600` is what examples render into
+    let size = || match arg("--size") {
+        Some(size) => size
+            .split_once('x')
+            .and_then(|(w, h)| Some([w.parse().ok()?, h.parse().ok()?]))
+            .unwrap_or_else(|| panic!("--size should be like 800x600, not \"{size}\"")),
+        None => [800, 600],
+    };
+
+    // `cargo run -- bench compare base.json new.json` compares two benchmark results, see `bench.rs`.
+    // Exits with 1 if something got slower by more than `--threshold` percent (10 by default).
+    if args.get(1).map(String::as_str) == Some("bench")
+        && args.get(2).map(String::as_str) == Some("compare")
+    {
+        let (Some(baseline), Some(current)) = (args.get(3), args.get(4)) else {
+            println!("Usage: bench compare <baseline.json> <current.json> [--threshold 10]");
+            return;
+        };
+        let threshold: f64 = arg("--threshold").map_or(10., |t| {
+            t.parse()
+                .unwrap_or_else(|_| panic!("--threshold should be a percentage, not \"{t}\""))
+        });
+        let (baseline, current) = match (
+            bench::Report::load(Path::new(baseline)),
+            bench::Report::load(Path::new(current)),
+        ) {
+            (Ok(baseline), Ok(current)) => (baseline, current),
+            (Err(err), _) | (_, Err(err)) => {
+                println!("Could not load {err}");
+                std::process::exit(2);
+            }
+        };
+        if (&baseline.adapter, baseline.size) != (&current.adapter, current.size) {
+            println!(
+                "Comparing {} at {:?} with {} at {:?}",
+                baseline.adapter, baseline.size, current.adapter, current.size
+            );
+        }
+        let changes = bench::compare(&baseline, &current);
+        print!("{}", bench::format_changes(&changes, threshold));
+        if changes.iter().any(|change| change.regressed(threshold)) {
+            println!("Slower by more than {threshold}%");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // `cargo run --release -- bench` benchmarks every example offscreen and writes the results as JSON.
+    // `--examples ex01,ex07` picks some, `--warmup 60 --frames 300` how many frames, `--out <path>` where to.
+    // Parameters only come from `--param`, like headless.
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let count = |flag: &str, default: u32| {
+            arg(flag).map_or(default, |n| {
+                n.parse()
+                    .unwrap_or_else(|_| panic!("{flag} should be a number, not \"{n}\""))
+            })
+        };
+        let settings = bench::Settings {
+            warmup: count("--warmup", 60),
+            frames: count("--frames", 300).max(1),
+        };
+
+        let (example_data, adapter) = setup_headless(trace_dir.as_deref(), size());
+        let mut examples = registry();
+        examples.load_params(None, &param_args);
+        let indices: Vec<usize> = match arg("--examples") {
+            Some(names) => names
+                .split(',')
+                .map(|name| {
+                    examples
+                        .find(name.trim())
+                        .unwrap_or_else(|| panic!("No example \"{name}\""))
+                })
+                .collect(),
+            None => (0..examples.len()).collect(),
+        };
+
+        let report = bench::run(&example_data, &adapter, &mut examples, &indices, settings);
+        print!("{}", bench::format_report(&report));
+        let path = arg("--out").map_or_else(
+            || PathBuf::from(format!("bench-{}.json", util::timestamp())),
+            PathBuf::from,
+        );
+        match report.save(&path) {
+            Ok(()) => println!("Saved results to {path:?}"),
+            Err(err) => println!("Could not save results: {err}"),
+        }
+        return;
+    }
+
+    // `cargo run -- bench-workgroups` runs the workgroup size benchmark of example 09 and exits.
+    // Without a window, like `bench`.
+    if args.get(1).map(String::as_str) == Some("bench-workgroups") {
+        let (example_data, _) = setup_headless(trace_dir.as_deref(), size());
+        let mut ex = example_09::Example09::new(&example_data);
+        example_09::print_timings(&ex.benchmark(&example_data));
+        return;
+    }
+
+    // `cargo run -- headless --listen /tmp/zoo.sock` runs without a window, driven through the socket.
+    // Parameters only come from `--param`, such that runs are the same on every machine.
+    if args.get(1).map(String::as_str) == Some("headless") {
+        let Some(address) = arg("--listen") else {
+            println!("Usage: headless --listen <socket path, or 127.0.0.1:port> [--size 800x600]");
+            return;
+        };
+
+        let (example_data, _) = setup_headless(trace_dir.as_deref(), size());
+        let mut examples = registry();
+        examples.load_params(None, &param_args);
+        let example_index = first_example(&examples);
+        headless::run(example_data, examples, example_index, listen(address));
+        return;
+    }
+
+    println!("[P]revious example\n[N]ext example\nF1 for all key bindings");
+    let (event_loop, mut example_data) = setup(trace_dir.as_deref());
+
+    let mut examples = registry();
+
+    let mut keymap = Keymap::default();
+    for conflict in keymap.register(keymap::GLOBAL, GLOBAL_ACTIONS) {
+        println!("Key binding conflict: {conflict}");
+    }
+    for factory in examples.factories() {
+        for conflict in keymap.register(factory.name, factory.actions) {
+            println!("Key binding conflict: {conflict}");
+        }
+    }
+    keymap.load_overrides(Path::new(KEYBINDINGS_PATH));
+
+    examples.load_params(Some(Path::new(PARAMS_PATH)), &param_args);
+
+    // `cargo run -- --split 5,6` starts with examples 5 and 6 side by side
+    let split_cells = arg("--split")
+        .map(|list| {
+            list.split(',')
+                .filter_map(|name| examples.find(name.trim()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|cells| !cells.is_empty());
+    let mut split = split_cells
+        .clone()
+        .map(|cells| split::SplitScreen::new(&example_data, cells));
+
+    let mut example_index = first_example(&examples);
+    // Renders the active example through an x-ray lens when enabled
+    let mut lens: Option<xray::XrayLens> = None;
     let mut is_focused = true;
+    let mut modifiers = Modifiers::default();
+    let mut pixel_scroll = PixelScroll::default();
+    // Example time stands still
+    let mut paused = false;
+    // While paused, frames which still advance time (by 1/60 s)
+    let mut steps = 0;
+    // Save the next frame to here
+    let mut screenshot_to: Option<PathBuf> = None;
+    // Start recording with the next frame, see `record.rs`
+    let mut record_start: Option<record::Settings> = None;
+    let mut recorder: Option<record::Recorder> = None;
+    // Examples whose shader is dumped again on hot reload, see `translate.rs`
+    let mut dumping: Vec<usize> = vec![];
+    // Print the debug groups of the next frame, see `debug.rs`
+    let mut print_frame = false;
+    // Construct the focused example again
+    let mut reset = false;
+    let mut quit = false;
+
+    // Commands from stdin, and from `cargo run -- --script demo.txt`
+    let mut console = console::Console::default();
+    console.listen_stdin();
+    if let Some(path) = arg("--script") {
+        match console.load_script(Path::new(path)) {
+            Ok(()) => println!("Running script {path:?}"),
+            Err(err) => println!("Could not read script {path:?}: {err}"),
+        }
+    }
+
+    // Requests from scripts and tests, `cargo run -- --listen /tmp/zoo.sock`, see `remote.rs`
+    let server = arg("--listen").map(|address| listen(address));
+    // Requests answered after the next frame, or after the steps
+    let mut frame_requests: Vec<remote::Pending> = vec![];
+    let mut step_requests: Vec<remote::Pending> = vec![];
 
     let mut last_time = std::time::Instant::now();
     let mut one_second = 1.0f32;
@@ -250,7 +903,164 @@ fn main() {
     println!("Watching {recursive_dir:?} for file changes");
 
     event_loop.run(move |event, _, ctrl_flow| {
-        let ex: &mut dyn Example = examples[example_index].as_mut();
+        // Commands, see `console.rs`
+        while let Some((origin, command)) = console.next() {
+            let mut run = |command: console::Command| -> Result<(), String> {
+                use console::Command;
+                let e = &example_data;
+                match command {
+                    Command::Example(name) => {
+                        example_index = examples
+                            .find(&name)
+                            .ok_or_else(|| format!("no example \"{name}\""))?;
+                        // In split screen, like P/N
+                        if let Some(split) = split.as_mut() {
+                            let cell = split.focused(e.mouse, e.viewport);
+                            split.set_example(cell, example_index);
+                        }
+                    }
+                    // `radius`, or `ex02.radius`
+                    Command::Set { name, value } => {
+                        println!("{}", examples.set_param(example_index, &name, &value, e)?)
+                    }
+                    Command::Params => print!("{}", examples.get(example_index, e).common().params),
+                    Command::Pause(pause) => {
+                        paused = pause.unwrap_or(!paused);
+                        println!("Paused: {paused}");
+                    }
+                    Command::Step(frames) => {
+                        paused = true;
+                        steps = frames;
+                    }
+                    Command::Screenshot(path) => {
+                        let label = match split {
+                            Some(_) => "split",
+                            None => examples.name(example_index),
+                        };
+                        screenshot_to = Some(path.unwrap_or_else(|| screenshot_path(label)));
+                    }
+                    Command::Polygon(mode) => {
+                        let common = examples.get(example_index, e).common();
+                        common.polygon_mode = mode;
+                    }
+                    Command::Record(Some(settings)) => record_start = Some(settings),
+                    Command::Record(None) => finish_recording(&mut recorder, &e.device),
+                    Command::Reload => {
+                        recompile_shaders(&mut examples, lens.as_mut(), split.as_mut(), &dumping, e)
+                    }
+                    Command::ShaderDump => {
+                        if !dumping.contains(&example_index) {
+                            dumping.push(example_index);
+                        }
+                        dump_shader(examples.get(example_index, e).common(), &e.device);
+                    }
+                    Command::List => {
+                        for i in 0..examples.len() {
+                            let shown = match split.as_ref() {
+                                Some(split) => split.examples().contains(&i),
+                                None => i == example_index,
+                            };
+                            println!(
+                                "{} {} {}{}",
+                                if shown { "*" } else { " " },
+                                i + 1,
+                                examples.name(i),
+                                if examples.fault(i).is_some() {
+                                    " (faulted)"
+                                } else {
+                                    ""
+                                }
+                            );
+                        }
+                    }
+                    Command::Help => print!("{}", console::HELP),
+                    Command::Quit => quit = true,
+                    // Handled by the console
+                    Command::Wait(_) => {}
+                }
+                Ok(())
+            };
+            if let Err(err) = command.and_then(&mut run) {
+                println!("{origin}: {err}");
+            }
+        }
+
+        // Requests, see `remote.rs`
+        while let Some(pending) = server.as_ref().and_then(remote::Server::try_next) {
+            use remote::Request;
+            let e = &mut example_data;
+            let result = match pending.request.clone() {
+                Request::Example { name } => examples
+                    .find(&name)
+                    .ok_or_else(|| format!("no example \"{name}\""))
+                    .map(|index| {
+                        example_index = index;
+                        if let Some(split) = split.as_mut() {
+                            let cell = split.focused(e.mouse, e.viewport);
+                            split.set_example(cell, index);
+                        }
+                        Value::Null
+                    }),
+                Request::Set { name, value } => examples
+                    .set_param(example_index, &name, &remote::value_text(&value), e)
+                    .map(|param| json!({ "param": param })),
+                Request::Step { frames } => {
+                    paused = true;
+                    steps = frames;
+                    step_requests.push(pending);
+                    continue;
+                }
+                Request::Frame => {
+                    frame_requests.push(pending);
+                    continue;
+                }
+                Request::Status => {
+                    let shown = match split.as_ref() {
+                        Some(split) => split.examples(),
+                        None => vec![example_index],
+                    };
+                    Ok(remote::status(
+                        &mut examples,
+                        &shown,
+                        example_index,
+                        e,
+                        paused,
+                    ))
+                }
+                Request::FrameLog => Ok(remote::frame_log()),
+                Request::Quit => {
+                    quit = true;
+                    Ok(Value::Null)
+                }
+                // Cursor, click, scroll
+                request => {
+                    if let Some((mouse, events)) =
+                        request.input(e.mouse, e.viewport, e.scale_factor())
+                    {
+                        e.mouse = mouse;
+                        for event in &events {
+                            match split.as_mut() {
+                                Some(split) => split.handle_input(&mut examples, e, event),
+                                None => examples.get(example_index, e).handle_input(event),
+                            }
+                        }
+                    }
+                    Ok(Value::Null)
+                }
+            };
+            pending.reply(result);
+        }
+
+        // In split screen the example under the cursor has the focus
+        if let Some(split) = split.as_ref() {
+            example_index = split.focused_example(example_data.mouse, example_data.viewport);
+        }
+        // The examples on screen
+        let active = match split.as_ref() {
+            Some(split) => split.examples(),
+            None => vec![example_index],
+        };
+        examples.set_active(&active, &example_data);
 
         // Re-compile shaders if fs events on wgsl files happen
         watcher.poll().unwrap();
@@ -265,10 +1075,14 @@ fn main() {
                             .iter()
                             .any(|p| p.extension().unwrap_or_default() == "wgsl")
                     {
-                        println!("wgsl changed, asking example to recompile shader");
-                        let common = ex.common();
-                        common.recreate_shader(&example_data.device);
-                        common.dirty = true;
+                        println!("wgsl changed, asking examples to recompile shaders");
+                        recompile_shaders(
+                            &mut examples,
+                            lens.as_mut(),
+                            split.as_mut(),
+                            &dumping,
+                            &example_data,
+                        );
                     }
                 }
                 Err(e) => println!("Watch err: {e:?}"),
@@ -278,11 +1092,45 @@ fn main() {
         // Update time, counters
         let now = std::time::Instant::now();
         let dt = now - last_time;
-        // Example time update
-        ex.common().increase_time(dt);
+        // Whether this event renders a frame
+        let rendering = matches!(
+            event,
+            winit::event::Event::RedrawRequested(_) | winit::event::Event::RedrawEventsCleared
+        );
+        // Example time update.
+        // While recording by 1/60 s per frame instead, see `record.rs`.
+        if !paused {
+            let dt = match (&recorder, rendering) {
+                (None, _) => dt,
+                (Some(_), true) => Duration::from_secs_f32(1. / 60.),
+                (Some(_), false) => Duration::ZERO,
+            };
+            for &i in &active {
+                examples.get(i, &example_data).common().increase_time(dt);
+            }
+        }
+        // Stepping while paused, see the `step` command
+        if paused && steps > 0 && rendering {
+            steps -= 1;
+            for &i in &active {
+                let common = examples.get(i, &example_data).common();
+                common.increase_time(Duration::from_secs_f32(1. / 60.));
+            }
+        }
         last_time = now;
 
-        *ctrl_flow = ControlFlow::WaitUntil(now + Duration::from_secs_f32(1. / 60.));
+        // Asked for by a key last time around, when the example was borrowed
+        if reset {
+            reset = false;
+            examples.reset(example_index, &example_data);
+        }
+
+        let ex: &mut dyn Example = examples.get(example_index, &example_data);
+
+        *ctrl_flow = match quit {
+            true => ControlFlow::Exit,
+            false => ControlFlow::WaitUntil(now + Duration::from_secs_f32(1. / 60.)),
+        };
 
         // do a thing every second
         one_second -= dt.as_secs_f32();
@@ -299,7 +1147,6 @@ fn main() {
                 ..
             } => {
                 *ctrl_flow = ControlFlow::Exit;
-                return;
             }
 
             Event::WindowEvent {
@@ -320,43 +1167,143 @@ fn main() {
                     return;
                 }
 
+                let chord = KeyChord {
+                    key: virtual_keycode,
+                    modifiers,
+                };
+                let action = match keymap.lookup(ex.common().label, chord) {
+                    Some((Scope::Example, action)) => {
+                        ex.handle_action(action);
+                        return;
+                    }
+                    Some((Scope::Global, action)) => action,
+                    None => return,
+                };
+
                 let common = ex.common();
-                match virtual_keycode {
-                    VirtualKeyCode::Up | VirtualKeyCode::W => {
+                match action {
+                    "polygon-mode-fill" => {
                         common.polygon_mode = match common.polygon_mode {
                             PolygonMode::Fill => PolygonMode::Fill,
                             PolygonMode::Line => PolygonMode::Fill,
                             PolygonMode::Point => PolygonMode::Line,
                         };
-                        common.dirty = true;
                     }
-                    VirtualKeyCode::Down | VirtualKeyCode::S => {
+                    "polygon-mode-point" => {
                         common.polygon_mode = match common.polygon_mode {
                             PolygonMode::Fill => PolygonMode::Line,
                             PolygonMode::Line => PolygonMode::Point,
                             PolygonMode::Point => PolygonMode::Point,
                         };
-                        common.dirty = true;
                     }
 
-                    VirtualKeyCode::Escape => {
+                    // X-ray lens on/off
+                    "lens" => {
+                        lens = match lens {
+                            Some(_) => None,
+                            None => Some(xray::XrayLens::new(&example_data)),
+                        };
+                    }
+                    // Lens shape
+                    "lens-shape" => {
+                        if let Some(lens) = lens.as_mut() {
+                            lens.toggle_shape();
+                        }
+                    }
+                    // What the lens shows
+                    "lens-mode" => {
+                        if let Some(lens) = lens.as_mut() {
+                            lens.next_mode();
+                        }
+                    }
+
+                    // Split screen on/off.
+                    // Starts with the `--split` examples, else this and the next one.
+                    "split-screen" => {
+                        split = match split {
+                            Some(_) => None,
+                            None => {
+                                let cells = split_cells.clone().unwrap_or_else(|| {
+                                    let first = example_index.min(examples.len() - 2);
+                                    vec![first, first + 1]
+                                });
+                                Some(split::SplitScreen::new(&example_data, cells))
+                            }
+                        };
+                    }
+                    "screenshot" => {
+                        let label = match split {
+                            Some(_) => "split",
+                            None => common.label,
+                        };
+                        screenshot_to = Some(screenshot_path(label));
+                    }
+                    "record" => match recorder {
+                        Some(_) => finish_recording(&mut recorder, &example_data.device),
+                        None => record_start = Some(record::Settings::default()),
+                    },
+                    "print-frame" => print_frame = true,
+                    "reset-example" => reset = true,
+                    "pause" => {
+                        paused = !paused;
+                        println!("Paused: {paused}");
+                    }
+                    "help" => {
+                        print!("{}", keymap.help(common.label));
+                        print!("{}", common.params);
+                    }
+
+                    // Generic controls for the example's parameters, see `params.rs`
+                    "next-param" | "previous-param" | "increase-param" | "decrease-param" => {
+                        let params = &mut common.params;
+                        match action {
+                            "next-param" => params.select(1),
+                            "previous-param" => params.select(-1),
+                            _ => {}
+                        }
+                        let Some(name) = params.selected() else {
+                            println!("{} has no parameters", common.label);
+                            return;
+                        };
+                        match action {
+                            "increase-param" => params.adjust(name, 1),
+                            "decrease-param" => params.adjust(name, -1),
+                            _ => {}
+                        }
+                        println!("{}.{}", common.label, params.describe(name));
+                    }
+                    "print-params" => {
+                        println!("{} parameters:", common.label);
+                        print!("{}", common.params);
+                    }
+                    "shader-dump" => {
+                        if !dumping.contains(&example_index) {
+                            dumping.push(example_index);
+                        }
+                        dump_shader(common, &example_data.device);
+                    }
+
+                    "quit" => {
                         *ctrl_flow = ControlFlow::Exit;
-                        return;
                     }
                     // [P]revious example
-                    VirtualKeyCode::P => {
+                    "previous-example" => {
                         example_index = example_index.saturating_sub(1);
-                        return;
                     }
                     // [N]ext example
-                    VirtualKeyCode::N => {
+                    "next-example" => {
                         example_index = (example_index + 1).min(examples.len() - 1);
-                        return;
                     }
-                    _ => {}
+                    _ => unreachable!("unhandled global action {action}"),
                 }
 
-                ex.handle_key(virtual_keycode);
+                // In split screen P/N change what the focused cell shows
+                if matches!(action, "previous-example" | "next-example") {
+                    if let Some(split) = split.as_mut() {
+                        let cell = split.focused(example_data.mouse, example_data.viewport);
+                        split.set_example(cell, example_index);
+                    }
+                }
             }
 
             Event::WindowEvent {
@@ -396,6 +1343,82 @@ fn main() {
                 ..
             } => {
                 example_data.mouse = [position.x as f32, position.y as f32];
+                let event = InputEvent::CursorMoved {
+                    position: example_data.mouse_position(),
+                };
+                match split.as_mut() {
+                    Some(split) => split.handle_input(&mut examples, &example_data, &event),
+                    None => ex.handle_input(&event),
+                }
+            }
+
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(state),
+                ..
+            } => {
+                modifiers = Modifiers {
+                    shift: state.shift(),
+                    ctrl: state.ctrl(),
+                    alt: state.alt(),
+                    logo: state.logo(),
+                };
+                let event = InputEvent::ModifiersChanged(modifiers);
+                match split.as_mut() {
+                    Some(split) => split.handle_input(&mut examples, &example_data, &event),
+                    None => ex.handle_input(&event),
+                }
+            }
+
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { state, button, .. },
+                ..
+            } => {
+                let event = InputEvent::Button {
+                    button: match button {
+                        winit::event::MouseButton::Left => MouseButton::Left,
+                        winit::event::MouseButton::Right => MouseButton::Right,
+                        winit::event::MouseButton::Middle => MouseButton::Middle,
+                        winit::event::MouseButton::Other(other) => MouseButton::Other(other),
+                    },
+                    pressed: state == ElementState::Pressed,
+                    position: example_data.mouse_position(),
+                    modifiers,
+                };
+                match split.as_mut() {
+                    Some(split) => split.handle_input(&mut examples, &example_data, &event),
+                    None => ex.handle_input(&event),
+                }
+            }
+
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                ..
+            } => {
+                let event = InputEvent::Scroll {
+                    delta: match delta {
+                        MouseScrollDelta::LineDelta(x, y) => ScrollDelta::Lines([x, y]),
+                        // Nothing until the touchpad moved about a line
+                        MouseScrollDelta::PixelDelta(p) => {
+                            match pixel_scroll.add([p.x as f32, p.y as f32]) {
+                                Some(pixels) => ScrollDelta::Pixels(pixels),
+                                None => return,
+                            }
+                        }
+                    },
+                    position: example_data.mouse_position(),
+                    modifiers,
+                };
+
+                // The lens takes the scroll to resize itself (not used in split screen)
+                match (split.as_mut(), lens.as_mut()) {
+                    (Some(split), _) => split.handle_input(&mut examples, &example_data, &event),
+                    (None, Some(lens)) => {
+                        if let Some(scroll_up) = event.scroll_up() {
+                            lens.handle_scroll(scroll_up);
+                        }
+                    }
+                    (None, None) => ex.handle_input(&event),
+                }
             }
 
             // Event::NewEvents(_) => todo!(),
@@ -408,35 +1431,105 @@ fn main() {
             // Event::RedrawRequested(_) => todo!(),
             // Event::LoopDestroyed => todo!(),
             Event::RedrawRequested(_) | Event::RedrawEventsCleared => {
+                let surface = example_data.surface.as_ref().expect("windowed");
+                let current_texture = match surface.get_current_texture() {
+                    Ok(t) => t,
+                    Err(wgpu::SurfaceError::Outdated) => return,
+                    Err(e) => panic!("{e:?}"),
+                };
+                let view = current_texture
+                    .texture
+                    .create_view(&TextureViewDescriptor::default());
+
+                let label = match split {
+                    Some(_) => "split",
+                    None => examples.name(example_index),
+                };
+
+                debug::begin_frame();
+                let frame = examples.get(example_index, &example_data).common().frame;
+
+                if let Some(settings) = record_start.take() {
+                    match record::Recorder::start(settings, label, &current_texture.texture) {
+                        Ok(started) => {
+                            println!("Recording to {:?}", started.path());
+                            recorder = Some(started);
+                        }
+                        Err(err) => println!("Could not record: {err}"),
+                    }
+                }
+
                 // Render!
-                ex.render(&example_data);
-                ex.common().increase_frame();
-                num_renders_since_last_second += 1;
-            }
+                // Split screen, or the example through the lens, or just the example
+                {
+                    let _scope = debug::scope(&format!("{label} frame {frame}"));
+                    let e = &mut example_data;
+                    match (split.as_mut(), lens.as_mut()) {
+                        (Some(split), _) => split.render(&mut examples, e, &view),
+                        (None, Some(lens)) => {
+                            examples.render(example_index, e, &view, |ex, e, view| {
+                                lens.render(ex, e, view)
+                            })
+                        }
+                        (None, None) => examples
+                            .render(example_index, e, &view, |ex, e, view| ex.render(e, view)),
+                    }
+                }
 
-            Event::DeviceEvent {
-                event:
-                    DeviceEvent::MouseWheel {
-                        delta: MouseScrollDelta::LineDelta(_horizontal, vertical),
-                    },
-                ..
-            } => {
-                if vertical > 0.5 {
-                    ex.handle_scroll(true)
-                } else if vertical < -0.5 {
-                    ex.handle_scroll(false)
+                let texture = &current_texture.texture;
+                if let Some(path) = screenshot_to.take() {
+                    let _scope = debug::scope("screenshot");
+                    match screenshot(&example_data, texture, &path) {
+                        Ok(()) => println!("Saved screenshot to {path:?}"),
+                        Err(err) => println!("Could not save screenshot: {err}"),
+                    }
+                }
+                // Like a screenshot
+                if !frame_requests.is_empty() {
+                    let _scope = debug::scope("frame request");
+                    let result = read_frame(&example_data, texture)
+                        .and_then(|bytes| remote::frame(texture.size(), texture.format(), &bytes));
+                    for pending in frame_requests.drain(..) {
+                        pending.reply(result.clone());
+                    }
                 }
-            }
 
-            Event::DeviceEvent {
-                event: DeviceEvent::Button { button: 1, state },
-                ..
-            } => {
-                ex.handle_click(example_data.mouse, state == ElementState::Pressed);
+                if let Some(rec) = recorder.as_mut() {
+                    let result = rec.frame_rendered(
+                        &example_data.device,
+                        &example_data.queue,
+                        &current_texture.texture,
+                    );
+                    if let Err(err) = &result {
+                        println!("Stopping the recording: {err}");
+                    }
+                    if result.is_err() || rec.done() {
+                        finish_recording(&mut recorder, &example_data.device);
+                    }
+                }
+                current_texture.present();
+                if print_frame {
+                    print_frame = false;
+                    print!("{}", debug::format_frame(&debug::take_frame()));
+                }
+                for &i in &active {
+                    examples.get(i, &example_data).common().increase_frame();
+                }
+                num_renders_since_last_second += 1;
+                console.frame_rendered();
+                if steps == 0 {
+                    for pending in step_requests.drain(..) {
+                        pending.reply(Ok(Value::Null));
+                    }
+                }
             }
 
             Event::DeviceEvent {
-                event: DeviceEvent::MouseMotion { .. } | DeviceEvent::Motion { .. },
+                event:
+                    DeviceEvent::MouseMotion { .. }
+                    | DeviceEvent::Motion { .. }
+                    | DeviceEvent::MouseWheel { .. }
+                    | DeviceEvent::Button { .. },
                 ..
             }
             | Event::MainEventsCleared
@@ -444,6 +1537,11 @@ fn main() {
                 // Verbose, don't print
             }
 
+            Event::LoopDestroyed => {
+                finish_recording(&mut recorder, &example_data.device);
+                examples.save_params();
+            }
+
             e => {
                 let mut should_print = true;
                 if !is_focused {
@@ -0,0 +1,577 @@
+/*
+Named parameters, instead of each example having its own fields, keys and clamps for its knobs.
+
+An example declares its parameters up front (like its actions), with a default and a range:
+
+    pub const PARAMS: &[Param] = &[
+        Param::f32("radius", "Radius of the ring", 0.3, 0.1, 2., 0.1),
+        Param::bool("eraser", "Erase instead of paint", false),
+    ];
+
+and reads the current values from `ExampleCommonState::params`, e.g. `params.f32("radius")`.
+The runner selects and adjusts them with generic keys, and prints them (see `main.rs`).
+
+Values can be set per example in a config file, one per line:
+
+    # namespace.parameter = value
+    ex02.radius = 0.5
+    ex07.color = cyan
+    ex07.eraser = true
+
+or from the command line, e.g. `--param ex02.radius=0.5`.
+Values changed while running are saved to the config file, such that they're the same next run.
+Values from the command line win over the file, but aren't saved (unless changed while running).
+ */
+use std::{collections::BTreeMap, fmt, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    F32 { min: f32, max: f32, step: f32 },
+    U32 { min: u32, max: u32, step: u32 },
+    Bool,
+    // One of the options, by index
+    Enum(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    F32(f32),
+    U32(u32),
+    Bool(bool),
+    Enum(usize),
+}
+
+#[derive(Debug)]
+pub struct Param {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: Kind,
+    pub default: Value,
+}
+
+impl Param {
+    pub const fn f32(
+        name: &'static str,
+        description: &'static str,
+        default: f32,
+        min: f32,
+        max: f32,
+        step: f32,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            kind: Kind::F32 { min, max, step },
+            default: Value::F32(default),
+        }
+    }
+
+    pub const fn u32(
+        name: &'static str,
+        description: &'static str,
+        default: u32,
+        min: u32,
+        max: u32,
+        step: u32,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            kind: Kind::U32 { min, max, step },
+            default: Value::U32(default),
+        }
+    }
+
+    pub const fn bool(name: &'static str, description: &'static str, default: bool) -> Self {
+        Self {
+            name,
+            description,
+            kind: Kind::Bool,
+            default: Value::Bool(default),
+        }
+    }
+
+    pub const fn choice(
+        name: &'static str,
+        description: &'static str,
+        options: &'static [&'static str],
+        default: usize,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            kind: Kind::Enum(options),
+            default: Value::Enum(default),
+        }
+    }
+
+    // Into range, or the default if it's the wrong kind of value
+    fn clamp(&self, value: Value) -> Value {
+        match (self.kind, value) {
+            (Kind::F32 { min, max, .. }, Value::F32(v)) => Value::F32(v.clamp(min, max)),
+            (Kind::U32 { min, max, .. }, Value::U32(v)) => Value::U32(v.clamp(min, max)),
+            (Kind::Bool, Value::Bool(v)) => Value::Bool(v),
+            (Kind::Enum(options), Value::Enum(v)) => Value::Enum(v.min(options.len() - 1)),
+            _ => self.default,
+        }
+    }
+
+    // `steps` up or down, toggles a bool and cycles through the options of an enum
+    fn step(&self, value: Value, steps: i32) -> Value {
+        let value = match (self.kind, value) {
+            (Kind::F32 { step, .. }, Value::F32(v)) => Value::F32(v + steps as f32 * step),
+            (Kind::U32 { step, .. }, Value::U32(v)) => {
+                Value::U32(v.saturating_add_signed(steps.saturating_mul(step as i32)))
+            }
+            (Kind::Bool, Value::Bool(v)) => Value::Bool(v ^ (steps % 2 != 0)),
+            (Kind::Enum(options), Value::Enum(v)) => {
+                let n = options.len() as i32;
+                Value::Enum((v as i32 + steps).rem_euclid(n) as usize)
+            }
+            _ => value,
+        };
+        self.clamp(value)
+    }
+
+    fn parse(&self, text: &str) -> Result<Value, String> {
+        let text = text.trim();
+        let value = match self.kind {
+            Kind::F32 { .. } => text.parse().map(Value::F32).ok(),
+            Kind::U32 { .. } => text.parse().map(Value::U32).ok(),
+            Kind::Bool => match text {
+                "true" | "on" | "1" => Some(Value::Bool(true)),
+                "false" | "off" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            // By name, or by index
+            Kind::Enum(options) => options
+                .iter()
+                .position(|&option| option == text)
+                .or_else(|| text.parse().ok().filter(|&i| i < options.len()))
+                .map(Value::Enum),
+        };
+        value
+            .map(|value| self.clamp(value))
+            .ok_or_else(|| format!("\"{text}\" isn't {} for \"{}\"", self.range(), self.name))
+    }
+
+    fn format(&self, value: Value) -> String {
+        match (self.kind, value) {
+            // As many decimals as the step has
+            (Kind::F32 { step, .. }, Value::F32(v)) => {
+                let decimals = (-step.log10().floor()).max(0.) as usize;
+                format!("{v:.decimals$}")
+            }
+            (_, Value::U32(v)) => v.to_string(),
+            (_, Value::Bool(v)) => v.to_string(),
+            (Kind::Enum(options), Value::Enum(v)) => options[v].to_string(),
+            (_, value) => format!("{value:?}"),
+        }
+    }
+
+    fn range(&self) -> String {
+        match self.kind {
+            Kind::F32 { min, max, step } => format!(
+                "{} to {}, step {}",
+                self.format(Value::F32(min)),
+                self.format(Value::F32(max)),
+                self.format(Value::F32(step))
+            ),
+            Kind::U32 { min, max, step } => format!("{min} to {max}, step {step}"),
+            Kind::Bool => "true or false".to_string(),
+            Kind::Enum(options) => options.join(", "),
+        }
+    }
+}
+
+// The current values of an example's parameters
+#[derive(Debug, Default)]
+pub struct Params {
+    params: &'static [Param],
+    values: Vec<Value>,
+    // Changed while running, see `Config::update`
+    changed: Vec<bool>,
+    // For the runner's generic controls
+    selected: usize,
+}
+
+impl Params {
+    pub fn new(params: &'static [Param]) -> Self {
+        Self {
+            params,
+            values: params.iter().map(|param| param.default).collect(),
+            changed: vec![false; params.len()],
+            selected: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    // Asking for a parameter which wasn't declared is a bug in the example
+    fn index(&self, name: &str) -> usize {
+        self.params
+            .iter()
+            .position(|param| param.name == name)
+            .unwrap_or_else(|| panic!("no parameter \"{name}\""))
+    }
+
+    fn get(&self, name: &str) -> Value {
+        self.values[self.index(name)]
+    }
+
+    pub fn f32(&self, name: &str) -> f32 {
+        match self.get(name) {
+            Value::F32(v) => v,
+            other => panic!("parameter \"{name}\" is {other:?}, not f32"),
+        }
+    }
+
+    pub fn u32(&self, name: &str) -> u32 {
+        match self.get(name) {
+            Value::U32(v) => v,
+            other => panic!("parameter \"{name}\" is {other:?}, not u32"),
+        }
+    }
+
+    pub fn bool(&self, name: &str) -> bool {
+        match self.get(name) {
+            Value::Bool(v) => v,
+            other => panic!("parameter \"{name}\" is {other:?}, not bool"),
+        }
+    }
+
+    // The index of the chosen option
+    pub fn choice(&self, name: &str) -> usize {
+        match self.get(name) {
+            Value::Enum(v) => v,
+            other => panic!("parameter \"{name}\" is {other:?}, not an enum"),
+        }
+    }
+
+    // Set and clamp into range
+    pub fn set(&mut self, name: &str, value: Value) {
+        let i = self.index(name);
+        self.values[i] = self.params[i].clamp(value);
+        self.changed[i] = true;
+    }
+
+    // A number of steps up or down, see `Param::step`
+    pub fn adjust(&mut self, name: &str, steps: i32) {
+        let i = self.index(name);
+        self.values[i] = self.params[i].step(self.values[i], steps);
+        self.changed[i] = true;
+    }
+
+    // From a config file or the command line, which doesn't count as a change
+    pub fn load(&mut self, name: &str, text: &str) -> Result<(), String> {
+        let i = self
+            .params
+            .iter()
+            .position(|param| param.name == name)
+            .ok_or_else(|| format!("no parameter \"{name}\""))?;
+        self.values[i] = self.params[i].parse(text)?;
+        Ok(())
+    }
+
+    // Select the next (or previous, if negative) parameter, wrapping around
+    pub fn select(&mut self, by: i32) {
+        if !self.is_empty() {
+            let n = self.params.len() as i32;
+            self.selected = (self.selected as i32 + by).rem_euclid(n) as usize;
+        }
+    }
+
+    pub fn selected(&self) -> Option<&'static str> {
+        self.params.get(self.selected).map(|param| param.name)
+    }
+
+    // E.g. `radius = 0.3 (0.1 to 2.0, step 0.1) Radius of the ring`
+    pub fn describe(&self, name: &str) -> String {
+        let i = self.index(name);
+        let param = &self.params[i];
+        format!(
+            "{} = {} ({}) {}",
+            param.name,
+            param.format(self.values[i]),
+            param.range(),
+            param.description
+        )
+        .trim_end()
+        .to_string()
+    }
+
+    // Every value, formatted such that `load` reads them back
+    pub fn values(&self) -> impl Iterator<Item = (&'static str, String)> + '_ {
+        self.params
+            .iter()
+            .zip(&self.values)
+            .map(|(param, &value)| (param.name, param.format(value)))
+    }
+
+    // Changed values, formatted such that `load` reads them back
+    fn changed(&self) -> impl Iterator<Item = (&'static str, String)> + '_ {
+        self.params
+            .iter()
+            .zip(&self.values)
+            .zip(&self.changed)
+            .filter(|(_, &changed)| changed)
+            .map(|((param, &value), _)| (param.name, param.format(value)))
+    }
+}
+
+// All parameters, the selected one marked
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, param) in self.params.iter().enumerate() {
+            let mark = if i == self.selected { ">" } else { " " };
+            writeln!(f, "{mark} {}", self.describe(param.name))?;
+        }
+        Ok(())
+    }
+}
+
+// Parameter values by "namespace.parameter", see the top of this file
+#[derive(Debug, Default)]
+pub struct Config {
+    // From the file, and what changed while running
+    saved: BTreeMap<String, String>,
+    // From the command line
+    overrides: BTreeMap<String, String>,
+    // `saved` differs from the file
+    dirty: bool,
+}
+
+// "namespace.parameter = value", also from the command line without spaces
+fn parse_line(line: &str) -> Result<(String, String), String> {
+    let (name, value) = line
+        .split_once('=')
+        .ok_or("expected \"namespace.parameter = value\"")?;
+    let name = name.trim();
+    if !name.contains('.') {
+        return Err(format!("expected \"namespace.parameter\", got \"{name}\""));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+impl Config {
+    // The text of a config file, and `--param` values from the command line.
+    // Returns one message per line or value which couldn't be used.
+    pub fn parse(config: &str, args: &[String]) -> (Self, Vec<String>) {
+        let mut errors = vec![];
+        let mut saved = BTreeMap::new();
+        let mut overrides = BTreeMap::new();
+
+        for (line_number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_line(line) {
+                Ok((name, value)) => {
+                    saved.insert(name, value);
+                }
+                Err(err) => errors.push(format!("line {}: {err}", line_number + 1)),
+            }
+        }
+        for arg in args {
+            match parse_line(arg) {
+                Ok((name, value)) => {
+                    overrides.insert(name, value);
+                }
+                Err(err) => errors.push(format!("--param {arg}: {err}")),
+            }
+        }
+
+        let config = Self {
+            saved,
+            overrides,
+            dirty: false,
+        };
+        (config, errors)
+    }
+
+    // Read the file if it exists, printing problems
+    pub fn load(path: &Path, args: &[String]) -> Self {
+        let text = std::fs::read_to_string(path).unwrap_or_default();
+        let (config, errors) = Self::parse(&text, args);
+        for error in errors {
+            println!("{path:?} {error}");
+        }
+        config
+    }
+
+    // Values for parameters which don't exist (or can't be parsed), given what each namespace declares
+    pub fn check(&self, namespaces: &[(&str, &'static [Param])]) -> Vec<String> {
+        let mut errors = vec![];
+        for (name, value) in self.saved.iter().chain(&self.overrides) {
+            let (namespace, param) = name.split_once('.').unwrap();
+            match namespaces.iter().find(|(ns, _)| *ns == namespace) {
+                Some((_, params)) => {
+                    if let Err(err) = Params::new(params).load(param, value) {
+                        errors.push(format!("{namespace}: {err}"));
+                    }
+                }
+                None => errors.push(format!("no namespace \"{namespace}\" for \"{name}\"")),
+            }
+        }
+        errors
+    }
+
+    // Set what's configured for the namespace.
+    // Returns problems, e.g. a value out of range was clamped, or a parameter doesn't exist.
+    pub fn apply(&self, namespace: &str, params: &mut Params) -> Vec<String> {
+        let mut errors = vec![];
+        // Overrides last, such that they win
+        for (name, value) in self.saved.iter().chain(&self.overrides) {
+            let Some(param) = name
+                .strip_prefix(namespace)
+                .and_then(|name| name.strip_prefix('.'))
+            else {
+                continue;
+            };
+            if let Err(err) = params.load(param, value) {
+                errors.push(format!("{namespace}: {err}"));
+            }
+        }
+        errors
+    }
+
+    // Remember what changed while running, to save it
+    pub fn update(&mut self, namespace: &str, params: &Params) {
+        for (param, value) in params.changed() {
+            let name = format!("{namespace}.{param}");
+            // Changed by hand, so the command line no longer applies
+            self.overrides.remove(&name);
+            if self.saved.get(&name) != Some(&value) {
+                self.saved.insert(name, value);
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = "# namespace.parameter = value\n".to_string();
+        for (name, value) in &self.saved {
+            text += &format!("{name} = {value}\n");
+        }
+        text
+    }
+
+    // Write the file if anything changed
+    pub fn save(&mut self, path: &Path) {
+        if !self.dirty {
+            return;
+        }
+        match std::fs::write(path, self.to_text()) {
+            Ok(()) => {
+                self.dirty = false;
+                println!("Saved parameters to {path:?}");
+            }
+            Err(err) => println!("Could not save parameters to {path:?}: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMS: &[Param] = &[
+        Param::f32("radius", "", 0.3, 0.1, 2., 0.1),
+        Param::u32("instances", "", 10, 3, 100, 1),
+        Param::bool("eraser", "", false),
+        Param::choice("color", "", &["red", "green", "blue"], 0),
+    ];
+
+    #[test]
+    fn adjust_clamps_and_cycles() {
+        let mut params = Params::new(PARAMS);
+        params.adjust("radius", -5);
+        assert_eq!(params.f32("radius"), 0.1);
+        params.adjust("instances", 1000);
+        assert_eq!(params.u32("instances"), 100);
+        params.adjust("instances", -1000);
+        assert_eq!(params.u32("instances"), 3);
+        params.adjust("eraser", 1);
+        assert!(params.bool("eraser"));
+        params.adjust("color", -1);
+        assert_eq!(params.choice("color"), 2);
+    }
+
+    #[test]
+    fn load_parses_by_kind() {
+        let mut params = Params::new(PARAMS);
+        assert!(params.load("radius", "0.5").is_ok());
+        assert!(params.load("color", "blue").is_ok());
+        assert!(params.load("eraser", "on").is_ok());
+        assert_eq!(
+            (
+                params.f32("radius"),
+                params.choice("color"),
+                params.bool("eraser")
+            ),
+            (0.5, 2, true)
+        );
+        // Out of range is clamped
+        assert!(params.load("instances", "1").is_ok());
+        assert_eq!(params.u32("instances"), 3);
+
+        assert!(params.load("color", "purple").is_err());
+        assert!(params.load("radius", "big").is_err());
+        assert!(params.load("nope", "1").is_err());
+        // Loading isn't a change
+        assert_eq!(params.changed().count(), 0);
+    }
+
+    #[test]
+    fn config_overrides_win_and_aren_t_saved() {
+        let (mut config, errors) = Config::parse(
+            "# comment\nex02.radius = 0.5\nex02.instances = 20\ngarbage\n",
+            &["ex02.radius=0.7".to_string()],
+        );
+        assert_eq!(errors.len(), 1, "{errors:?}");
+
+        let mut params = Params::new(PARAMS);
+        assert!(config.apply("ex02", &mut params).is_empty());
+        assert_eq!(params.f32("radius"), 0.7);
+        assert_eq!(params.u32("instances"), 20);
+
+        // Other namespaces don't apply
+        let mut other = Params::new(PARAMS);
+        assert!(config.apply("ex0", &mut other).is_empty());
+        assert_eq!(other.f32("radius"), 0.3);
+
+        params.adjust("instances", 1);
+        config.update("ex02", &params);
+        assert_eq!(
+            config.to_text(),
+            "# namespace.parameter = value\nex02.instances = 21\nex02.radius = 0.5\n"
+        );
+    }
+
+    #[test]
+    fn config_check() {
+        let (config, _) = Config::parse(
+            "ex02.radius = 0.5\nex02.nope = 1\nex99.radius = 1\nex02.color = purple\n",
+            &[],
+        );
+        let errors = config.check(&[("ex02", PARAMS)]);
+        assert_eq!(errors.len(), 3, "{errors:?}");
+    }
+
+    #[test]
+    fn describe() {
+        let mut params = Params::new(PARAMS);
+        params.select(-1);
+        assert_eq!(params.selected(), Some("color"));
+        assert_eq!(
+            params.describe("radius"),
+            "radius = 0.3 (0.1 to 2.0, step 0.1)"
+        );
+        assert!(params
+            .to_string()
+            .contains("> color = red (red, green, blue)"));
+    }
+}
@@ -0,0 +1,339 @@
+/*
+Recording what's on screen, e.g. an animation for a bug report.
+F10 starts and stops a GIF, the console's `record` does more:
+`record gif 120` records 120 frames, `record png 0 2` every second frame as PNGs until `record stop`.
+They're written to `recordings/<example>-<timestamp>.gif`, or `recordings/<example>-<timestamp>/00000.png, ..`.
+
+While recording, example time moves by 1/60 s per frame instead of by the clock,
+such that the recording plays smoothly even when recording slows the zoo down.
+
+Things we learned:
+    - Waiting for `map_async` right after copying a frame (like screenshots do, see `util::read_texture`)
+        stalls until the GPU has finished that frame. Instead each copy goes into one of a few buffers,
+        and is picked up a frame or two later once it's mapped. Only when every buffer is still
+        in flight do we wait.
+    - The swapchain texture can be copied from when the surface is configured with `COPY_SRC`.
+        That saves rendering every frame twice, which would also make e.g. example 7 paint twice as fast.
+    - GIF frame delays are in 1/100 s. 1/60 s rounds to 2/100 s, so a GIF of every frame plays at 50 fps,
+        a bit slow. Every second frame is 3/100 s, 33 fps.
+    - Encoding a GIF frame (down to 256 colors) takes a while, so frames are written on a thread.
+ */
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, SyncSender},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Extent3d, Maintain,
+    MapMode, Queue, Texture, TextureFormat, TextureUsages,
+};
+
+use crate::util;
+
+// Copies in flight before waiting for the oldest
+const BUFFERS: usize = 3;
+// Frames read back but not written yet, before waiting for the writer
+const QUEUED_FRAMES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Gif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub format: Format,
+    // How many frames to record, until stopped if none
+    pub frames: Option<u32>,
+    // Record every nth frame
+    pub every: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            format: Format::Gif,
+            frames: None,
+            every: 1,
+        }
+    }
+}
+
+// In 1/100 s, for frames 1/60 s apart. Less than 2/100 s is often shown much slower.
+fn gif_delay(every: u32) -> u16 {
+    ((every as f32 * 100. / 60.).round() as u16).max(2)
+}
+
+// Where the frames go, on the writer thread
+enum Output {
+    // Into this directory
+    Png(PathBuf),
+    Gif(gif::Encoder<BufWriter<File>>),
+}
+
+// Write frames (tightly packed, as `util::read_texture` gives them) until the sender hangs up
+fn write_frames(
+    mut output: Output,
+    size: Extent3d,
+    format: TextureFormat,
+    delay: u16,
+    frames: Receiver<Vec<u8>>,
+) -> Result<(), String> {
+    for (i, bytes) in frames.into_iter().enumerate() {
+        match &mut output {
+            Output::Png(dir) => {
+                util::save_png(&dir.join(format!("{i:05}.png")), size, format, &bytes)?
+            }
+            Output::Gif(encoder) => {
+                let mut rgba = util::to_rgba(format, &bytes)?;
+                // Speed 10 is gif's default trade-off between quality and time
+                let mut frame = gif::Frame::from_rgba_speed(
+                    size.width as u16,
+                    size.height as u16,
+                    &mut rgba,
+                    10,
+                );
+                frame.delay = delay;
+                encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// A copy of a frame, on its way back from the GPU
+struct Readback {
+    buffer: Buffer,
+    mapped: Arc<AtomicBool>,
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    settings: Settings,
+    size: Extent3d,
+    bytes_per_row: (u32, u32),
+    // Frames rendered, and recorded, since starting
+    rendered: u32,
+    recorded: u32,
+    // Buffers to copy into, and those waiting to be mapped (oldest first)
+    free: Vec<Buffer>,
+    in_flight: VecDeque<Readback>,
+    frames: SyncSender<Vec<u8>>,
+    writer: JoinHandle<Result<(), String>>,
+}
+
+impl Recorder {
+    // Record frames like `texture` (the swapchain's), named after `label`
+    pub fn start(settings: Settings, label: &str, texture: &Texture) -> Result<Self, String> {
+        if !texture.usage().contains(TextureUsages::COPY_SRC) {
+            return Err("the surface can't be copied from (no COPY_SRC)".to_string());
+        }
+        let size = texture.size();
+        let format = texture.format();
+        // Fail now rather than on the writer thread
+        util::to_rgba(format, &[])?;
+
+        let name = format!("{label}-{}", util::timestamp());
+        let (path, output) = match settings.format {
+            Format::Png => {
+                let dir = Path::new("recordings").join(name);
+                std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+                (dir.clone(), Output::Png(dir))
+            }
+            Format::Gif => {
+                std::fs::create_dir_all("recordings").map_err(|e| e.to_string())?;
+                let path = Path::new("recordings").join(format!("{name}.gif"));
+                let file = File::create(&path).map_err(|e| e.to_string())?;
+                let mut encoder = gif::Encoder::new(
+                    BufWriter::new(file),
+                    size.width as u16,
+                    size.height as u16,
+                    &[],
+                )
+                .map_err(|e| e.to_string())?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(|e| e.to_string())?;
+                (path, Output::Gif(encoder))
+            }
+        };
+
+        let (tx, rx) = std::sync::mpsc::sync_channel(QUEUED_FRAMES);
+        let delay = gif_delay(settings.every);
+        let writer = std::thread::spawn(move || write_frames(output, size, format, delay, rx));
+
+        Ok(Self {
+            path,
+            settings,
+            size,
+            bytes_per_row: util::bytes_per_row(texture),
+            rendered: 0,
+            recorded: 0,
+            free: vec![],
+            in_flight: VecDeque::new(),
+            frames: tx,
+            writer,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Recorded as many frames as asked for
+    pub fn done(&self) -> bool {
+        Some(self.recorded) == self.settings.frames && self.in_flight.is_empty()
+    }
+
+    // Hand mapped copies to the writer, oldest first
+    fn collect(&mut self) -> Result<(), String> {
+        while let Some(readback) = self.in_flight.front() {
+            if !readback.mapped.load(Ordering::Acquire) {
+                break;
+            }
+            let readback = self.in_flight.pop_front().unwrap();
+            let bytes = util::unpad_rows(
+                &readback.buffer.slice(..).get_mapped_range(),
+                self.bytes_per_row,
+            );
+            readback.buffer.unmap();
+            self.free.push(readback.buffer);
+
+            if self.frames.send(bytes).is_err() {
+                return Err("the writer stopped".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    // The runner rendered `texture` (what's on screen), record it if it's one of the frames wanted
+    pub fn frame_rendered(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        texture: &Texture,
+    ) -> Result<(), String> {
+        if texture.size() != self.size {
+            return Err("the window changed size".to_string());
+        }
+
+        let wanted = self.rendered.is_multiple_of(self.settings.every)
+            && self.settings.frames != Some(self.recorded);
+        self.rendered += 1;
+
+        if wanted {
+            if self.free.is_empty() && self.in_flight.len() >= BUFFERS {
+                // All in flight, wait for the oldest
+                device.poll(Maintain::Wait);
+                self.collect()?;
+                if self.free.is_empty() {
+                    return Err("reading back a frame failed".to_string());
+                }
+            }
+            let buffer = self.free.pop().unwrap_or_else(|| {
+                device.create_buffer(&BufferDescriptor {
+                    label: "record-readback".into(),
+                    size: (self.bytes_per_row.1 * self.size.height) as u64,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                })
+            });
+
+            let mut ce = device.create_command_encoder(&CommandEncoderDescriptor {
+                label: "record-ce".into(),
+            });
+            util::copy_texture_to_buffer(&mut ce, texture, &buffer);
+            queue.submit(std::iter::once(ce.finish()));
+
+            let mapped = Arc::new(AtomicBool::new(false));
+            let done = mapped.clone();
+            buffer.slice(..).map_async(MapMode::Read, move |result| {
+                done.store(result.is_ok(), Ordering::Release);
+            });
+            self.in_flight.push_back(Readback { buffer, mapped });
+            self.recorded += 1;
+        }
+
+        // Doesn't wait, just runs the callbacks of copies which are done
+        device.poll(Maintain::Poll);
+        self.collect()
+    }
+
+    // Wait for the copies in flight and the writer. Says what was recorded.
+    pub fn finish(mut self, device: &Device) -> Result<String, String> {
+        device.poll(Maintain::Wait);
+        self.collect()?;
+        if !self.in_flight.is_empty() {
+            return Err("reading back a frame failed".to_string());
+        }
+
+        drop(self.frames);
+        self.writer
+            .join()
+            .map_err(|_| "the writer panicked".to_string())??;
+        Ok(format!(
+            "Recorded {} frames to {:?}",
+            self.recorded, self.path
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gif_delays() {
+        assert_eq!(gif_delay(1), 2);
+        assert_eq!(gif_delay(2), 3);
+        assert_eq!(gif_delay(6), 10);
+    }
+
+    #[test]
+    fn writes_a_gif() {
+        let path = std::env::temp_dir().join(format!("zoo-test-{}.gif", std::process::id()));
+        let size = Extent3d {
+            width: 4,
+            height: 2,
+            depth_or_array_layers: 1,
+        };
+        let encoder = gif::Encoder::new(BufWriter::new(File::create(&path).unwrap()), 4, 2, &[]);
+
+        // Two BGRA frames, red then blue
+        let (tx, rx) = std::sync::mpsc::sync_channel(2);
+        tx.send([0, 0, 255, 255].repeat(8)).unwrap();
+        tx.send([255, 0, 0, 255].repeat(8)).unwrap();
+        drop(tx);
+        write_frames(
+            Output::Gif(encoder.unwrap()),
+            size,
+            TextureFormat::Bgra8UnormSrgb,
+            3,
+            rx,
+        )
+        .unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+        let mut frames = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push((frame.delay, frame.buffer[..4].to_vec()));
+        }
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(
+            frames,
+            [(3, vec![255, 0, 0, 255]), (3, vec![0, 0, 255, 255])]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+/*
+The examples the runner knows about.
+
+Examples are registered as factories and constructed the first time they're shown,
+such that e.g. example 08's 256 textures aren't made unless it's visited.
+
+The runner tells the registry which examples are on screen (one, or several in split screen).
+Examples which appear get `on_enter`, examples which disappear get `on_exit`,
+and then, depending on their `Unload` policy, are dropped to free their resources.
+
+Examples render through the registry, which catches their wgpu errors and panics (see `fault.rs`).
+A faulted example shows a placeholder until its shaders change (hot reload) or it's reset.
+
+The registry also keeps the configured parameter values (see `params.rs`),
+sets them when an example is constructed, and remembers changes when it exits.
+ */
+use std::path::{Path, PathBuf};
+
+use wgpu::{Device, TextureView};
+
+use crate::{
+    fault::{self, Placeholder},
+    keymap::Action,
+    params::{self, Param, Params},
+    Example, ExampleData,
+};
+
+// What happens to an example when it's no longer shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unload {
+    // Keep it around, it continues where it left off when shown again
+    Keep,
+    // Drop it, it's constructed again when shown again
+    OnExit,
+}
+
+pub struct Factory {
+    // Also the example's key binding namespace, e.g. "ex01"
+    pub name: &'static str,
+    // Known up front, such that key bindings can be checked before any example exists
+    pub actions: &'static [Action],
+    // Known up front too, such that configured values can be checked
+    pub params: &'static [Param],
+    pub unload: Unload,
+    pub make: fn(&ExampleData) -> Box<dyn Example>,
+}
+
+struct Entry {
+    factory: Factory,
+    instance: Option<Box<dyn Example>>,
+    // Why the example stopped rendering
+    fault: Option<String>,
+}
+
+pub struct Registry {
+    entries: Vec<Entry>,
+    // On screen, by index
+    active: Vec<usize>,
+    // Made when an example first faults
+    placeholder: Option<Placeholder>,
+    // Parameter values, and where they're saved
+    params: params::Config,
+    params_path: Option<PathBuf>,
+}
+
+impl Registry {
+    pub fn new(factories: Vec<Factory>) -> Self {
+        Self {
+            entries: factories
+                .into_iter()
+                .map(|factory| Entry {
+                    factory,
+                    instance: None,
+                    fault: None,
+                })
+                .collect(),
+            active: vec![],
+            placeholder: None,
+            params: params::Config::default(),
+            params_path: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn factories(&self) -> impl Iterator<Item = &Factory> {
+        self.entries.iter().map(|entry| &entry.factory)
+    }
+
+    pub fn name(&self, index: usize) -> &'static str {
+        self.entries[index].factory.name
+    }
+
+    // By name ("ex07") or number ("7")
+    pub fn find(&self, name: &str) -> Option<usize> {
+        if let Some(index) = name
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=self.len()).contains(n))
+        {
+            return Some(index - 1);
+        }
+        self.entries.iter().position(|e| e.factory.name == name)
+    }
+
+    // Parameter values from a config file (where changes are saved to) and the command line.
+    // Without a file only the command line's values are used, and nothing is saved.
+    pub fn load_params(&mut self, path: Option<&Path>, args: &[String]) {
+        self.params = match path {
+            Some(path) => params::Config::load(path, args),
+            None => {
+                let (config, errors) = params::Config::parse("", args);
+                for error in errors {
+                    println!("{error}");
+                }
+                config
+            }
+        };
+        self.params_path = path.map(Path::to_path_buf);
+
+        let namespaces: Vec<_> = self.factories().map(|f| (f.name, f.params)).collect();
+        for error in self.params.check(&namespaces) {
+            println!("Parameter config: {error}");
+        }
+    }
+
+    // Remember the parameters changed in constructed examples, and save them
+    pub fn save_params(&mut self) {
+        for entry in &mut self.entries {
+            if let Some(instance) = entry.instance.as_mut() {
+                self.params
+                    .update(entry.factory.name, &instance.common().params);
+            }
+        }
+        if let Some(path) = self.params_path.as_ref() {
+            self.params.save(path);
+        }
+    }
+
+    // The example, constructed if needed
+    pub fn get(&mut self, index: usize, e: &ExampleData) -> &mut dyn Example {
+        let entry = &mut self.entries[index];
+        if entry.instance.is_none() {
+            println!("Constructing {}", entry.factory.name);
+            let mut instance = (entry.factory.make)(e);
+
+            let params = &mut instance.common().params;
+            *params = Params::new(entry.factory.params);
+            for error in self.params.apply(entry.factory.name, params) {
+                println!("Parameter config: {error}");
+            }
+
+            entry.instance = Some(instance);
+        }
+        entry.instance.as_mut().unwrap().as_mut()
+    }
+
+    // Set a parameter from text: `radius` of the example at `index`, or of any example as `ex02.radius`.
+    // Like `--param`, this isn't saved. Gives the parameter's description, e.g. for printing.
+    pub fn set_param(
+        &mut self,
+        index: usize,
+        name: &str,
+        value: &str,
+        e: &ExampleData,
+    ) -> Result<String, String> {
+        let (index, name) = match name.split_once('.') {
+            Some((namespace, name)) => (
+                self.find(namespace)
+                    .ok_or_else(|| format!("no example \"{namespace}\""))?,
+                name,
+            ),
+            None => (index, name),
+        };
+        let label = self.name(index);
+        let params = &mut self.get(index, e).common().params;
+        params.load(name, value)?;
+        Ok(format!("{label}.{}", params.describe(name)))
+    }
+
+    // Why the example stopped rendering, if it did
+    pub fn fault(&self, index: usize) -> Option<&str> {
+        self.entries[index].fault.as_deref()
+    }
+
+    // Render the example with `render` (e.g. through the x-ray lens), or the placeholder if it faulted.
+    // Errors and panics during `render` fault the example.
+    pub fn render(
+        &mut self,
+        index: usize,
+        e: &ExampleData,
+        target: &TextureView,
+        render: impl FnOnce(&mut dyn Example, &ExampleData, &TextureView),
+    ) {
+        if self.entries[index].fault.is_none() {
+            let ex = self.get(index, e);
+            let frame = ex.common().frame;
+            if let Err(err) = fault::capture(&e.device, || render(ex, e, target)) {
+                let entry = &mut self.entries[index];
+                println!("{} faulted in frame {frame}: {err}", entry.factory.name);
+                entry.fault = Some(err);
+            }
+        }
+
+        if self.entries[index].fault.is_some() {
+            self.placeholder
+                .get_or_insert_with(|| Placeholder::new(e))
+                .render(e, target);
+        }
+    }
+
+    // Construct the example again, which also forgets a fault
+    pub fn reset(&mut self, index: usize, e: &ExampleData) {
+        let entry = &mut self.entries[index];
+        println!("Resetting {}", entry.factory.name);
+        if let Some(instance) = entry.instance.as_mut() {
+            instance.on_exit();
+            self.params
+                .update(entry.factory.name, &instance.common().params);
+        }
+        entry.instance = None;
+        entry.fault = None;
+
+        if self.active.contains(&index) {
+            self.get(index, e).on_enter(e);
+        }
+    }
+
+    // Recompile the shaders of every constructed example (not just the active ones, the others
+    // would be stale when shown again). Faulted examples get another try, unless this fails too.
+    pub fn recompile_shaders(&mut self, device: &Device) {
+        for entry in &mut self.entries {
+            let Some(instance) = entry.instance.as_mut() else {
+                continue;
+            };
+            let result = fault::capture(device, || {
+                let common = instance.common();
+                common.recreate_shader(device);
+                common.dirty = true;
+            });
+            match (result, entry.fault.is_some()) {
+                (Err(err), _) => {
+                    println!("{} shader error: {err}", entry.factory.name);
+                    entry.fault = Some(err);
+                }
+                (Ok(()), true) => {
+                    println!("{} shaders changed, trying again", entry.factory.name);
+                    entry.fault = None;
+                }
+                (Ok(()), false) => {}
+            }
+        }
+
+        if let Some(placeholder) = self.placeholder.as_mut() {
+            let common = placeholder.common();
+            common.recreate_shader(device);
+            common.dirty = true;
+        }
+    }
+
+    // Set which examples are on screen, entering and exiting examples as needed
+    pub fn set_active(&mut self, active: &[usize], e: &ExampleData) {
+        let previous = std::mem::take(&mut self.active);
+
+        for &index in previous.iter().filter(|i| !active.contains(i)) {
+            let entry = &mut self.entries[index];
+            if let Some(instance) = entry.instance.as_mut() {
+                instance.on_exit();
+                self.params
+                    .update(entry.factory.name, &instance.common().params);
+            }
+            if entry.factory.unload == Unload::OnExit {
+                println!("Unloading {}", entry.factory.name);
+                entry.instance = None;
+            }
+        }
+        // Does nothing unless a parameter changed
+        if let Some(path) = self.params_path.as_ref() {
+            self.params.save(path);
+        }
+
+        for &index in active.iter().filter(|i| !previous.contains(i)) {
+            self.get(index, e).on_enter(e);
+        }
+
+        self.active = active.to_vec();
+    }
+}
@@ -0,0 +1,468 @@
+/*
+A local control socket, such that scripts and tests can drive a running zoo:
+`cargo run -- --listen /tmp/zoo.sock` listens on a Unix domain socket,
+`cargo run -- --listen 127.0.0.1:7878` on TCP (localhost only).
+`cargo run -- headless --listen /tmp/zoo.sock` does the same without a window, see `headless.rs`.
+
+One JSON object per line, every request gets one response line:
+
+    > {"cmd": "example", "name": "ex07"}
+    < {"ok": true}
+    > {"cmd": "click", "x": 200, "y": 150}
+    < {"ok": true}
+    > {"cmd": "frame"}
+    < {"ok": true, "width": 800, "height": 600, "png": "iVBORw0KGgo..."}
+    > {"cmd": "set", "name": "radius", "value": 80}
+    < {"ok": true, "param": "ex07.radius = 80 (2 to 500, step 5) Brush radius in pixels"}
+    > {"cmd": "frame-log"}
+    < {"ok": true, "log": "ex07 frame 3\n  ex07-ce\n  ..."}
+    > {"cmd": "example", "name": "ex99"}
+    < {"ok": false, "error": "no example \"ex99\""}
+
+`cargo run --bin zoo-client` is a small client, see `src/bin/zoo-client.rs`.
+
+Every connection gets a thread, which hands requests to the runner and waits for the answer.
+The runner answers them between frames, so a request sees the zoo as it is on screen.
+
+Things we learned:
+    - A Unix socket's file stays behind when the process exits, and binding to it again fails.
+        So an old socket file is removed first (but nothing which isn't a socket).
+ */
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::mpsc::{Receiver, Sender},
+};
+
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use wgpu::{Extent3d, TextureFormat};
+
+use crate::{
+    debug,
+    input::{InputEvent, Modifiers, MouseButton, Position, ScrollDelta},
+    registry::Registry,
+    util, ExampleData,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Button {
+    #[default]
+    Left,
+    Right,
+    Middle,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum Request {
+    // By name or number, like `--example`
+    Example {
+        name: String,
+    },
+    // A parameter of the example, or of any example as `ex02.radius`.
+    // The value is a string (e.g. an option's name), a number or a bool.
+    Set {
+        name: String,
+        value: Value,
+    },
+    // Move the cursor, in window pixels
+    Cursor {
+        x: f32,
+        y: f32,
+    },
+    // Move the cursor there, press and release a button.
+    // With `pressed` only press or release, e.g. to drag.
+    Click {
+        x: f32,
+        y: f32,
+        #[serde(default)]
+        button: Button,
+        pressed: Option<bool>,
+    },
+    // At the cursor, lines up (positive) or down
+    Scroll {
+        lines: f32,
+    },
+    // Pause, then let this many frames of 1/60 s happen.
+    // Answered once they have.
+    Step {
+        #[serde(default = "one")]
+        frames: u32,
+    },
+    // The next frame, as a PNG
+    Frame,
+    // The debug groups and markers of the last frame rendered, see `debug.rs`
+    #[serde(rename = "frame-log")]
+    FrameLog,
+    // The examples, which one is shown, its parameters, ...
+    Status,
+    Quit,
+}
+
+fn one() -> u32 {
+    1
+}
+
+// A parameter value as `Params::load` reads it
+pub fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+impl Request {
+    // Cursor, click and scroll requests as input events, given where the cursor was (window pixels).
+    // Gives where the cursor is after, and the events.
+    pub fn input(
+        &self,
+        mouse: [f32; 2],
+        size: [f32; 2],
+        scale_factor: f32,
+    ) -> Option<([f32; 2], Vec<InputEvent>)> {
+        let position = |mouse| Position::new(mouse, size, scale_factor);
+        match *self {
+            Request::Cursor { x, y } => Some((
+                [x, y],
+                vec![InputEvent::CursorMoved {
+                    position: position([x, y]),
+                }],
+            )),
+            Request::Click {
+                x,
+                y,
+                button,
+                pressed,
+            } => {
+                let position = position([x, y]);
+                let button = |pressed| InputEvent::Button {
+                    button: match button {
+                        Button::Left => MouseButton::Left,
+                        Button::Right => MouseButton::Right,
+                        Button::Middle => MouseButton::Middle,
+                    },
+                    pressed,
+                    position,
+                    modifiers: Modifiers::default(),
+                };
+
+                let mut events = vec![InputEvent::CursorMoved { position }];
+                match pressed {
+                    Some(pressed) => events.push(button(pressed)),
+                    None => events.extend([button(true), button(false)]),
+                }
+                Some(([x, y], events))
+            }
+            Request::Scroll { lines } => Some((
+                mouse,
+                vec![InputEvent::Scroll {
+                    delta: ScrollDelta::Lines([0., lines]),
+                    position: position(mouse),
+                    modifiers: Modifiers::default(),
+                }],
+            )),
+            _ => None,
+        }
+    }
+}
+
+// The answer to a `frame` request, from `read_texture`'s pixels
+pub fn frame(size: Extent3d, format: TextureFormat, bytes: &[u8]) -> Result<Value, String> {
+    let png = util::encode_png(size, format, bytes)?;
+    Ok(json!({
+        "width": size.width,
+        "height": size.height,
+        "png": base64::engine::general_purpose::STANDARD.encode(png),
+    }))
+}
+
+// What `debug` logged of the last frame, once: It's taken
+pub fn frame_log() -> Value {
+    json!({ "log": debug::format_frame(&debug::take_frame()) })
+}
+
+// The answer to a `status` request
+pub fn status(
+    examples: &mut Registry,
+    shown: &[usize],
+    index: usize,
+    e: &ExampleData,
+    paused: bool,
+) -> Value {
+    let list: Vec<_> = (0..examples.len())
+        .map(|i| {
+            json!({
+                "name": examples.name(i),
+                "shown": shown.contains(&i),
+                "fault": examples.fault(i),
+            })
+        })
+        .collect();
+    let size = e.extent_3d();
+
+    let common = examples.get(index, e).common();
+    let params: serde_json::Map<_, _> = common
+        .params
+        .values()
+        .map(|(name, value)| (name.to_string(), value.into()))
+        .collect();
+    json!({
+        "example": common.label,
+        "frame": common.frame,
+        "time": common.time.as_secs_f32(),
+        "paused": paused,
+        "width": size.width,
+        "height": size.height,
+        "params": params,
+        "examples": list,
+    })
+}
+
+// `{"ok": true}` with the result's fields, or `{"ok": false, "error": ...}`
+pub fn response(result: Result<Value, String>) -> String {
+    let response = match result {
+        Ok(Value::Object(mut fields)) => {
+            fields.insert("ok".to_string(), true.into());
+            Value::Object(fields)
+        }
+        Ok(Value::Null) => json!({ "ok": true }),
+        Ok(value) => json!({ "ok": true, "value": value }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    };
+    response.to_string()
+}
+
+// A request for the runner to answer
+pub struct Pending {
+    pub request: Request,
+    reply: Sender<Result<Value, String>>,
+}
+
+impl Pending {
+    pub fn reply(self, result: Result<Value, String>) {
+        // The client may have hung up, nothing to do then
+        self.reply.send(result).ok();
+    }
+}
+
+pub struct Server {
+    requests: Receiver<Pending>,
+}
+
+impl Server {
+    // A TCP address (localhost only), or else the path of a Unix socket
+    pub fn listen(address: &str) -> io::Result<Self> {
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        if let Ok(address) = address.parse::<SocketAddr>() {
+            if !address.ip().is_loopback() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "only localhost, e.g. 127.0.0.1:7878",
+                ));
+            }
+            let listener = TcpListener::bind(address)?;
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Ok(reader) = stream.try_clone() {
+                        connection(reader, stream, tx.clone());
+                    }
+                }
+            });
+        } else {
+            #[cfg(unix)]
+            {
+                use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+
+                // Left behind by an earlier run
+                if std::fs::metadata(address).is_ok_and(|m| m.file_type().is_socket()) {
+                    std::fs::remove_file(address)?;
+                }
+                let listener = UnixListener::bind(address)?;
+                std::thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        if let Ok(reader) = stream.try_clone() {
+                            connection(reader, stream, tx.clone());
+                        }
+                    }
+                });
+            }
+            #[cfg(not(unix))]
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "no Unix sockets here, use e.g. 127.0.0.1:7878",
+            ));
+        }
+
+        Ok(Self { requests: rx })
+    }
+
+    // A request, if one is waiting
+    pub fn try_next(&self) -> Option<Pending> {
+        self.requests.try_recv().ok()
+    }
+
+    // Wait for a request
+    pub fn next(&self) -> Option<Pending> {
+        self.requests.recv().ok()
+    }
+}
+
+// Read requests, wait for the runner's answers and write them back, on a thread
+fn connection(
+    reader: impl Read + Send + 'static,
+    mut writer: impl Write + Send + 'static,
+    requests: Sender<Pending>,
+) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let result = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => {
+                    let (tx, rx) = std::sync::mpsc::channel();
+                    if requests.send(Pending { request, reply: tx }).is_err() {
+                        break;
+                    }
+                    // Dropped without an answer, e.g. when quitting
+                    rx.recv()
+                        .unwrap_or_else(|_| Err("no answer from the runner".to_string()))
+                }
+                Err(err) => Err(format!("bad request: {err}")),
+            };
+            if writeln!(writer, "{}", response(result)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_requests() {
+        let parse = |line| serde_json::from_str::<Request>(line).map_err(|e| e.to_string());
+
+        assert_eq!(
+            parse(r#"{"cmd": "example", "name": "ex07"}"#),
+            Ok(Request::Example {
+                name: "ex07".into()
+            })
+        );
+        assert_eq!(
+            parse(r#"{"cmd": "click", "x": 10, "y": 20.5}"#),
+            Ok(Request::Click {
+                x: 10.,
+                y: 20.5,
+                button: Button::Left,
+                pressed: None
+            })
+        );
+        assert_eq!(parse(r#"{"cmd": "step"}"#), Ok(Request::Step { frames: 1 }));
+        assert_eq!(parse(r#"{"cmd": "frame"}"#), Ok(Request::Frame));
+        assert_eq!(parse(r#"{"cmd": "frame-log"}"#), Ok(Request::FrameLog));
+
+        assert!(parse(r#"{"cmd": "dance"}"#).is_err());
+        assert!(parse(r#"{"cmd": "cursor", "x": 1}"#).is_err());
+        assert!(parse("example 7").is_err());
+    }
+
+    #[test]
+    fn values_as_text() {
+        assert_eq!(value_text(&json!("cyan")), "cyan");
+        assert_eq!(value_text(&json!(1.5)), "1.5");
+        assert_eq!(value_text(&json!(40)), "40");
+        assert_eq!(value_text(&json!(true)), "true");
+    }
+
+    #[test]
+    fn click_events() {
+        let click = Request::Click {
+            x: 400.,
+            y: 300.,
+            button: Button::Right,
+            pressed: None,
+        };
+        let (mouse, events) = click.input([0., 0.], [800., 600.], 1.).unwrap();
+        assert_eq!(mouse, [400., 300.]);
+
+        let buttons: Vec<_> = events
+            .iter()
+            .filter_map(|event| match *event {
+                InputEvent::Button {
+                    button, pressed, ..
+                } => Some((button, pressed)),
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(events[0], InputEvent::CursorMoved { .. }));
+        assert_eq!(
+            buttons,
+            [(MouseButton::Right, true), (MouseButton::Right, false)]
+        );
+        assert_eq!(events[1].position().unwrap().clip, [0., 0.]);
+
+        // Scrolls where the cursor is
+        let (mouse, events) = Request::Scroll { lines: 1. }
+            .input([400., 300.], [800., 600.], 1.)
+            .unwrap();
+        assert_eq!(mouse, [400., 300.]);
+        assert_eq!(events[0].scroll_up(), Some(true));
+
+        assert_eq!(Request::Frame.input(mouse, [800., 600.], 1.), None);
+    }
+
+    #[test]
+    fn responses() {
+        assert_eq!(response(Ok(Value::Null)), r#"{"ok":true}"#);
+        assert_eq!(
+            response(Ok(json!({ "frame": 3 }))),
+            r#"{"frame":3,"ok":true}"#
+        );
+        assert_eq!(
+            response(Err("no example \"ex12\"".into())),
+            r#"{"error":"no example \"ex12\"","ok":false}"#
+        );
+    }
+
+    // The socket and the threads, with the test as the runner
+    #[cfg(unix)]
+    #[test]
+    fn round_trip() {
+        use std::os::unix::net::UnixStream;
+
+        let path = std::env::temp_dir().join(format!("zoo-test-{}.sock", std::process::id()));
+        let server = Server::listen(path.to_str().unwrap()).unwrap();
+
+        let runner = std::thread::spawn(move || {
+            let pending = server.next().unwrap();
+            assert_eq!(pending.request, Request::Status);
+            pending.reply(Ok(json!({ "example": "ex02" })));
+        });
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+        // Answered by the connection, the runner never sees it
+        writeln!(stream, "not json").unwrap();
+        assert!(lines.next().unwrap().unwrap().contains(r#""ok":false"#));
+
+        writeln!(stream, r#"{{"cmd": "status"}}"#).unwrap();
+        assert_eq!(
+            lines.next().unwrap().unwrap(),
+            r#"{"example":"ex02","ok":true}"#
+        );
+
+        runner.join().unwrap();
+        std::fs::remove_file(&path).ok();
+    }
+}
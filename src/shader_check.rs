@@ -0,0 +1,270 @@
+/*
+Checks every shader in `src` (the `.wgsl` files) under `cargo test`, without a GPU, with naga (what wgpu uses to compile them):
+    - it parses and validates, with the capabilities the features the zoo requests give (`WANTED_FEATURES`)
+    - it translates to SPIR-V (Vulkan), GLSL ES (GL), HLSL (DX12) and MSL (Metal)
+    - the entry points the Rust code names (`entry_point: "vs"`) exist in the shaders it loads
+    - it's loaded somewhere
+
+How naga is set up for each backend, and what we learned doing so, is in `translate.rs`.
+ */
+use std::path::{Path, PathBuf};
+
+use naga::{valid::ModuleInfo, Module};
+
+use crate::{translate, OPTIONAL_FEATURES, WANTED_FEATURES};
+
+// Shaders a backend can't translate, and why. These examples fault on that backend.
+const UNSUPPORTED: &[(&str, &str, &str)] = &[(
+    "ex08.wgsl",
+    "glsl",
+    "GLSL ES has no binding arrays (and GL no TEXTURE_BINDING_ARRAY)",
+)];
+
+// Work in progress the checks below find broken, and why. Its failures are expected until it's done.
+const KNOWN_BROKEN: &[(&str, &str)] = &[
+    (
+        "example_todo_compute.rs",
+        "the compute example to be, not a module yet, loads ex04-compute.wgsl which doesn't exist",
+    ),
+    (
+        "ex-todo-compute.wgsl",
+        "the compute example's shader to be, nothing loads it yet",
+    ),
+];
+
+fn src_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src")
+}
+
+// The files in `src` with this extension, by name
+fn files(extension: &str) -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = std::fs::read_dir(src_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == extension))
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            (name, std::fs::read_to_string(&path).unwrap())
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn validate(name: &str, source: &str) -> Result<(Module, ModuleInfo), String> {
+    translate::validate(name, source, WANTED_FEATURES | OPTIONAL_FEATURES)
+}
+
+// Translate for each backend, errors by backend
+fn translate(module: &Module, info: &ModuleInfo) -> Vec<(&'static str, String)> {
+    translate::translate(module, info)
+        .into_iter()
+        .filter_map(|translation| {
+            let err = translation.result.err()?;
+            Some(match translation.backend {
+                "glsl" => ("glsl", format!("{}: {err}", translation.name)),
+                backend => (backend, err),
+            })
+        })
+        .collect()
+}
+
+// The string literals in Rust source, roughly: escaped quotes aren't handled
+fn literals(source: &str) -> impl Iterator<Item = &str> {
+    source.split('"').skip(1).step_by(2)
+}
+
+// The shader files a Rust file loads, and the entry points it names.
+// Not counting its tests (the `mod tests` at the bottom), whose fixtures may look like either.
+fn shader_uses(source: &str) -> (Vec<&str>, Vec<&str>) {
+    let source = source
+        .split("#[cfg(test)]\nmod tests")
+        .next()
+        .unwrap_or_default();
+    let shaders = literals(source)
+        .filter(|s| s.ends_with(".wgsl") && !s.contains([' ', '/']))
+        .collect();
+    let entry_points = source
+        .split("entry_point: \"")
+        .skip(1)
+        .filter_map(|rest| rest.split('"').next())
+        .collect();
+    (shaders, entry_points)
+}
+
+// The Rust files, except for the shader tests
+fn rust_files() -> Vec<(String, String)> {
+    files("rs")
+        .into_iter()
+        .filter(|(name, _)| name != "shader_check.rs" && name != "shader_test.rs")
+        .collect()
+}
+
+// The modules main.rs declares, and main.rs. Other files aren't compiled, so can't load a shader.
+fn modules() -> Vec<String> {
+    let main = std::fs::read_to_string(src_dir().join("main.rs")).unwrap();
+    main.lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches("pub ");
+            Some(format!(
+                "{}.rs",
+                line.strip_prefix("mod ")?.strip_suffix(';')?
+            ))
+        })
+        .chain(["main.rs".to_string()])
+        .collect()
+}
+
+// Shaders which don't exist or lack the entry points the Rust code names, by Rust file
+fn entry_point_errors() -> Vec<(String, String)> {
+    let shaders = files("wgsl");
+    let mut errors = vec![];
+    for (rust_file, rust_source) in rust_files() {
+        let (used, entry_points) = shader_uses(&rust_source);
+        if entry_points.is_empty() {
+            continue;
+        }
+        let mut modules = vec![];
+        for shader in &used {
+            match shaders.iter().find(|(name, _)| name == shader) {
+                // Invalid ones fail `shaders_validate_and_translate`
+                Some((name, source)) => modules.extend(validate(name, source).map(|v| v.0)),
+                None => errors.push((
+                    rust_file.clone(),
+                    format!("{rust_file} loads {shader}, which doesn't exist"),
+                )),
+            }
+        }
+        for entry_point in entry_points {
+            let found = modules
+                .iter()
+                .any(|module| module.entry_points.iter().any(|ep| ep.name == entry_point));
+            if !found {
+                errors.push((
+                    rust_file.clone(),
+                    format!(
+                        "{rust_file} uses entry point \"{entry_point}\", which none of {used:?} has"
+                    ),
+                ));
+            }
+        }
+    }
+    errors
+}
+
+// The shaders no module loads
+fn unused_shaders() -> Vec<String> {
+    let modules = modules();
+    let rust_files: Vec<_> = rust_files()
+        .into_iter()
+        .filter(|(name, _)| modules.contains(name))
+        .collect();
+    files("wgsl")
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| {
+            !rust_files
+                .iter()
+                .any(|(_, source)| shader_uses(source).0.contains(&name.as_str()))
+        })
+        .collect()
+}
+
+fn known_broken(file: &str) -> bool {
+    KNOWN_BROKEN.iter().any(|&(broken, _)| broken == file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shaders_validate_and_translate() {
+        let mut errors = vec![];
+        for (name, source) in files("wgsl") {
+            let (module, info) = match validate(&name, &source) {
+                Ok(valid) => valid,
+                Err(err) => {
+                    errors.push(err);
+                    continue;
+                }
+            };
+            for (backend, err) in translate(&module, &info) {
+                let expected = UNSUPPORTED
+                    .iter()
+                    .any(|&(file, b, _)| (file, b) == (name.as_str(), backend));
+                if !expected {
+                    errors.push(format!("{name} to {backend}: {err}"));
+                }
+            }
+        }
+        assert!(errors.is_empty(), "{}", errors.join("\n"));
+    }
+
+    #[test]
+    fn unsupported_is_up_to_date() {
+        // Such that the list shrinks when naga learns something
+        for &(file, backend, why) in UNSUPPORTED {
+            let source = std::fs::read_to_string(src_dir().join(file)).unwrap();
+            let (module, info) = validate(file, &source).unwrap();
+            assert!(
+                translate(&module, &info).iter().any(|&(b, _)| b == backend),
+                "{file} translates to {backend} now, despite {why}"
+            );
+        }
+    }
+
+    #[test]
+    fn entry_points_exist() {
+        let errors: Vec<String> = entry_point_errors()
+            .into_iter()
+            .filter(|(file, _)| !known_broken(file))
+            .map(|(_, err)| err)
+            .collect();
+        assert!(errors.is_empty(), "{}", errors.join("\n"));
+    }
+
+    #[test]
+    fn shaders_are_used() {
+        let unused: Vec<String> = unused_shaders()
+            .into_iter()
+            .filter(|name| !known_broken(name))
+            .collect();
+        assert!(unused.is_empty(), "No module loads {unused:?}");
+    }
+
+    #[test]
+    fn known_broken_is_up_to_date() {
+        // Such that the list shrinks when the work is done
+        let errors = entry_point_errors();
+        let unused = unused_shaders();
+        for &(file, why) in KNOWN_BROKEN {
+            assert!(
+                errors.iter().any(|(f, _)| f == file) || unused.iter().any(|f| f == file),
+                "{file} passes now, despite {why}"
+            );
+        }
+    }
+
+    #[test]
+    fn finds_shader_uses() {
+        let source = r#"
+            let shader_source = "ex04.wgsl";
+            // See "src/ex04.wgsl", or "a.wgsl b"
+            VertexState { entry_point: "vs_show", .. }
+        "#;
+        assert_eq!(shader_uses(source), (vec!["ex04.wgsl"], vec!["vs_show"]));
+
+        // Only the tests are left out, not what comes after another `#[cfg(test)]`
+        let source = concat!(
+            "#[cfg(test)]\n",
+            "mod shader_check;\n",
+            "let shader_source = \"ex04.wgsl\";\n",
+            "#[cfg(test)]\n",
+            "mod tests {\n",
+            "    const TRACE: &str = r#\"data: \"data1.wgsl\", entry_point: \"vs\"\"#;\n",
+            "}\n",
+        );
+        assert_eq!(shader_uses(source), (vec!["ex04.wgsl"], vec![]));
+    }
+}
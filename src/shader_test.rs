@@ -0,0 +1,492 @@
+/*
+Unit tests for WGSL functions, run on the GPU by `cargo test`.
+
+    ShaderFn::new("ex02.wgsl", "rotation").check(&[((0.,), [[1., 0.], [0., 1.]])], 1e-6);
+    ShaderFn::new("ex03.wgsl", "framebuffer_to_clip").check_with(&inputs, |&(p, size)| reference(p, size), 1e-6);
+
+The function's file gets a compute entry point appended which, for every row of inputs,
+loads the arguments from a storage buffer, calls the function and stores the result in another.
+Arguments are tuples of `GpuValue`s: f32, i32, u32, arrays of those for vectors,
+and arrays of arrays for matrices (columns, like WGSL and glam).
+
+Runs on a fallback (CPU) adapter, e.g. Mesa's llvmpipe, such that it works without a GPU.
+`WGPU_BACKEND` picks the backends to look at, all by default.
+
+Things we learned:
+    - Passing everything as `array<u32>` and `bitcast`ing sidesteps WGSL's layout rules,
+        e.g. a `vec3<f32>` in a storage array takes 16 bytes.
+    - A compute pipeline with `layout: None` only gets the bindings its entry point uses,
+        so the file's other globals (uniforms, push constants) don't need to be bound.
+    - The module still has to validate as a whole, e.g. ex03's push constant needs `PUSH_CONSTANTS`.
+    - A matrix can be constructed from its scalars, column by column.
+ */
+use std::{borrow::Cow, fmt::Debug, sync::OnceLock};
+
+use bytemuck::Pod;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Backends, BindGroupDescriptor, BindGroupEntry, BufferDescriptor, BufferUsages,
+    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor, Device,
+    ErrorFilter, Features, Maintain, MapMode, Queue, ShaderModuleDescriptor,
+};
+
+use crate::util::read_shader;
+
+const WORKGROUP_SIZE: usize = 64;
+
+// A scalar which can be passed in a `u32`
+pub trait Scalar: Pod + Debug + Into<f64> {
+    const WGSL: &'static str;
+}
+
+impl Scalar for f32 {
+    const WGSL: &'static str = "f32";
+}
+
+impl Scalar for i32 {
+    const WGSL: &'static str = "i32";
+}
+
+impl Scalar for u32 {
+    const WGSL: &'static str = "u32";
+}
+
+// An argument or a result
+pub trait GpuValue: Copy + Debug {
+    // E.g. "vec2<f32>"
+    fn wgsl() -> String;
+    fn scalar() -> &'static str;
+    // None for a scalar, [n] for a vector, [columns, rows] for a matrix
+    fn shape() -> Vec<usize>;
+    fn words(&self) -> Vec<u32>;
+    fn from_words(words: &[u32]) -> Self;
+    // For comparing
+    fn components(&self) -> Vec<f64>;
+}
+
+impl<S: Scalar> GpuValue for S {
+    fn wgsl() -> String {
+        S::WGSL.to_string()
+    }
+    fn scalar() -> &'static str {
+        S::WGSL
+    }
+    fn shape() -> Vec<usize> {
+        vec![]
+    }
+    fn words(&self) -> Vec<u32> {
+        vec![bytemuck::cast(*self)]
+    }
+    fn from_words(words: &[u32]) -> Self {
+        bytemuck::cast(words[0])
+    }
+    fn components(&self) -> Vec<f64> {
+        vec![(*self).into()]
+    }
+}
+
+impl<S: Scalar, const N: usize> GpuValue for [S; N] {
+    fn wgsl() -> String {
+        format!("vec{N}<{}>", S::WGSL)
+    }
+    fn scalar() -> &'static str {
+        S::WGSL
+    }
+    fn shape() -> Vec<usize> {
+        vec![N]
+    }
+    fn words(&self) -> Vec<u32> {
+        self.iter().map(|&s| bytemuck::cast(s)).collect()
+    }
+    fn from_words(words: &[u32]) -> Self {
+        std::array::from_fn(|i| bytemuck::cast(words[i]))
+    }
+    fn components(&self) -> Vec<f64> {
+        self.iter().map(|&s| s.into()).collect()
+    }
+}
+
+impl<const C: usize, const R: usize> GpuValue for [[f32; R]; C] {
+    fn wgsl() -> String {
+        format!("mat{C}x{R}<f32>")
+    }
+    fn scalar() -> &'static str {
+        "f32"
+    }
+    fn shape() -> Vec<usize> {
+        vec![C, R]
+    }
+    fn words(&self) -> Vec<u32> {
+        self.iter().flatten().map(|s| s.to_bits()).collect()
+    }
+    fn from_words(words: &[u32]) -> Self {
+        std::array::from_fn(|c| std::array::from_fn(|r| f32::from_bits(words[c * R + r])))
+    }
+    fn components(&self) -> Vec<f64> {
+        self.iter().flatten().map(|&s| s.into()).collect()
+    }
+}
+
+fn word_count<V: GpuValue>() -> usize {
+    V::shape().iter().product()
+}
+
+// Component `i` of `expr`, in the order of `GpuValue::words`
+fn component<V: GpuValue>(expr: &str, i: usize) -> String {
+    match V::shape()[..] {
+        [] => expr.to_string(),
+        [_] => format!("{expr}[{i}]"),
+        [_, rows] => format!("{expr}[{}][{}]", i / rows, i % rows),
+        _ => unreachable!(),
+    }
+}
+
+// Loads a `V` from the input words, starting at `offset` in the row
+fn load<V: GpuValue>(offset: usize) -> String {
+    let scalars: Vec<String> = (offset..offset + word_count::<V>())
+        .map(|i| match V::scalar() {
+            "u32" => format!("zoo_test_in[row_in + {i}u]"),
+            scalar => format!("bitcast<{scalar}>(zoo_test_in[row_in + {i}u])"),
+        })
+        .collect();
+    format!("{}({})", V::wgsl(), scalars.join(", "))
+}
+
+// A function's arguments
+pub trait Args: Debug {
+    // WGSL expressions loading each argument
+    fn loads() -> Vec<String>;
+    // Per row
+    fn word_count() -> usize;
+    fn words(&self) -> Vec<u32>;
+}
+
+macro_rules! impl_args {
+    ($($name:ident),+) => {
+        impl<$($name: GpuValue),+> Args for ($($name,)+) {
+            // The last argument's offset isn't needed
+            #[allow(unused_assignments)]
+            fn loads() -> Vec<String> {
+                let mut offset = 0;
+                vec![$({
+                    let load = load::<$name>(offset);
+                    offset += word_count::<$name>();
+                    load
+                }),+]
+            }
+
+            fn word_count() -> usize {
+                0 $(+ word_count::<$name>())+
+            }
+
+            #[allow(non_snake_case)]
+            fn words(&self) -> Vec<u32> {
+                let ($($name,)+) = self;
+                let mut words = vec![];
+                $(words.extend($name.words());)+
+                words
+            }
+        }
+    };
+}
+
+impl_args!(A);
+impl_args!(A, B);
+impl_args!(A, B, C);
+impl_args!(A, B, C, D);
+
+// Calls `function` once per row of inputs
+fn entry_point<A: Args, R: GpuValue>(function: &str, rows: usize) -> String {
+    let stores: String = (0..word_count::<R>())
+        .map(|i| {
+            let value = component::<R>("result", i);
+            match R::scalar() {
+                "u32" => format!("    zoo_test_out[row_out + {i}u] = {value};\n"),
+                _ => format!("    zoo_test_out[row_out + {i}u] = bitcast<u32>({value});\n"),
+            }
+        })
+        .collect();
+    format!(
+        "
+@group(0) @binding(0) var<storage, read> zoo_test_in: array<u32>;
+@group(0) @binding(1) var<storage, read_write> zoo_test_out: array<u32>;
+
+@compute @workgroup_size({WORKGROUP_SIZE})
+fn zoo_test_main(@builtin(global_invocation_id) id: vec3<u32>) {{
+    if (id.x >= {rows}u) {{
+        return;
+    }}
+    let row_in = id.x * {in_words}u;
+    let row_out = id.x * {out_words}u;
+    let result: {result} = {function}({args});
+{stores}}}
+",
+        in_words = A::word_count(),
+        out_words = word_count::<R>(),
+        result = R::wgsl(),
+        args = A::loads().join(", "),
+    )
+}
+
+// Shared by all tests, which run on several threads
+fn gpu() -> &'static (Device, Queue) {
+    static GPU: OnceLock<(Device, Queue)> = OnceLock::new();
+    GPU.get_or_init(|| {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(Backends::all()),
+            ..Default::default()
+        });
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            force_fallback_adapter: true,
+            ..Default::default()
+        }))
+        .expect("no fallback adapter for shader tests, e.g. install Mesa's llvmpipe or lavapipe");
+
+        pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: "shader-test-device".into(),
+                features: adapter.features() & Features::PUSH_CONSTANTS,
+                limits: adapter.limits(),
+            },
+            None,
+        ))
+        .unwrap()
+    })
+}
+
+// A function in a WGSL file
+pub struct ShaderFn {
+    source: String,
+    function: &'static str,
+}
+
+impl ShaderFn {
+    // E.g. `ShaderFn::new("ex02.wgsl", "rotation")`
+    pub fn new(file: &str, function: &'static str) -> Self {
+        Self::from_source(read_shader(file), function)
+    }
+
+    pub fn from_source(source: String, function: &'static str) -> Self {
+        Self { source, function }
+    }
+
+    // The function's result for each row of arguments
+    pub fn run<A: Args, R: GpuValue>(&self, inputs: &[A]) -> Vec<R> {
+        if inputs.is_empty() {
+            return vec![];
+        }
+        let (device, queue) = gpu();
+        let source = self.source.clone() + &entry_point::<A, R>(self.function, inputs.len());
+
+        device.push_error_scope(ErrorFilter::Validation);
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: "shader-test".into(),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&source)),
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: "shader-test-pipeline".into(),
+            layout: None,
+            module: &module,
+            entry_point: "zoo_test_main",
+        });
+
+        let in_words: Vec<u32> = inputs.iter().flat_map(Args::words).collect();
+        let out_size = (inputs.len() * word_count::<R>() * 4) as u64;
+        let in_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: "shader-test-in".into(),
+            contents: bytemuck::cast_slice(&in_words),
+            usage: BufferUsages::STORAGE,
+        });
+        let out_buf = device.create_buffer(&BufferDescriptor {
+            label: "shader-test-out".into(),
+            size: out_size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let read_buf = device.create_buffer(&BufferDescriptor {
+            label: "shader-test-read".into(),
+            size: out_size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: "shader-test-bg".into(),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: in_buf.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: out_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut ce = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "shader-test-ce".into(),
+        });
+        {
+            let mut cpass = ce.begin_compute_pass(&ComputePassDescriptor {
+                label: "shader-test-cp".into(),
+            });
+            cpass.set_pipeline(&pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch_workgroups(inputs.len().div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        ce.copy_buffer_to_buffer(&out_buf, 0, &read_buf, 0, out_size);
+        queue.submit(std::iter::once(ce.finish()));
+
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            panic!(
+                "{}: {error}\nGenerated entry point:{}",
+                self.function,
+                &source[self.source.len()..]
+            );
+        }
+
+        let slice = read_buf.slice(..);
+        slice.map_async(MapMode::Read, |_| {});
+        device.poll(Maintain::Wait);
+        let words: Vec<u32> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        read_buf.unmap();
+
+        words.chunks(word_count::<R>()).map(R::from_words).collect()
+    }
+
+    // Compare with the expected results, component by component
+    pub fn check<A: Args + Clone, R: GpuValue>(&self, cases: &[(A, R)], tolerance: f64) {
+        let inputs: Vec<A> = cases.iter().map(|(args, _)| args.clone()).collect();
+        let expected: Vec<R> = cases.iter().map(|&(_, result)| result).collect();
+        self.compare(&inputs, &self.run(&inputs), &expected, tolerance);
+    }
+
+    // Compare with a Rust implementation
+    pub fn check_with<A: Args, R: GpuValue>(
+        &self,
+        inputs: &[A],
+        reference: impl Fn(&A) -> R,
+        tolerance: f64,
+    ) {
+        let expected: Vec<R> = inputs.iter().map(reference).collect();
+        self.compare(inputs, &self.run(inputs), &expected, tolerance);
+    }
+
+    fn compare<A: Args, R: GpuValue>(
+        &self,
+        inputs: &[A],
+        actual: &[R],
+        expected: &[R],
+        tolerance: f64,
+    ) {
+        for ((args, actual), expected) in inputs.iter().zip(actual).zip(expected) {
+            let close = actual
+                .components()
+                .iter()
+                .zip(expected.components())
+                .all(|(a, e)| (a - e).abs() <= tolerance);
+            assert!(
+                close,
+                "{}{args:?} gave {actual:?}, expected {expected:?} (within {tolerance})",
+                self.function
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Position;
+
+    #[test]
+    fn passes_types_through() {
+        let swizzle = ShaderFn::from_source(
+            "fn swizzle(v: vec3<f32>, i: i32, m: mat2x3<f32>) -> vec4<f32> {
+                return vec4<f32>(v.zy, f32(i), m[1][2]);
+            }"
+            .to_string(),
+            "swizzle",
+        );
+        swizzle.check(
+            &[
+                (
+                    ([1f32, 2., 3.], -4i32, [[0f32, 0., 0.], [0., 0., 5.]]),
+                    [3f32, 2., -4., 5.],
+                ),
+                (([0f32, 0., 0.], 7i32, [[1f32; 3]; 2]), [0f32, 0., 7., 1.]),
+            ],
+            0.,
+        );
+
+        let bits = ShaderFn::from_source(
+            "fn bits(a: u32, b: vec2<u32>) -> u32 { return a ^ b.y; }".to_string(),
+            "bits",
+        );
+        assert_eq!(
+            bits.run::<_, u32>(&[(0xffff_0000u32, [0u32, 0xffff_ffff])]),
+            [0x0000_ffff]
+        );
+    }
+
+    #[test]
+    fn many_rows() {
+        let double = ShaderFn::from_source(
+            "fn double(x: f32) -> f32 { return x * 2.; }".to_string(),
+            "double",
+        );
+        let inputs: Vec<(f32,)> = (0..1000).map(|i| (i as f32,)).collect();
+        double.check_with(&inputs, |&(x,)| x * 2., 0.);
+    }
+
+    #[test]
+    fn ex02_rotation() {
+        let rotation = ShaderFn::new("ex02.wgsl", "rotation");
+        rotation.check(
+            &[
+                ((0f32,), [[1f32, 0.], [0., 1.]]),
+                // Clockwise, i.e. x goes to -y
+                ((std::f32::consts::FRAC_PI_2,), [[0., -1.], [1., 0.]]),
+            ],
+            1e-6,
+        );
+        let inputs: Vec<(f32,)> = (0..64).map(|i| (i as f32 * 0.1,)).collect();
+        rotation.check_with(
+            &inputs,
+            |&(a,)| glam::Mat2::from_angle(-a).to_cols_array_2d(),
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn ex03_framebuffer_to_clip() {
+        // Agrees with the mouse position examples get
+        let size = [800f32, 600.];
+        let inputs: Vec<([f32; 2], [f32; 2])> =
+            [[0., 0.], [800., 600.], [400., 300.], [12.5, 580.]]
+                .into_iter()
+                .map(|p| (p, size))
+                .collect();
+        ShaderFn::new("ex03.wgsl", "framebuffer_to_clip").check_with(
+            &inputs,
+            |&(p, size)| Position::new(p, size, 1.).clip,
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn ex04_id_color() {
+        // The same hash on the CPU
+        let reference = |&(id,): &(u32,)| {
+            if id == 0 {
+                return [0f32; 3];
+            }
+            let mut h = id.wrapping_mul(747796405).wrapping_add(2891336453);
+            h = ((h >> ((h >> 28) + 4)) ^ h).wrapping_mul(277803737);
+            [h & 255, (h >> 8) & 255, (h >> 16) & 255].map(|c| c as f32 / 255.)
+        };
+        let inputs: Vec<(u32,)> = (0..256).chain([u32::MAX]).map(|id| (id,)).collect();
+        ShaderFn::new("ex04.wgsl", "id_color").check_with(&inputs, reference, 1e-6);
+    }
+}
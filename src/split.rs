@@ -0,0 +1,303 @@
+/*
+Goals:
+    - Run several examples at once, each in a cell of a grid, to compare them side by side
+    - Each example keeps its own time, and gets input when the cursor is over its cell
+
+Things we learned:
+    - Examples clear their whole target (`Operations::default()`), and a clear ignores the viewport.
+        So examples can't share the swapchain texture directly, they each render into a texture the size of their cell.
+        Then one pass draws those into the swapchain, using `set_viewport` to place each one.
+    - Examples size things from `ExampleData::extent_3d` and the mouse, so setting those to the cell's
+        while an example renders is all it takes to fit it into a cell.
+    - In the fragment shader the builtin position is in framebuffer coordinates even with a viewport set,
+        so it can't tell where in the cell we are. Passing uv from the vertex shader can.
+ */
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, CommandEncoderDescriptor, Extent3d, FragmentState, MultisampleState,
+    Operations, PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PushConstantRange,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerDescriptor, ShaderStages, Texture, TextureDescriptor, TextureDimension,
+    TextureUsages, TextureView, TextureViewDescriptor, VertexState,
+};
+
+use crate::{
+    debug,
+    input::{InputEvent, Position},
+    registry::Registry,
+    util::ExampleCommonState,
+    ExampleData,
+};
+
+pub struct SplitScreen {
+    common: ExampleCommonState,
+    render_pipeline: Option<RenderPipeline>,
+    bgl0: BindGroupLayout,
+    sampler: Sampler,
+
+    // Which example each cell shows, by index, row major
+    cells: Vec<usize>,
+    // What each cell renders into
+    textures: Vec<Texture>,
+
+    // While a button is held the cell it was pressed in keeps the input,
+    // such that e.g. dragging out of a cell works
+    captured: Option<usize>,
+}
+
+impl SplitScreen {
+    pub fn new(e: &ExampleData, cells: Vec<usize>) -> Self {
+        let common = ExampleCommonState::new(&e.device, e.swapchain_format, "split.wgsl", "split");
+
+        let bgl0 = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "split-bgl0".into(),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        Self {
+            common,
+            render_pipeline: None,
+            bgl0,
+            sampler: e.device.create_sampler(&SamplerDescriptor::default()),
+            cells,
+            textures: vec![],
+            captured: None,
+        }
+    }
+
+    pub fn common(&mut self) -> &mut ExampleCommonState {
+        &mut self.common
+    }
+
+    // The examples shown, each once
+    pub fn examples(&self) -> Vec<usize> {
+        let mut examples = self.cells.clone();
+        examples.sort_unstable();
+        examples.dedup();
+        examples
+    }
+
+    pub fn set_example(&mut self, cell: usize, example: usize) {
+        self.cells[cell] = example;
+    }
+
+    // Columns and rows, as square as possible
+    fn grid(&self) -> (u32, u32) {
+        let n = self.cells.len() as u32;
+        let columns = (n as f32).sqrt().ceil() as u32;
+        (columns, n.div_ceil(columns))
+    }
+
+    // x, y, width, height in pixels, given the size of the whole target
+    fn cell_rect(&self, cell: usize, size: [f32; 2]) -> [u32; 4] {
+        let (columns, rows) = self.grid();
+        let (w, h) = (size[0] as u32 / columns, size[1] as u32 / rows);
+        let (column, row) = (cell as u32 % columns, cell as u32 / columns);
+        [column * w, row * h, w, h]
+    }
+
+    fn cell_at(&self, mouse: [f32; 2], size: [f32; 2]) -> Option<usize> {
+        (0..self.cells.len()).find(|&cell| {
+            let [x, y, w, h] = self.cell_rect(cell, size).map(|v| v as f32);
+            (x..x + w).contains(&mouse[0]) && (y..y + h).contains(&mouse[1])
+        })
+    }
+
+    // The cell which gets keys and mouse input
+    pub fn focused(&self, mouse: [f32; 2], size: [f32; 2]) -> usize {
+        self.captured
+            .or_else(|| self.cell_at(mouse, size))
+            .unwrap_or(0)
+    }
+
+    pub fn focused_example(&self, mouse: [f32; 2], size: [f32; 2]) -> usize {
+        self.cells[self.focused(mouse, size)]
+    }
+
+    // Send the event to the focused cell's example, with positions relative to the cell
+    pub fn handle_input(&mut self, examples: &mut Registry, e: &ExampleData, event: &InputEvent) {
+        let cell = self.focused(e.mouse, e.viewport);
+        match event {
+            InputEvent::Button { pressed: true, .. } => self.captured = Some(cell),
+            InputEvent::Button { pressed: false, .. } => self.captured = None,
+            _ => {}
+        }
+
+        let event = match event.position() {
+            Some(position) => {
+                let [x, y, w, h] = self.cell_rect(cell, e.viewport).map(|v| v as f32);
+                let window = [position.window[0] - x, position.window[1] - y];
+                let scale_factor = e.scale_factor();
+                event.with_position(Position::new(window, [w, h], scale_factor))
+            }
+            None => *event,
+        };
+
+        examples.get(self.cells[cell], e).handle_input(&event);
+    }
+
+    fn make_render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: "split-rpassd".into(),
+            layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: "split-rpass-pld".into(),
+                bind_group_layouts: &[&self.bgl0],
+                push_constant_ranges: &[PushConstantRange {
+                    stages: ShaderStages::FRAGMENT,
+                    range: 0..4,
+                }],
+            })),
+            vertex: VertexState {
+                module: &self.common.shader_module,
+                entry_point: "vs",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &self.common.shader_module,
+                entry_point: "fs",
+                targets: &[Some(e.swapchain_format.into())],
+            }),
+            primitive: PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // The polygon mode is for the examples in the cells
+                polygon_mode: PolygonMode::Fill,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    // Render each cell's example, then put them all on the target
+    pub fn render(&mut self, examples: &mut Registry, e: &mut ExampleData, target: &TextureView) {
+        if self.common.dirty || self.render_pipeline.is_none() {
+            self.common.dirty = false;
+            self.render_pipeline = Some(self.make_render_pipeline(e));
+        }
+
+        let (size, mouse) = (e.viewport, e.mouse);
+        let focused = self.focused(mouse, size);
+        let rects: Vec<[u32; 4]> = (0..self.cells.len())
+            .map(|cell| self.cell_rect(cell, size))
+            .collect();
+
+        // A cell may have zero size when the window is tiny
+        if rects.iter().any(|&[_, _, w, h]| w == 0 || h == 0) {
+            return;
+        }
+
+        // Handle resize
+        let cell_size = Extent3d {
+            width: rects[0][2],
+            height: rects[0][3],
+            depth_or_array_layers: 1,
+        };
+        if self.textures.len() != self.cells.len()
+            || self.textures.iter().any(|t| t.size() != cell_size)
+        {
+            self.textures = (0..self.cells.len())
+                .map(|_| {
+                    e.device.create_texture(&TextureDescriptor {
+                        label: "split-cell".into(),
+                        size: cell_size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format: e.swapchain_format,
+                        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                })
+                .collect();
+        }
+
+        let views: Vec<TextureView> = self
+            .textures
+            .iter()
+            .map(|t| t.create_view(&TextureViewDescriptor::default()))
+            .collect();
+
+        // Each example thinks its cell is the whole target
+        for (cell, &[x, y, w, h]) in rects.iter().enumerate() {
+            let example = self.cells[cell];
+            let _scope = debug::scope(&format!("cell {cell}: {}", examples.name(example)));
+            e.viewport = [w as f32, h as f32];
+            e.mouse = [mouse[0] - x as f32, mouse[1] - y as f32];
+            examples.render(example, e, &views[cell], |ex, e, view| ex.render(e, view));
+        }
+        e.viewport = size;
+        e.mouse = mouse;
+
+        let bind_groups: Vec<_> = views
+            .iter()
+            .map(|view| {
+                e.device.create_bind_group(&BindGroupDescriptor {
+                    label: "split-bg0".into(),
+                    layout: &self.bgl0,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(view),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "split-ce".into(),
+        });
+
+        {
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "split-rp".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(self.render_pipeline.as_ref().unwrap());
+
+            for (cell, &[x, y, w, h]) in rects.iter().enumerate() {
+                debug::marker(&mut rpass, &format!("cell {cell}"));
+                rpass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0., 1.);
+                rpass.set_bind_group(0, &bind_groups[cell], &[]);
+                rpass.set_push_constants(
+                    ShaderStages::FRAGMENT,
+                    0,
+                    ((cell == focused) as u32).to_le_bytes().as_ref(),
+                );
+                rpass.draw(0..3, 0..1);
+            }
+        }
+
+        e.queue.submit(std::iter::once(ce.finish()));
+    }
+}
@@ -0,0 +1,46 @@
+@group(0)
+@binding(0)
+var t_cell: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var s_sampler: sampler;
+
+// 1 if this cell gets the input
+var<push_constant> focused: u32;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Full screen triangle, draw 3.
+// Full screen here means the viewport, i.e. the cell.
+@vertex
+fn vs(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.position = vec4<f32>(uv * vec2<f32>(2., -2.) + vec2<f32>(-1., 1.), 0., 1.);
+    out.uv = uv;
+
+    return out;
+}
+
+@fragment
+fn fs(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_cell, s_sampler, input.uv);
+
+    // The builtin position is in framebuffer coordinates, not relative to the viewport,
+    // so use the uv to know how far from the cell's edge we are
+    let size = vec2<f32>(textureDimensions(t_cell));
+    let edge = min(input.uv, 1. - input.uv) * size;
+    if (min(edge.x, edge.y) < 2.) {
+        if (focused == 1u) {
+            return vec4<f32>(1., 1., 1., 1.);
+        }
+        return vec4<f32>(0.3, 0.3, 0.3, 1.);
+    }
+
+    return color;
+}
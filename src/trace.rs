@@ -0,0 +1,497 @@
+/*
+Summarize a wgpu API trace, see `cargo run -- --trace` and `cargo run -- trace-summary <dir>`.
+
+A trace is a directory with a `trace.ron` (and the data written to buffers and textures as `.bin` files).
+`trace.ron` is a list of actions, one per line at the top level:
+
+    CreateBuffer(Id(0, 1, Vulkan), (
+        label: Some("ex06-uni-mouse"),
+        ...
+    )),
+    CreateRenderPipeline(
+        id: Id(0, 1, Vulkan),
+        desc: (
+            label: Some("ex06-rp"),
+            ...
+        ),
+        implicit_context: None,
+    ),
+    WriteBuffer(
+        ...
+    ),
+    Submit(1, [ ... ]),
+    Present(Id(0, 1, Vulkan)),
+
+We don't need to understand all of it, just count the top level actions between presents,
+and note the labels of what's created such that per frame churn can be pinned on something.
+Some `Create*` actions are tuples with the descriptor second, others (pipelines, views, shader modules,
+query sets, bundles) are structs with the descriptor in `desc`. Either way the label is one level into the descriptor.
+ */
+use std::{collections::BTreeMap, path::Path};
+
+#[derive(Debug, Default, Clone)]
+pub struct FrameStats {
+    // Every `Create*`, pipelines included
+    pub resources: usize,
+    pub pipelines: usize,
+    pub buffer_writes: usize,
+    pub texture_writes: usize,
+    pub submits: usize,
+    // What was created, as `Kind label`, e.g. `CreateBuffer "ex06-uni-mouse"`
+    pub created: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    // Split on `Present`.
+    // The first frame also has the setup, the last might be incomplete.
+    pub frames: Vec<FrameStats>,
+}
+
+// A `Create*` action spanning several lines, until its parentheses close
+struct Pending {
+    kind: String,
+    depth: i32,
+    // Nesting of the descriptor, once we're in it
+    desc: Option<i32>,
+    label: Option<String>,
+}
+
+// How much a line opens (or closes) parentheses, not counting those in strings
+fn nesting(line: &str) -> i32 {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+pub fn summarize(trace: &str) -> Summary {
+    let mut summary = Summary::default();
+    let mut frame = FrameStats::default();
+    // Looking for the label of what's being created
+    let mut pending: Option<Pending> = None;
+
+    for line in trace.lines() {
+        if let Some(p) = pending.as_mut() {
+            let trimmed = line.trim();
+            if p.desc == Some(p.depth) && p.label.is_none() {
+                if let Some(label) = trimmed.strip_prefix("label: ") {
+                    let label = label.trim_end_matches(',');
+                    p.label = Some(match label.strip_prefix("Some(") {
+                        Some(label) => label.strip_suffix(')').unwrap_or(label).to_string(),
+                        None => "(no label)".to_string(),
+                    });
+                }
+            }
+            // Struct variants, e.g. `CreateRenderPipeline(id: .., desc: (..), ..)`
+            if p.depth == 1 && trimmed.starts_with("desc: (") {
+                p.desc = Some(2);
+            }
+            p.depth += nesting(line);
+            if p.depth <= 0 {
+                let p = pending.take().unwrap();
+                let label = p.label.as_deref().unwrap_or("(no label)");
+                *frame
+                    .created
+                    .entry(format!("{} {label}", p.kind))
+                    .or_default() += 1;
+            }
+            continue;
+        }
+
+        // Top level actions aren't indented
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let action: String = line.chars().take_while(char::is_ascii_alphabetic).collect();
+
+        match action.as_str() {
+            "" => {}
+            "Present" => summary.frames.push(std::mem::take(&mut frame)),
+            "WriteBuffer" => frame.buffer_writes += 1,
+            "WriteTexture" => frame.texture_writes += 1,
+            "Submit" => frame.submits += 1,
+            kind if kind.starts_with("Create") => {
+                frame.resources += 1;
+                if kind.ends_with("Pipeline") {
+                    frame.pipelines += 1;
+                }
+                let depth = nesting(line);
+                if depth > 0 {
+                    pending = Some(Pending {
+                        kind: kind.to_string(),
+                        depth,
+                        // Tuple variants, e.g. `CreateBuffer(Id(..), (` open the descriptor right away
+                        desc: (depth == 2).then_some(2),
+                        label: None,
+                    });
+                } else {
+                    *frame.created.entry(kind.to_string()).or_default() += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if frame.resources + frame.buffer_writes + frame.texture_writes + frame.submits > 0 {
+        summary.frames.push(frame);
+    }
+
+    summary
+}
+
+pub fn print_summary(summary: &Summary) {
+    let Some((first, rest)) = summary.frames.split_first() else {
+        println!("Empty trace");
+        return;
+    };
+
+    println!(
+        "{:>6} {:>10} {:>10} {:>14} {:>15} {:>8}",
+        "frame", "resources", "pipelines", "buffer writes", "texture writes", "submits"
+    );
+    let row = |name: &str, f: &FrameStats| {
+        println!(
+            "{name:>6} {:>10} {:>10} {:>14} {:>15} {:>8}",
+            f.resources, f.pipelines, f.buffer_writes, f.texture_writes, f.submits
+        )
+    };
+
+    // Setup ends up in the first frame, show it separately from the rest
+    row("0", first);
+    const SHOWN: usize = 10;
+    for (i, frame) in rest.iter().enumerate().take(SHOWN) {
+        row(&(i + 1).to_string(), frame);
+    }
+    if rest.len() > SHOWN {
+        println!("{:>6}", "...");
+    }
+
+    if rest.is_empty() {
+        return;
+    }
+
+    // After the first frame nothing should have to be created,
+    // unless something changed (resize, hot reload, ..)
+    let n = rest.len() as f32;
+    let mean = |f: fn(&FrameStats) -> usize| rest.iter().map(f).sum::<usize>() as f32 / n;
+    println!(
+        "\nPer frame after the first ({} frames): {:.1} resources, {:.1} pipelines, {:.1} buffer writes, {:.1} texture writes, {:.1} submits",
+        rest.len(),
+        mean(|f| f.resources),
+        mean(|f| f.pipelines),
+        mean(|f| f.buffer_writes),
+        mean(|f| f.texture_writes),
+        mean(|f| f.submits),
+    );
+
+    let mut created: BTreeMap<&str, usize> = BTreeMap::new();
+    for frame in rest {
+        for (what, count) in &frame.created {
+            *created.entry(what).or_default() += count;
+        }
+    }
+    let mut created: Vec<_> = created.into_iter().collect();
+    created.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    if !created.is_empty() {
+        println!("\nCreated after the first frame, per frame:");
+        for (what, count) in created.iter().take(20) {
+            println!("{:>8.1}  {what}", *count as f32 / n);
+        }
+    }
+}
+
+// `cargo run -- trace-summary <dir>`
+pub fn summarize_dir(dir: &Path) -> Result<(), String> {
+    let path = dir.join("trace.ron");
+    let trace =
+        std::fs::read_to_string(&path).map_err(|err| format!("can't read {path:?}: {err}"))?;
+    println!("Trace {path:?}");
+    print_summary(&summarize(&trace));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Excerpts of a headless trace (`bench --trace` on GL), with presents as a window would add them
+    const TRACE: &str = r#"[
+CreateTexture(Id(0, 1, Gl), (
+    label: Some("headless"),
+    size: (
+        width: 800,
+        height: 600,
+        depthOrArrayLayers: 1,
+    ),
+    mip_level_count: 1,
+    sample_count: 1,
+    dimension: r#2d,
+    format: "bgra8unorm-srgb",
+    usage: 17,
+    view_formats: [],
+)),
+CreateTextureView(
+    id: Id(0, 1, Gl),
+    parent_id: Id(0, 1, Gl),
+    desc: (
+        label: None,
+        format: None,
+        dimension: None,
+        range: (
+            aspect: all,
+            base_mip_level: 0,
+            mip_level_count: None,
+            base_array_layer: 0,
+            array_layer_count: None,
+        ),
+    ),
+),
+CreateShaderModule(
+    id: Id(0, 1, Gl),
+    desc: (
+        label: Some("ex02"),
+        shader_bound_checks: (
+            runtime_checks: true,
+        ),
+    ),
+    data: "data1.wgsl",
+),
+CreateRenderPipeline(
+    id: Id(0, 1, Gl),
+    desc: (
+        label: Some("ex02-rpd"),
+        layout: Some(Id(0, 1, Gl)),
+        vertex: (
+            stage: (
+                module: Id(0, 1, Gl),
+                entry_point: "vs",
+            ),
+            buffers: [
+                (
+                    arrayStride: 8,
+                    stepMode: vertex,
+                    attributes: [
+                        (
+                            format: float32x2,
+                            offset: 0,
+                            shaderLocation: 0,
+                        ),
+                    ],
+                ),
+            ],
+        ),
+        primitive: (
+            topology: r#triangle-list,
+            stripIndexFormat: None,
+            frontFace: ccw,
+            cullMode: None,
+            unclippedDepth: false,
+            polygonMode: fill,
+            conservative: false,
+        ),
+        depth_stencil: None,
+        multisample: (
+            count: 1,
+            mask: 18446744073709551615,
+            alphaToCoverageEnabled: false,
+        ),
+        fragment: Some((
+            stage: (
+                module: Id(0, 1, Gl),
+                entry_point: "fs",
+            ),
+            targets: [
+                Some((
+                    format: "bgra8unorm-srgb",
+                    blend: None,
+                    writeMask: 15,
+                )),
+            ],
+        )),
+        multiview: None,
+    ),
+    implicit_context: None,
+),
+CreateBuffer(Id(0, 1, Gl), (
+    label: Some("ex02-index-buf"),
+    size: 24,
+    usage: 40,
+    mapped_at_creation: false,
+)),
+WriteBuffer(
+    id: Id(0, 1, Gl),
+    data: "data2.bin",
+    range: (
+        start: 0,
+        end: 24,
+    ),
+    queued: true,
+),
+Submit(1, [
+    RunRenderPass(
+        base: (
+            label: Some("ex02-rp"),
+            commands: [
+                SetPipeline(Id(0, 1, Gl)),
+                SetVertexBuffer(
+                    slot: 0,
+                    buffer_id: Id(0, 1, Gl),
+                    offset: 0,
+                    size: None,
+                ),
+                SetBindGroup(
+                    index: 0,
+                    num_dynamic_offsets: 0,
+                    bind_group_id: Id(0, 1, Gl),
+                ),
+                InsertDebugMarker(
+                    color: 0,
+                    len: 13,
+                ),
+                Draw(
+                    vertex_count: 3,
+                    instance_count: 10,
+                    first_vertex: 0,
+                    first_instance: 0,
+                ),
+            ],
+            dynamic_offsets: [],
+            string_data: [
+                101,
+                120,
+                48,
+                50,
+                45,
+                114,
+                105,
+                110,
+                103,
+                32,
+                120,
+                49,
+                48,
+            ],
+            push_constant_data: [],
+        ),
+        target_colors: [
+            Some((
+                view: Id(0, 1, Gl),
+                resolve_target: None,
+                channel: (
+                    load_op: clear,
+                    store_op: store,
+                    clear_value: (
+                        r: 0.0,
+                        g: 0.0,
+                        b: 0.0,
+                        a: 0.0,
+                    ),
+                    read_only: false,
+                ),
+            )),
+        ],
+        target_depth_stencil: None,
+    ),
+]),
+Present(Id(0, 1, Gl)),
+CreateBuffer(Id(0, 2, Gl), (
+    label: Some("ex02-index-buf"),
+    size: 24,
+    usage: 40,
+    mapped_at_creation: false,
+)),
+WriteBuffer(
+    id: Id(0, 2, Gl),
+    data: "data7.bin",
+    range: (
+        start: 0,
+        end: 24,
+    ),
+    queued: true,
+),
+CreateTextureView(
+    id: Id(0, 1, Gl),
+    parent_id: Id(0, 1, Gl),
+    desc: (
+        label: None,
+        format: None,
+        dimension: None,
+        range: (
+            aspect: all,
+            base_mip_level: 0,
+            mip_level_count: None,
+            base_array_layer: 0,
+            array_layer_count: None,
+        ),
+    ),
+),
+Present(Id(0, 1, Gl)),
+DestroyBuffer(Id(0, 2, Gl)),
+]"#;
+
+    #[test]
+    fn frames_split_on_present() {
+        let summary = summarize(TRACE);
+        // The trailing destroy isn't anything we count
+        assert_eq!(summary.frames.len(), 2);
+
+        let [first, second] = &summary.frames[..] else {
+            unreachable!()
+        };
+        assert_eq!(first.resources, 5);
+        assert_eq!(first.pipelines, 1);
+        assert_eq!(first.buffer_writes, 1);
+        assert_eq!(first.submits, 1);
+
+        assert_eq!(second.resources, 2);
+        assert_eq!(second.pipelines, 0);
+        assert_eq!(second.buffer_writes, 1);
+    }
+
+    #[test]
+    fn created_by_label() {
+        let summary = summarize(TRACE);
+        let [first, second] = &summary.frames[..] else {
+            unreachable!()
+        };
+        // Tuple variants
+        assert_eq!(first.created[r#"CreateTexture "headless""#], 1);
+        assert_eq!(second.created[r#"CreateBuffer "ex02-index-buf""#], 1);
+        // Struct variants, labelled in `desc`
+        assert_eq!(first.created[r#"CreateRenderPipeline "ex02-rpd""#], 1);
+        assert_eq!(first.created[r#"CreateShaderModule "ex02""#], 1);
+        assert_eq!(second.created["CreateTextureView (no label)"], 1);
+        // Labels inside submits aren't resources
+        assert!(!first.created.keys().any(|k| k.contains("ex02-rp\"")));
+    }
+
+    #[test]
+    fn parentheses_in_labels() {
+        let trace = r#"CreateComputePipeline(
+    id: Id(0, 1, Gl),
+    desc: (
+        label: Some("ex09-cp (8x8)"),
+        layout: Some(Id(0, 1, Gl)),
+    ),
+    implicit_context: None,
+),
+CreateBuffer(Id(0, 1, Gl), (
+    label: Some(":)"),
+)),"#;
+        let created = &summarize(trace).frames[0].created;
+        assert_eq!(created[r#"CreateComputePipeline "ex09-cp (8x8)""#], 1);
+        assert_eq!(created[r#"CreateBuffer ":)""#], 1);
+    }
+}
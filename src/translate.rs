@@ -0,0 +1,389 @@
+/*
+What naga (wgpu's shader compiler) makes of a WGSL file: its IR, and the code for each backend.
+F7 (or `shader-dump` in the console) writes the active example's shader to `shader-dump/<example>/`:
+    - source.wgsl, module.ir.txt and module.info.txt: the source, naga's IR and what validation found out
+    - module.spv (for `spirv-dis`) and module.spvasm: SPIR-V for Vulkan, with a rough disassembly
+    - <entry point>.vert.glsl, .frag.glsl, .comp.glsl: GLSL ES for GL, one per entry point
+    - module.hlsl and module.metal: HLSL for DX12 and MSL for Metal
+    - error.txt, or <name>.<backend>.error.txt when validating or translating failed
+After that it's dumped again on every hot reload, to watch how WGSL edits change the generated code.
+Also used by `shader_check.rs` to check every shader on every backend.
+
+Options are roughly what wgpu's backends use, the real ones depend on the pipeline layout and the adapter.
+
+Things we learned:
+    - wgpu turns device features into naga capabilities when creating a shader module,
+        e.g. `PUSH_CONSTANTS` into `PUSH_CONSTANT`. Without them ex03 and ex08 don't validate.
+    - Validation passing doesn't mean every backend can translate it, e.g. GLSL has no binding arrays.
+        Some backends panic (`unreachable!`) instead of returning an error.
+    - HLSL and MSL need the size of an unsized `binding_array`, wgpu passes it on from the pipeline layout.
+        HLSL also needs to be told where push constants go, and MSL binding arrays need Metal 2.
+    - GLSL is written per entry point (it has one `main`), the other backends do the whole module at once.
+    - naga has no SPIR-V disassembler, but the `spirv` crate it uses knows the opcode names.
+ */
+use std::{panic::AssertUnwindSafe, path::Path};
+
+use naga::{
+    back::{glsl, hlsl, msl, spv},
+    valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+    ArraySize, Module, ResourceBinding, ShaderStage, TypeInner,
+};
+use num_traits::FromPrimitive;
+use wgpu::Features;
+
+use crate::{fault, util::read_shader};
+
+// Like wgpu-core does when creating a shader module
+fn capabilities(features: Features) -> Capabilities {
+    let mut caps = Capabilities::empty();
+    caps.set(
+        Capabilities::PUSH_CONSTANT,
+        features.contains(Features::PUSH_CONSTANTS),
+    );
+    caps.set(
+        Capabilities::FLOAT64,
+        features.contains(Features::SHADER_F64),
+    );
+    caps.set(
+        Capabilities::PRIMITIVE_INDEX,
+        features.contains(Features::SHADER_PRIMITIVE_INDEX),
+    );
+    let non_uniform =
+        features.contains(Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING);
+    caps.set(
+        Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+        non_uniform,
+    );
+    caps.set(Capabilities::SAMPLER_NON_UNIFORM_INDEXING, non_uniform);
+    caps.set(
+        Capabilities::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+        features.contains(Features::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING),
+    );
+    caps.set(
+        Capabilities::STORAGE_TEXTURE_16BIT_NORM_FORMATS,
+        features.contains(Features::TEXTURE_FORMAT_16BIT_NORM),
+    );
+    caps.set(
+        Capabilities::MULTIVIEW,
+        features.contains(Features::MULTIVIEW),
+    );
+    caps.set(
+        Capabilities::EARLY_DEPTH_TEST,
+        features.contains(Features::SHADER_EARLY_DEPTH_TEST),
+    );
+    caps
+}
+
+// Parse and validate like a device with these features would. Errors point into `source`.
+pub fn validate(
+    name: &str,
+    source: &str,
+    features: Features,
+) -> Result<(Module, ModuleInfo), String> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|err| err.emit_to_string_with_path(source, name))?;
+    let info = Validator::new(ValidationFlags::all(), capabilities(features))
+        .validate(&module)
+        .map_err(|err| err.emit_to_string_with_path(source, name))?;
+    Ok((module, info))
+}
+
+// Some backends panic rather than return an error
+fn guard<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    std::panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|panic| Err(format!("panic: {}", fault::panic_message(panic.as_ref()))))
+}
+
+// wgpu sizes unsized binding arrays from the pipeline layout, like example 08's textures
+const BINDING_ARRAY_SIZE: u32 = 256;
+
+// The unsized binding arrays, and what's in them
+fn binding_arrays(module: &Module) -> Vec<(ResourceBinding, &TypeInner)> {
+    module
+        .global_variables
+        .iter()
+        .filter_map(|(_, global)| {
+            let TypeInner::BindingArray {
+                base,
+                size: ArraySize::Dynamic,
+            } = module.types[global.ty].inner
+            else {
+                return None;
+            };
+            Some((global.binding.clone()?, &module.types[base].inner))
+        })
+        .collect()
+}
+
+// A rough disassembly: opcode names and operands, with the strings (names, entry points) decoded.
+// `spirv-dis module.spv` gives a proper one.
+fn disassemble(words: &[u32]) -> String {
+    let mut out = String::new();
+    if let [_, version, generator, bound, _, ..] = words {
+        out += &format!(
+            "; SPIR-V {}.{}, generator {generator:#x}, bound {bound}\n",
+            (version >> 16) & 0xff,
+            (version >> 8) & 0xff
+        );
+    }
+    let mut rest = words.get(5..).unwrap_or_default();
+    while let Some(&first) = rest.first() {
+        let (count, opcode) = ((first >> 16) as usize, first & 0xffff);
+        if count == 0 || count > rest.len() {
+            out += "; truncated\n";
+            break;
+        }
+        let operands = &rest[1..count];
+        rest = &rest[count..];
+
+        let op = spirv::Op::from_u32(opcode);
+        // Where the string operand is, for the ops which have one
+        let string_at = match op {
+            Some(spirv::Op::Extension | spirv::Op::SourceExtension) => Some(0),
+            Some(spirv::Op::Name | spirv::Op::ExtInstImport | spirv::Op::String) => Some(1),
+            Some(spirv::Op::MemberName | spirv::Op::EntryPoint) => Some(2),
+            _ => None,
+        };
+        match op {
+            Some(op) => out += &format!("Op{op:?}"),
+            None => out += &format!("Op{opcode}"),
+        }
+        let mut i = 0;
+        while i < operands.len() {
+            if Some(i) == string_at {
+                let bytes: Vec<u8> = operands[i..]
+                    .iter()
+                    .flat_map(|word| word.to_le_bytes())
+                    .take_while(|&b| b != 0)
+                    .collect();
+                out += &format!(" {:?}", String::from_utf8_lossy(&bytes));
+                // Nul terminated, padded to a word
+                i += bytes.len() / 4 + 1;
+            } else {
+                out += &format!(" {}", operands[i]);
+                i += 1;
+            }
+        }
+        out += "\n";
+    }
+    out
+}
+
+// One backend's code, or why there is none
+pub struct Translation {
+    // "spv", "glsl", "hlsl" or "msl"
+    pub backend: &'static str,
+    // "module", or the entry point for GLSL
+    pub name: String,
+    // By file extension
+    pub result: Result<Vec<(&'static str, Vec<u8>)>, String>,
+}
+
+// Translate for each backend
+pub fn translate(module: &Module, info: &ModuleInfo) -> Vec<Translation> {
+    let mut translations = vec![];
+
+    translations.push(Translation {
+        backend: "spv",
+        name: "module".to_string(),
+        result: guard(|| {
+            let words = spv::write_vec(module, info, &spv::Options::default(), None)
+                .map_err(|err| err.to_string())?;
+            Ok(vec![
+                ("spv", bytemuck::cast_slice(&words).to_vec()),
+                ("spvasm", disassemble(&words).into_bytes()),
+            ])
+        }),
+    });
+
+    // What wgpu's GL backend targets
+    let options = glsl::Options {
+        version: glsl::Version::Embedded {
+            version: 310,
+            is_webgl: false,
+        },
+        ..Default::default()
+    };
+    for entry_point in &module.entry_points {
+        let pipeline_options = glsl::PipelineOptions {
+            shader_stage: entry_point.stage,
+            entry_point: entry_point.name.clone(),
+            multiview: None,
+        };
+        let extension = match entry_point.stage {
+            ShaderStage::Vertex => "vert.glsl",
+            ShaderStage::Fragment => "frag.glsl",
+            ShaderStage::Compute => "comp.glsl",
+        };
+        translations.push(Translation {
+            backend: "glsl",
+            name: entry_point.name.clone(),
+            result: guard(|| {
+                let mut out = String::new();
+                glsl::Writer::new(
+                    &mut out,
+                    module,
+                    info,
+                    &options,
+                    &pipeline_options,
+                    Default::default(),
+                )
+                .and_then(|mut writer| writer.write())
+                .map_err(|err| err.to_string())?;
+                Ok(vec![(extension, out.into_bytes())])
+            }),
+        });
+    }
+
+    // wgpu's DX12 backend puts push constants in a root constant like this
+    let options = hlsl::Options {
+        push_constants_target: Some(hlsl::BindTarget {
+            space: 0,
+            register: 0,
+            binding_array_size: None,
+        }),
+        binding_map: binding_arrays(module)
+            .into_iter()
+            .map(|(binding, _)| {
+                let target = hlsl::BindTarget {
+                    space: binding.group as u8,
+                    register: binding.binding,
+                    binding_array_size: Some(BINDING_ARRAY_SIZE),
+                };
+                (binding, target)
+            })
+            .collect(),
+        ..Default::default()
+    };
+    translations.push(Translation {
+        backend: "hlsl",
+        name: "module".to_string(),
+        result: guard(|| {
+            let mut out = String::new();
+            hlsl::Writer::new(&mut out, &options)
+                .write(module, info)
+                .map_err(|err| err.to_string())?;
+            Ok(vec![("hlsl", out.into_bytes())])
+        }),
+    });
+
+    // Binding arrays need Metal 2
+    let resources = msl::EntryPointResources {
+        resources: binding_arrays(module)
+            .into_iter()
+            .map(|(binding, base)| {
+                let slot = Some(binding.binding as u8);
+                let target = msl::BindTarget {
+                    binding_array_size: Some(BINDING_ARRAY_SIZE),
+                    ..match base {
+                        TypeInner::Image { .. } => msl::BindTarget {
+                            texture: slot,
+                            ..Default::default()
+                        },
+                        TypeInner::Sampler { .. } => msl::BindTarget {
+                            sampler: slot.map(msl::BindSamplerTarget::Resource),
+                            ..Default::default()
+                        },
+                        _ => msl::BindTarget {
+                            buffer: slot,
+                            ..Default::default()
+                        },
+                    }
+                };
+                (binding, target)
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let options = msl::Options {
+        lang_version: (2, 0),
+        per_entry_point_map: module
+            .entry_points
+            .iter()
+            .map(|entry_point| (entry_point.name.clone(), resources.clone()))
+            .collect(),
+        ..Default::default()
+    };
+    translations.push(Translation {
+        backend: "msl",
+        name: "module".to_string(),
+        result: guard(|| {
+            let (out, _) =
+                msl::write_string(module, info, &options, &msl::PipelineOptions::default())
+                    .map_err(|err| err.to_string())?;
+            Ok(vec![("metal", out.into_bytes())])
+        }),
+    });
+
+    translations
+}
+
+fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|err| format!("{path:?}: {err}"))
+}
+
+// Write `shader_source` (a file in "src") translated for a device with these features to `shader-dump/<label>/`.
+// Says what was written.
+pub fn dump(label: &str, shader_source: &str, features: Features) -> Result<String, String> {
+    let dir = Path::new("shader-dump").join(label);
+    // Start over, such that nothing is left of e.g. a renamed entry point
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|err| format!("{dir:?}: {err}"))?;
+    }
+    std::fs::create_dir_all(&dir).map_err(|err| format!("{dir:?}: {err}"))?;
+
+    let source = read_shader(shader_source);
+    write(&dir.join("source.wgsl"), &source)?;
+    let (module, info) = match validate(shader_source, &source, features) {
+        Ok(valid) => valid,
+        Err(err) => {
+            write(&dir.join("error.txt"), &err)?;
+            return Err(format!("{shader_source} doesn't validate, see {dir:?}"));
+        }
+    };
+    write(&dir.join("module.ir.txt"), format!("{module:#?}"))?;
+    write(&dir.join("module.info.txt"), format!("{info:#?}"))?;
+
+    let mut failed = vec![];
+    for translation in translate(&module, &info) {
+        let name = translation.name;
+        match translation.result {
+            Ok(files) => {
+                for (extension, contents) in files {
+                    write(&dir.join(format!("{name}.{extension}")), contents)?;
+                }
+            }
+            Err(err) => {
+                let backend = translation.backend;
+                write(&dir.join(format!("{name}.{backend}.error.txt")), err)?;
+                failed.push(format!("{name} to {backend}"));
+            }
+        }
+    }
+
+    Ok(match failed[..] {
+        [] => format!("Dumped {label}'s shader to {dir:?}"),
+        _ => format!(
+            "Dumped {label}'s shader to {dir:?}, translating {} failed",
+            failed.join(", ")
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassembles() {
+        let source = "@compute @workgroup_size(1) fn main_cs() {}";
+        let (module, info) = validate("test.wgsl", source, Features::empty()).unwrap();
+        let words = spv::write_vec(&module, &info, &spv::Options::default(), None).unwrap();
+        let text = disassemble(&words);
+
+        assert!(text.starts_with("; SPIR-V 1."), "{text}");
+        assert!(text.contains("OpEntryPoint 5 "), "{text}");
+        assert!(text.contains(" \"main_cs\""), "{text}");
+        assert!(text.contains("OpFunctionEnd\n"), "{text}");
+    }
+}
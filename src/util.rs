@@ -1,6 +1,16 @@
-use std::{borrow::Cow, path::PathBuf, time::Duration};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use wgpu::{Device, PolygonMode, ShaderModule, ShaderModuleDescriptor, TextureFormat};
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, CommandEncoderDescriptor, Device,
+    Extent3d, ImageCopyBuffer, ImageDataLayout, Maintain, MapMode, PolygonMode, Queue,
+    ShaderModule, ShaderModuleDescriptor, Texture, TextureFormat, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use crate::params::Params;
 
 /// Comman state examples should have
 /// TODO: Mark dirty?
@@ -21,23 +31,60 @@ pub struct ExampleCommonState {
 
     // Which frame # is going to be rendered
     pub frame: u64,
+
+    // Named knobs, set up by the registry from the example's declared parameters
+    pub params: Params,
+}
+
+// What an example builds for its polygon mode (e.g. pipelines), kept for each mode it was built for.
+// The x-ray lens renders the example in two modes every frame, without rebuilding anything this way.
+// `clear` when dirty.
+pub struct PerPolygonMode<T>(Vec<(PolygonMode, T)>);
+
+impl<T> Default for PerPolygonMode<T> {
+    fn default() -> Self {
+        Self(vec![])
+    }
+}
+
+impl<T> PerPolygonMode<T> {
+    pub fn get(&self, mode: PolygonMode) -> Option<&T> {
+        self.0
+            .iter()
+            .find(|(m, _)| *m == mode)
+            .map(|(_, built)| built)
+    }
+
+    pub fn insert(&mut self, mode: PolygonMode, built: T) {
+        self.0.retain(|(m, _)| *m != mode);
+        self.0.push((mode, built));
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+// Read a wgsl file in the "src" dir.
+// E.g. a valid `shader_source` arg would be "ex01.wgsl".
+pub fn read_shader(shader_source: &str) -> String {
+    let mut path = PathBuf::new();
+    path.push(concat!(env!("CARGO_MANIFEST_DIR"), "/src"));
+    path.push(shader_source);
+    println!("Loading shader at {path:?}");
+
+    std::fs::read_to_string(path).unwrap()
 }
 
 // Create a shader module from a wgsl file in the "src" dir.
-// E.g. a valid `wgsl` arg would be "ex01.wgsl".
-fn shader_module<'l>(
+fn shader_module(
     device: &Device,
     shader_source: &'static str,
     label: &'static str,
 ) -> ShaderModule {
-    let mut path = PathBuf::new();
-    path.push(concat!(env!("CARGO_MANIFEST_DIR"), "/src"));
-    path.push(&shader_source);
-    println!("Loading shader at {path:?}");
-
     device.create_shader_module(ShaderModuleDescriptor {
         label: label.into(),
-        source: wgpu::ShaderSource::Wgsl(Cow::Owned(std::fs::read_to_string(path).unwrap())),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(read_shader(shader_source))),
     })
 }
 
@@ -57,6 +104,7 @@ impl ExampleCommonState {
             dirty: true,
             time: Duration::from_secs(0),
             frame: 0,
+            params: Params::default(),
         }
     }
 
@@ -76,3 +124,140 @@ impl ExampleCommonState {
         self.frame
     }
 }
+
+// A texture's bytes per row, tightly packed and padded as copies into buffers need them
+pub fn bytes_per_row(texture: &Texture) -> (u32, u32) {
+    let bytes_per_pixel = texture
+        .format()
+        .block_size(None)
+        .expect("should be a color format");
+    let unpadded_bytes_per_row = texture.width() * bytes_per_pixel;
+    // Copies need rows aligned to 256 bytes
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+        * COPY_BYTES_PER_ROW_ALIGNMENT;
+    (unpadded_bytes_per_row, padded_bytes_per_row)
+}
+
+// Copy a (2D, single mip) texture into a buffer with `bytes_per_row`'s padded rows
+pub fn copy_texture_to_buffer(ce: &mut CommandEncoder, texture: &Texture, buffer: &Buffer) {
+    let (_, padded_bytes_per_row) = bytes_per_row(texture);
+    ce.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+}
+
+// The rows of a mapped buffer without their padding
+pub fn unpad_rows(data: &[u8], (unpadded, padded): (u32, u32)) -> Vec<u8> {
+    data.chunks(padded as usize)
+        .flat_map(|row| &row[..unpadded as usize])
+        .copied()
+        .collect()
+}
+
+// Copy a (2D, single mip) texture back to the CPU and wait for it.
+// The rows are tightly packed in the result, i.e. without the padding the copy needs.
+// The texture needs `TextureUsages::COPY_SRC`.
+pub fn read_texture(device: &Device, queue: &Queue, texture: &Texture) -> Vec<u8> {
+    let size = texture.size();
+    let (unpadded_bytes_per_row, padded_bytes_per_row) = bytes_per_row(texture);
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: "readback".into(),
+        size: (padded_bytes_per_row * size.height) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut ce = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: "readback-ce".into(),
+    });
+    copy_texture_to_buffer(&mut ce, texture, &buffer);
+    queue.submit(std::iter::once(ce.finish()));
+
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, |_| {});
+    device.poll(Maintain::Wait);
+
+    let data = unpad_rows(
+        &slice.get_mapped_range(),
+        (unpadded_bytes_per_row, padded_bytes_per_row),
+    );
+    buffer.unmap();
+
+    data
+}
+
+// 8 bit RGBA from tightly packed RGBA or BGRA pixels (e.g. from `read_texture`)
+pub fn to_rgba(format: TextureFormat, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => Ok(bytes.to_vec()),
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => Ok(bytes
+            .chunks_exact(4)
+            .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+            .collect()),
+        format => Err(format!("can't save {format:?} as an image")),
+    }
+}
+
+// Encode tightly packed 8 bit RGBA or BGRA pixels as a PNG
+pub fn encode_png(size: Extent3d, format: TextureFormat, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let rgba = to_rgba(format, bytes)?;
+
+    let mut png = vec![];
+    let mut encoder = png::Encoder::new(&mut png, size.width, size.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgba))
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+// Save tightly packed 8 bit RGBA or BGRA pixels as a PNG file
+pub fn save_png(
+    path: &Path,
+    size: Extent3d,
+    format: TextureFormat,
+    bytes: &[u8],
+) -> Result<(), String> {
+    let png = encode_png(size, format, bytes)?;
+    std::fs::write(path, png).map_err(|e| e.to_string())
+}
+
+// The current UTC time as `YYYYMMDD-HHMMSS`, e.g. for naming output directories
+pub fn timestamp() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,332 @@
+/*
+Goals:
+    - From the README ideas: Scissor rect around cursor, two passes, one shows fill polygon mode,
+        other shows something else. Would make something like an x-ray effect?
+    - Should work with any example, so the lens wraps whichever example is active
+
+Things we learned:
+    - Examples can't draw in two polygon modes at once, so the wrapped example renders the scene twice into offscreen
+        textures, and the lens composites those onto the screen.
+    - Flipping the polygon mode every frame must not go through `dirty`: The wrapped example would recreate its
+        pipeline(s) twice a frame, and examples which reset things when dirty (e.g. example 07 clears its canvas)
+        would lose that state under the lens. Examples keep their pipelines per polygon mode instead (`PerPolygonMode`).
+    - The scissor rect alone gives a rectangle lens, a circle needs a `discard` in the fragment shader.
+    - `textureSample` must be in uniform control flow, so sample before discarding.
+ */
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, Buffer, BufferUsages, CommandEncoderDescriptor, Extent3d, FragmentState,
+    MultisampleState, Operations, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
+    PushConstantRange, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerDescriptor, ShaderStages, Texture, TextureDescriptor,
+    TextureDimension, TextureUsages, TextureView, TextureViewDescriptor, VertexState,
+};
+
+use crate::{debug, util::ExampleCommonState, Example, ExampleData};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LensShape {
+    Rectangle,
+    Circle,
+}
+
+// What to show inside the lens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LensMode {
+    Line,
+    Point,
+    // Luminance heatmap of the filled scene
+    Debug,
+}
+
+impl LensMode {
+    fn next(self) -> Self {
+        match self {
+            LensMode::Line => LensMode::Point,
+            LensMode::Point => LensMode::Debug,
+            LensMode::Debug => LensMode::Line,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct LensUniform {
+    center: [f32; 2],
+    radius: f32,
+    shape: u32,
+    debug: u32,
+    _pad: u32,
+}
+
+pub struct XrayLens {
+    common: ExampleCommonState,
+    render_pipeline: Option<RenderPipeline>,
+    bgl0: BindGroupLayout,
+    sampler: Sampler,
+    lens_buf: Buffer,
+
+    // The wrapped example renders its filled scene here
+    fill: Texture,
+    // And here in line/point mode
+    xray: Texture,
+
+    shape: LensShape,
+    mode: LensMode,
+    // Half size of the lens, in pixels
+    radius: f32,
+}
+
+impl XrayLens {
+    pub fn new(e: &ExampleData) -> Self {
+        let common = ExampleCommonState::new(&e.device, e.swapchain_format, "xray.wgsl", "xray");
+
+        let bgl0 = e
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: "xray-bgl0".into(),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let lens_buf = e.device.create_buffer_init(&BufferInitDescriptor {
+            label: "xray-uni-lens".into(),
+            contents: bytemuck::bytes_of(&LensUniform::zeroed()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        Self {
+            common,
+            render_pipeline: None,
+            bgl0,
+            sampler: e.device.create_sampler(&SamplerDescriptor::default()),
+            lens_buf,
+            fill: scene_texture(e, "xray-fill", e.extent_3d()),
+            xray: scene_texture(e, "xray-xray", e.extent_3d()),
+            shape: LensShape::Rectangle,
+            mode: LensMode::Line,
+            radius: 100.,
+        }
+    }
+
+    pub fn common(&mut self) -> &mut ExampleCommonState {
+        &mut self.common
+    }
+
+    pub fn toggle_shape(&mut self) {
+        self.shape = match self.shape {
+            LensShape::Rectangle => LensShape::Circle,
+            LensShape::Circle => LensShape::Rectangle,
+        };
+        println!("Lens shape: {:?}", self.shape);
+    }
+
+    pub fn next_mode(&mut self) {
+        self.mode = self.mode.next();
+        println!("Lens mode: {:?}", self.mode);
+    }
+
+    pub fn handle_scroll(&mut self, scroll_up: bool) {
+        if scroll_up {
+            self.radius = (self.radius * 1.1).min(2000.);
+        } else {
+            self.radius = (self.radius / 1.1).max(10.);
+        }
+    }
+
+    fn make_render_pipeline(&self, e: &ExampleData) -> RenderPipeline {
+        e.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: "xray-rpassd".into(),
+            layout: Some(&e.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: "xray-rpass-pld".into(),
+                bind_group_layouts: &[&self.bgl0],
+                push_constant_ranges: &[PushConstantRange {
+                    stages: ShaderStages::FRAGMENT,
+                    range: 0..4,
+                }],
+            })),
+            vertex: VertexState {
+                module: &self.common.shader_module,
+                entry_point: "vs",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &self.common.shader_module,
+                entry_point: "fs",
+                targets: &[Some(e.swapchain_format.into())],
+            }),
+            primitive: PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // Always fill, the polygon mode is for the wrapped example
+                polygon_mode: PolygonMode::Fill,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn bind_group(&self, e: &ExampleData, view: &TextureView) -> BindGroup {
+        e.device.create_bind_group(&BindGroupDescriptor {
+            label: "xray-bg0".into(),
+            layout: &self.bgl0,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: self.lens_buf.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    // Render the given example through the lens into the target.
+    pub fn render(&mut self, ex: &mut dyn Example, e: &ExampleData, target: &TextureView) {
+        if self.common.dirty || self.render_pipeline.is_none() {
+            self.common.dirty = false;
+            self.render_pipeline = Some(self.make_render_pipeline(e));
+        }
+
+        // Handle resize
+        if self.fill.size() != e.extent_3d() {
+            self.fill = scene_texture(e, "xray-fill", e.extent_3d());
+            self.xray = scene_texture(e, "xray-xray", e.extent_3d());
+        }
+
+        let fill_view = self.fill.create_view(&TextureViewDescriptor::default());
+        let xray_view = self.xray.create_view(&TextureViewDescriptor::default());
+
+        // Let the example render its scene into our textures
+        let own_mode = ex.common().polygon_mode;
+        render_with_mode(ex, e, &fill_view, PolygonMode::Fill);
+        match self.mode {
+            LensMode::Line => render_with_mode(ex, e, &xray_view, PolygonMode::Line),
+            LensMode::Point => render_with_mode(ex, e, &xray_view, PolygonMode::Point),
+            // Uses the filled scene
+            LensMode::Debug => {}
+        }
+        set_polygon_mode(ex, own_mode);
+
+        let [mx, my] = e.mouse;
+        e.queue.write_buffer(
+            &self.lens_buf,
+            0,
+            bytemuck::bytes_of(&LensUniform {
+                center: [mx, my],
+                radius: self.radius,
+                shape: (self.shape == LensShape::Circle) as u32,
+                debug: (self.mode == LensMode::Debug) as u32,
+                _pad: 0,
+            }),
+        );
+
+        let bg_fill = self.bind_group(e, &fill_view);
+        let bg_lens = match self.mode {
+            LensMode::Debug => self.bind_group(e, &fill_view),
+            _ => self.bind_group(e, &xray_view),
+        };
+
+        // The lens rect, clamped to the target
+        let Extent3d { width, height, .. } = e.extent_3d();
+        let x0 = (mx - self.radius).clamp(0., width as f32) as u32;
+        let y0 = (my - self.radius).clamp(0., height as f32) as u32;
+        let x1 = (mx + self.radius).clamp(0., width as f32) as u32;
+        let y1 = (my + self.radius).clamp(0., height as f32) as u32;
+
+        let mut ce = e.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: "xray-ce".into(),
+        });
+
+        {
+            let mut rpass = ce.begin_render_pass(&RenderPassDescriptor {
+                label: "xray-rp".into(),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(self.render_pipeline.as_ref().unwrap());
+
+            // Filled everywhere
+            debug::marker(&mut rpass, "xray-fill");
+            rpass.set_bind_group(0, &bg_fill, &[]);
+            rpass.set_push_constants(ShaderStages::FRAGMENT, 0, 0u32.to_le_bytes().as_ref());
+            rpass.draw(0..3, 0..1);
+
+            // Then the lens on top
+            if x1 > x0 && y1 > y0 {
+                debug::marker(&mut rpass, "xray-lens");
+                rpass.set_scissor_rect(x0, y0, x1 - x0, y1 - y0);
+                rpass.set_bind_group(0, &bg_lens, &[]);
+                rpass.set_push_constants(ShaderStages::FRAGMENT, 0, 1u32.to_le_bytes().as_ref());
+                rpass.draw(0..3, 0..1);
+            }
+        }
+
+        e.queue.submit(std::iter::once(ce.finish()));
+    }
+}
+
+// Not marking the example dirty, it has pipelines for each mode once they're built
+fn set_polygon_mode(ex: &mut dyn Example, mode: PolygonMode) {
+    ex.common().polygon_mode = mode;
+}
+
+fn render_with_mode(ex: &mut dyn Example, e: &ExampleData, view: &TextureView, mode: PolygonMode) {
+    let _scope = debug::scope(&format!("xray-scene-{mode:?}"));
+    set_polygon_mode(ex, mode);
+    ex.render(e, view);
+}
+
+// Same size and format as the swapchain, such that examples can render into it
+fn scene_texture(e: &ExampleData, label: &str, size: Extent3d) -> Texture {
+    e.device.create_texture(&TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: e.swapchain_format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
@@ -0,0 +1,83 @@
+struct Lens {
+    // Cursor in framebuffer pixels
+    center: vec2<f32>,
+    // Half the width/height of the lens, in pixels
+    radius: f32,
+    // 0: rectangle, 1: circle
+    shape: u32,
+    // 0: show the x-ray scene, 1: debug view of the filled scene
+    debug: u32,
+}
+
+@group(0)
+@binding(0)
+var t_scene: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var s_sampler: sampler;
+
+@group(0)
+@binding(2)
+var<uniform> u_lens: Lens;
+
+// 0: drawing the whole screen, 1: drawing the lens
+var<push_constant> is_lens: u32;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Full screen triangle, draw 3
+@vertex
+fn vs(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.position = vec4<f32>(uv * vec2<f32>(2., -2.) + vec2<f32>(-1., 1.), 0., 1.);
+    out.uv = uv;
+
+    return out;
+}
+
+@fragment
+fn fs(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Sample before any discard to stay in uniform control flow
+    let scene = textureSample(t_scene, s_sampler, input.uv);
+
+    if (is_lens == 0u) {
+        return scene;
+    }
+
+    // The scissor rect already cuts out the rectangle, circles need a bit more work
+    let d = input.position.xy - u_lens.center;
+    let l = length(d);
+    if (u_lens.shape == 1u && l > u_lens.radius) {
+        discard;
+    }
+
+    // How far from the edge of the lens, in pixels
+    var edge = u_lens.radius - max(abs(d.x), abs(d.y));
+    if (u_lens.shape == 1u) {
+        edge = u_lens.radius - l;
+    }
+    if (edge < 2.) {
+        return vec4<f32>(1.);
+    }
+
+    if (u_lens.debug == 0u) {
+        return scene;
+    }
+
+    // Debug view: Luminance as a heatmap, with a grid every 16 pixels
+    let lum = dot(scene.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    var col = vec3<f32>(saturate(lum * 2.), saturate(1. - abs(lum * 2. - 1.)), saturate(1. - lum * 2.));
+
+    let grid = input.position.xy % 16.;
+    if (grid.x < 1. || grid.y < 1.) {
+        col *= 0.5;
+    }
+
+    return vec4<f32>(col, 1.);
+}